@@ -1,7 +1,10 @@
 use super::game_agent;
 use super::telegram::Telegram;
 use super::*;
+use base64::prelude::*;
+use cardgames::i18n::{Language, Text};
 use cardgames::primitives::Game;
+use cardgames::text;
 use std::collections::HashMap;
 
 fn add_player_to_game(
@@ -10,14 +13,32 @@ fn add_player_to_game(
     player_games: &mut HashMap<telegram_bot_raw::types::refs::UserId, String>,
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     from: telegram_bot_raw::types::chat::User,
+    languages: &primitives::Languages,
 ) {
-    client.send_message(("Provo ad aggiungerti alla partita...", from.id).into());
+    let language = utils::language_of(languages, from.id.into());
+    client.send_message(
+        (
+            Text::new("Provo ad aggiungerti alla partita..."),
+            from.id,
+            language,
+        )
+            .into(),
+    );
     // Check wether the user is already playing a game
     if player_games.contains_key(&from.id) {
         // The user can't join two games at the same time
-        client.send_message(("Non puoi unirti a più partite contemporaneamente", from.id).into());
+        client.send_message(
+            (
+                Text::new("Non puoi unirti a più partite contemporaneamente"),
+                from.id,
+                language,
+            )
+                .into(),
+        );
     } else if let Some(ch) = game_channel.get(&game_id) {
-        player_games.insert(from.id, game_id.clone());
+        player_games
+            .entry(from.id)
+            .or_insert_with(|| game_id.clone());
         ch.send(threading::ThreadMessage::AddPlayer(
             cardgames::primitives::Player {
                 id: from.id.into(),
@@ -26,7 +47,8 @@ fn add_player_to_game(
         ))
         .unwrap();
     } else {
-        client.send_message(("Gioco non trovato!", from.id).into());
+        let language = utils::language_of(languages, from.id.into());
+        client.send_message((Text::new("Gioco non trovato!"), from.id, language).into());
     }
 }
 
@@ -34,11 +56,16 @@ fn handle_string_message(
     game_id: &String,
     client: &Telegram,
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
+    game_last_played: &mut HashMap<String, std::time::Instant>,
     from: telegram_bot_raw::types::chat::User,
     text: String,
+    languages: &primitives::Languages,
 ) {
     // Check wether the user is already playing a game
     if let Some(ch) = game_channel.get(game_id) {
+        if let Some(inst) = game_last_played.get_mut(game_id) {
+            *inst = std::time::Instant::now();
+        }
         ch.send(threading::ThreadMessage::HandleStringMessage(
             cardgames::primitives::Player {
                 id: from.id.into(),
@@ -48,18 +75,21 @@ fn handle_string_message(
         ))
         .unwrap();
     } else {
-        client.send_message(("Gioco non trovato!", from.id).into());
+        let language = utils::language_of(languages, from.id.into());
+        client.send_message((Text::new("Gioco non trovato!"), from.id, language).into());
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn init_game(
     game_index: usize,
     from: telegram_bot_raw::types::chat::User,
-    playable_games: &Vec<Box<dyn Game>>,
+    playable_games: &[Box<dyn Game>],
     player_games: &mut HashMap<telegram_bot_raw::types::refs::UserId, String>,
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     game_last_played: &mut HashMap<String, std::time::Instant>,
     client: &mut Telegram,
+    languages: &primitives::Languages,
 ) {
     use threading::ThreadMessage;
     let game_id = ulid::Ulid::new().to_string();
@@ -75,16 +105,100 @@ fn init_game(
     game_last_played.insert(game_id.clone(), std::time::Instant::now());
     client.send_message(
         (
-            format!(
+            text!(
                 "Per invitare altre persone condividi questo link: https://t.me/{}?start={}",
-                &client.username, game_id
+                client.username,
+                game_id
             ),
             from.id,
+            utils::language_of(languages, from.id.into()),
         )
             .into(),
     );
     let game_tg_client = client.clone();
-    game_agent::new_agent(game_tg_client, game_index, playable_games, receiver);
+    let mut game = playable_games[game_index].get_new_instance();
+    game.init();
+    game_agent::new_agent(
+        game_tg_client,
+        game,
+        game_id,
+        receiver,
+        languages.clone(),
+        HashMap::new(),
+    );
+}
+
+/// Restarts the games whose snapshot has been saved before the bot was stopped
+pub fn restore_games(
+    player_games: &mut HashMap<telegram_bot_raw::types::refs::UserId, String>,
+    game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
+    game_last_played: &mut HashMap<String, std::time::Instant>,
+    client: &Telegram,
+    languages: &primitives::Languages,
+) {
+    let dir = match std::env::var(SNAPSHOTS_DIR_VAR) {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+    // Without the directory the bot starts anyway, with no games to restore
+    let entries = match std::fs::create_dir_all(&dir).and_then(|_| std::fs::read_dir(&dir)) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Could not restore the games from {}: {}", dir, e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let game_id = match path.file_stem().and_then(|x| x.to_str()) {
+            Some(x) if path.extension().map(|y| y == "json").unwrap_or(false) => x.to_owned(),
+            _ => continue,
+        };
+        let game = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|x| serde_json::from_str::<game_agent::SavedGame>(&x).ok())
+            .ok_or_else(|| "Salvataggio illeggibile".to_owned())
+            .and_then(|x| {
+                let ai_levels = x.ai_levels;
+                x.snapshot
+                    .restore()
+                    .map(|game| (game, ai_levels))
+                    .map_err(|e| e.to_string())
+            });
+        let (game, ai_levels) = match game {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Could not restore {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let (sender, receiver) = mpsc::sync_channel(10);
+        for player in game
+            .get_players()
+            .into_iter()
+            .filter(|x| !utils::is_ai(x.id))
+        {
+            player_games.insert(player.id.into(), game_id.clone());
+            client.send_message(
+                (
+                    Text::new("Il bot è stato riavviato, puoi continuare la tua partita"),
+                    player.id.into(),
+                    utils::language_of(languages, player.id),
+                )
+                    .into(),
+            );
+        }
+        game_channel.insert(game_id.clone(), sender);
+        game_last_played.insert(game_id.clone(), std::time::Instant::now());
+        game_agent::new_agent(
+            client.clone(),
+            game,
+            game_id,
+            receiver,
+            languages.clone(),
+            ai_levels,
+        );
+    }
 }
 
 fn try_start_game(
@@ -93,6 +207,7 @@ fn try_start_game(
     game_last_played: &mut HashMap<String, std::time::Instant>,
     game_channel: &HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     client: &Telegram,
+    languages: &primitives::Languages,
 ) {
     use threading::ThreadMessage;
     if let Some(game_id) = player_games.get(&player_id) {
@@ -104,7 +219,8 @@ fn try_start_game(
             .send(ThreadMessage::Start)
             .expect("Could not start game");
     } else {
-        client.send_message(("Gioco non trovato", player_id).into());
+        let language = utils::language_of(languages, player_id.into());
+        client.send_message((Text::new("Gioco non trovato!"), player_id, language).into());
     }
 }
 fn try_handle_move(
@@ -114,6 +230,7 @@ fn try_handle_move(
     game_last_played: &mut HashMap<String, std::time::Instant>,
     game_channel: &HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     client: &Telegram,
+    languages: &primitives::Languages,
 ) {
     if let Some(game_id) = player_games.get(&from.id) {
         if let Some(inst) = game_last_played.get_mut(game_id) {
@@ -130,7 +247,8 @@ fn try_handle_move(
             ))
             .expect("Could not handle move");
     } else {
-        client.send_message(("Gioco non trovato", from.id).into());
+        let language = utils::language_of(languages, from.id.into());
+        client.send_message((Text::new("Gioco non trovato!"), from.id, language).into());
     }
 }
 
@@ -141,6 +259,7 @@ fn handle_callback_query(
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     game_last_played: &mut HashMap<String, std::time::Instant>,
     client: &mut Telegram,
+    languages: &primitives::Languages,
 ) {
     //let qry_id: String = qry.id.into;
     //client.ack_callback_query(&format!("{}", qry.id));
@@ -158,6 +277,7 @@ fn handle_callback_query(
                 game_channel,
                 game_last_played,
                 client,
+                languages,
             );
         }
         "start" => {
@@ -168,11 +288,50 @@ fn handle_callback_query(
                 game_last_played,
                 game_channel,
                 client,
+                languages,
             );
         }
+        "add_ai" => {
+            let level = data
+                .get(1)
+                .and_then(|x| cardgames::ai::Level::from_code(x))
+                .unwrap_or(cardgames::ai::Level::Medium);
+            if let Some(ch) = player_games
+                .get(&qry.from.id)
+                .and_then(|x| game_channel.get(x))
+            {
+                ch.send(threading::ThreadMessage::AddAiPlayer(
+                    cardgames::primitives::Player {
+                        id: qry.from.id.into(),
+                        name: utils::get_user_name(&qry.from.first_name, &qry.from.last_name),
+                    },
+                    level,
+                ))
+                .unwrap_or_default();
+            }
+        }
+        "variant" => {
+            if let (Some(variant), Some(option), Some(ch)) = (
+                data.get(1),
+                data.get(2),
+                player_games
+                    .get(&qry.from.id)
+                    .and_then(|x| game_channel.get(x)),
+            ) {
+                ch.send(threading::ThreadMessage::SetVariant(
+                    cardgames::primitives::Player {
+                        id: qry.from.id.into(),
+                        name: utils::get_user_name(&qry.from.first_name, &qry.from.last_name),
+                    },
+                    variant.clone(),
+                    option.clone(),
+                ))
+                .unwrap_or_default();
+            }
+        }
         "handle_move" => {
             let card: cardgames::primitives::Card =
-                bincode::deserialize(&base64::decode(&data[1]).unwrap()).unwrap();
+                bincode::deserialize(&BASE64_STANDARD.decode(&data[1]).unwrap()).unwrap();
             try_handle_move(
                 card,
                 qry.from,
@@ -180,6 +339,7 @@ fn handle_callback_query(
                 game_last_played,
                 game_channel,
                 client,
+                languages,
             );
         }
         _ => {}
@@ -193,45 +353,71 @@ fn handle_update(
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     game_last_played: &mut HashMap<String, std::time::Instant>,
     client: &mut Telegram,
+    languages: &primitives::Languages,
 ) {
     use telegram_bot_raw::types::message::MessageKind;
     use telegram_bot_raw::types::update::UpdateKind;
     if let UpdateKind::Message(msg) = update.kind {
         if let MessageKind::Text { data, entities } = msg.kind {
             drop(entities); // Silence the stupid warning and free some RAM
+            utils::remember_language(languages, &msg.from);
+            let language = utils::language_of(languages, msg.from.id.into());
             if data.contains("/start") {
                 let pieces: Vec<String> = data.split(" ").map(|x| x.to_owned()).collect();
                 if pieces.len() == 1 {
                     client.send_message(
                         (
-                            "Ciao! A che gioco vuoi giocare?",
+                            Text::new("Ciao! A che gioco vuoi giocare?"),
                             msg.from.id,
                             playable_games,
+                            language,
                         )
                             .into(),
                     );
                 } else {
                     let game_id = pieces[1].clone();
-                    add_player_to_game(game_id, client, player_games, game_channel, msg.from);
+                    add_player_to_game(
+                        game_id,
+                        client,
+                        player_games,
+                        game_channel,
+                        msg.from,
+                        languages,
+                    );
                 }
             } else if data == "/commit" {
-                client.send_message(
-                    (
-                        format!("This instance is running on {}", crate::get_git_version()),
-                        msg.from.id,
-                    )
-                        .into(),
+                let text = text!(
+                    "Questa istanza è in esecuzione sulla versione {}",
+                    crate::get_git_version()
                 );
+                client.send_message((text, msg.from.id, language).into());
+            } else if data.starts_with("/verifica") {
+                for text in verify_shuffle(&data) {
+                    client.send_message((text, msg.from.id, language).into());
+                }
+            } else if data.starts_with("/lingua") || data.starts_with("/language") {
+                let text = set_language(&data, msg.from.id.into(), languages);
+                let language = utils::language_of(languages, msg.from.id.into());
+                client.send_message((text, msg.from.id, language).into());
             } else {
                 // Pass to thread
                 // It's a text message that has to be handled. If a user has more than one active game
                 // I have to ask him which one
                 if let Some(game_id) = player_games.get(&msg.from.id) {
-                    handle_string_message(game_id, client, game_channel, msg.from, data);
+                    handle_string_message(
+                        game_id,
+                        client,
+                        game_channel,
+                        game_last_played,
+                        msg.from,
+                        data,
+                        languages,
+                    );
                 }
             }
         } // ignoring other message kinds since they're useless for us
     } else if let UpdateKind::CallbackQuery(qry) = update.kind {
+        utils::remember_language(languages, &qry.from);
         handle_callback_query(
             qry,
             playable_games,
@@ -239,10 +425,80 @@ fn handle_update(
             game_channel,
             game_last_played,
             client,
+            languages,
         );
     }
 }
 
+/// Checks the seed revealed at the end of a game against the commitment announced at the start,
+/// then shuffles again from the seed every deck of the game, hands of a match included.
+fn verify_shuffle(command: &str) -> Vec<Text> {
+    use cardgames::fairness;
+    let pieces: Vec<&str> = command.split_whitespace().collect();
+    let seed = match pieces.get(1).and_then(|x| x.parse::<u64>().ok()) {
+        Some(x) if pieces.len() == 3 || pieces.len() == 4 => x,
+        _ => {
+            return vec![Text::new(
+                "Scrivi /verifica seguito dal seed, dall'impronta del mazzo e dai mescolamenti della partita",
+            )]
+        }
+    };
+    // Senza mescolamenti si controlla solo il primo mazzo, come nelle vecchie partite
+    let record = match fairness::parse_record(pieces.get(3).unwrap_or(&"")) {
+        Some(x) => x,
+        None => return vec![Text::new("I mescolamenti della partita non sono validi")],
+    };
+    match fairness::verify(seed, pieces[2], &record) {
+        Some(dealt) => {
+            let mut res = vec![Text::new(
+                "✅ Il seed corrisponde all'impronta. Ecco i mazzi della partita, nell'ordine in cui sono stati mescolati:",
+            )];
+            // Un messaggio per mazzo, che non superi la lunghezza massima di Telegram
+            let (mut number, mut previous) = (0, None);
+            for x in &dealt {
+                // I mazzi si contano da capo in ogni mano
+                number = if previous == Some(x.hand) {
+                    number + 1
+                } else {
+                    1
+                };
+                previous = Some(x.hand);
+                res.push(match x.hand {
+                    Some(hand) => text!(
+                        "Mazzo {} della mano {}:\n{}",
+                        number,
+                        hand,
+                        Text::join(&x.deck, ", ")
+                    ),
+                    None => text!("Mazzo {}:\n{}", number, Text::join(&x.deck, ", ")),
+                });
+            }
+            res
+        }
+        None => vec![Text::new(
+            "❌ Il seed non corrisponde all'impronta del mazzo",
+        )],
+    }
+}
+
+/// Sets the language the user reads the messages in, given its code
+fn set_language(command: &str, user: i64, languages: &primitives::Languages) -> Text {
+    let code = command.split_whitespace().nth(1).map(|x| x.to_lowercase());
+    match Language::ALL
+        .iter()
+        .find(|x| Some(x.code()) == code.as_deref())
+    {
+        Some(language) => {
+            languages.write().unwrap().insert(user, *language);
+            text!("Lingua impostata: {}", language.name())
+        }
+        None => text!(
+            "Scrivi /lingua seguito da {}",
+            Text::join(Language::ALL.iter().map(|x| x.code()), " o ")
+        ),
+    }
+}
+
 fn handle_game_termination(
     game_last_played: &HashMap<String, std::time::Instant>,
     game_channel: &HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
@@ -282,7 +538,7 @@ fn purge_dead_games(
     *player_games = player_games
         .iter()
         .filter(|x| cleanup_list.iter().position(|y| y == x.1).is_none())
-        .map(|x| (x.0.clone(), x.1.clone()))
+        .map(|x| (*x.0, x.1.clone()))
         .collect();
     *game_channel = game_channel
         .iter()
@@ -292,7 +548,7 @@ fn purge_dead_games(
     *game_last_played = game_last_played
         .iter()
         .filter(|x| cleanup_list.iter().position(|y| y == x.0).is_none())
-        .map(|x| (x.0.clone(), x.1.clone()))
+        .map(|x| (x.0.clone(), *x.1))
         .collect();
 }
 
@@ -302,6 +558,7 @@ pub fn main_bot_logic(
     game_channel: &mut HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>>,
     game_last_played: &mut HashMap<String, std::time::Instant>,
     client: &mut Telegram,
+    languages: &primitives::Languages,
 ) {
     loop {
         for update in client.get_updates() {
@@ -312,6 +569,7 @@ pub fn main_bot_logic(
                 game_channel,
                 game_last_played,
                 client,
+                languages,
             );
         }
         handle_game_termination(game_last_played, game_channel);
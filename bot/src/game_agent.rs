@@ -1,53 +1,230 @@
-use std::collections::HashMap;
+use super::primitives::Languages;
+use super::telegram::{Message, Telegram};
 use super::threading::ThreadMessage;
-use cardgames::primitives;
 use super::utils;
-use super::telegram::{Telegram, Message};
+use super::SNAPSHOTS_DIR_VAR;
+use cardgames::ai::{Level, Strategy};
+use cardgames::fairness;
+use cardgames::i18n::Text;
+use cardgames::log::Recorder;
+use cardgames::primitives;
 use cardgames::primitives::Game;
+use cardgames::snapshot::Snapshot;
+use cardgames::text;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What the bot saves of a game in progress: its snapshot and the level of each computer player
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub snapshot: Snapshot,
+    pub ai_levels: HashMap<i64, Level>,
+}
+
+/// Where the snapshot of a game is saved, if the bot has been configured to save them
+pub fn snapshot_path(game_id: &str) -> Option<std::path::PathBuf> {
+    std::env::var(SNAPSHOTS_DIR_VAR)
+        .ok()
+        .map(|x| std::path::Path::new(&x).join(format!("{}.json", game_id)))
+}
+
+/// Where the log of a game is saved when it ends, so that it can be replayed later
+pub fn log_path(game_id: &str) -> Option<std::path::PathBuf> {
+    std::env::var(SNAPSHOTS_DIR_VAR)
+        .ok()
+        .map(|x| std::path::Path::new(&x).join(format!("{}.log", game_id)))
+}
+
+/// Lets the computer players move until it's the turn of a person or the game ends.
+/// The players restored from a snapshot get back their strategy at the level they were added with.
+fn play_ai_turns(
+    game: &mut dyn Game,
+    strategies: &mut HashMap<i64, Box<dyn Strategy>>,
+    levels: &HashMap<i64, Level>,
+    status: &mut Vec<primitives::GameStatus>,
+) {
+    while !status
+        .iter()
+        .any(|x| matches!(x, primitives::GameStatus::GameEnded))
+    {
+        let player = match game.get_next_player() {
+            Some(x) if utils::is_ai(x.id) => x,
+            _ => return,
+        };
+        let strategy = match strategies.entry(player.id) {
+            std::collections::hash_map::Entry::Occupied(x) => x.into_mut(),
+            std::collections::hash_map::Entry::Vacant(x) => match cardgames::ai::strategy_for(
+                game,
+                levels.get(&player.id).copied().unwrap_or(Level::Medium),
+            ) {
+                Some(strategy) => x.insert(strategy),
+                None => return,
+            },
+        };
+        let card = match strategy.choose_card(game, &player) {
+            Some(x) => x,
+            None => return,
+        };
+        let mut res = game.handle_move(&player, card);
+        let rejected = matches!(res.first(), Some(primitives::GameStatus::InvalidMove(_)));
+        status.append(&mut res);
+        if rejected {
+            return;
+        }
+    }
+}
 
-pub fn new_agent(game_tg_client: Telegram, game_index: usize, playable_games: &Vec<Box<dyn Game>>, receiver: std::sync::mpsc::Receiver<ThreadMessage>) {
-    let game = Box::leak(playable_games[game_index].get_new_instance());
+/// Sends a message to each person playing, outside of the messages that get replaced at every move
+fn send_to_players(client: &Telegram, game: &dyn Game, languages: &Languages, text: &Text) {
+    for player in game
+        .get_players()
+        .into_iter()
+        .filter(|x| !utils::is_ai(x.id))
+    {
+        client.send_message(Message {
+            chat_id: player.id,
+            text: text.translate(utils::language_of(languages, player.id)),
+            keyboard: None,
+        });
+    }
+}
+
+pub fn new_agent(
+    game_tg_client: Telegram,
+    game: Box<dyn Game>,
+    game_id: String,
+    receiver: std::sync::mpsc::Receiver<ThreadMessage>,
+    languages: Languages,
+    ai_levels: HashMap<i64, Level>,
+) {
+    let recorder = Box::leak(Box::new(Recorder::new(game)));
     std::thread::spawn(move || {
         let mut message_list: HashMap<i64, i64> = HashMap::new();
         let client = game_tg_client;
-        let game = game;
-        game.init();
+        let game: &mut dyn Game = recorder;
+        let mut strategies: HashMap<i64, Box<dyn Strategy>> = HashMap::new();
+        let mut levels = ai_levels;
         let mut game_is_running = true;
         while game_is_running {
             let message = receiver.recv().unwrap();
+            let is_ping = matches!(message, ThreadMessage::Ping);
             let status = match message {
-                ThreadMessage::AddPlayer(p) => vec![game.add_player(p.clone()).unwrap_or_else(|x| primitives::GameStatus::NotifyUser(p, x.to_owned()))],
-                ThreadMessage::Start => vec![game.start(), primitives::GameStatus::NotifyRoom(game.get_status())],
+                ThreadMessage::AddPlayer(p) => vec![game
+                    .add_player(p.clone())
+                    .unwrap_or_else(|x| primitives::GameStatus::NotifyUser(p, x.text()))],
+                ThreadMessage::AddAiPlayer(p, level) => {
+                    // Only who created the game can fill the seats
+                    match cardgames::ai::strategy_for(game, level)
+                        .filter(|_| game.get_players().first() == Some(&p))
+                    {
+                        Some(strategy) => {
+                            let number = game
+                                .get_players()
+                                .iter()
+                                .filter(|x| utils::is_ai(x.id))
+                                .count() as i64
+                                + 1;
+                            let player = primitives::Player {
+                                id: -number,
+                                name: format!("🤖 Computer {}", number),
+                            };
+                            match game.add_player(player) {
+                                Ok(x) => {
+                                    strategies.insert(-number, strategy);
+                                    levels.insert(-number, level);
+                                    vec![x]
+                                }
+                                Err(x) => vec![primitives::GameStatus::NotifyUser(p, x.text())],
+                            }
+                        }
+                        None => {
+                            vec![primitives::GameStatus::NotifyUser(
+                                p,
+                                Text::new(
+                                    "Non puoi aggiungere giocatori del computer a questa partita",
+                                ),
+                            )]
+                        }
+                    }
+                }
+                ThreadMessage::SetVariant(p, variant, option) => {
+                    // Only who created the game can change the rules
+                    if game.get_players().first() != Some(&p) {
+                        vec![primitives::GameStatus::NotifyUser(
+                            p,
+                            Text::new("Solo chi ha creato la partita può cambiare le regole"),
+                        )]
+                    } else {
+                        match game.set_variant(&variant, &option) {
+                            Ok(()) => game
+                                .variants()
+                                .into_iter()
+                                .filter(|x| x.code == variant)
+                                .map(|x| {
+                                    primitives::GameStatus::RulesChanged(
+                                        p.clone(),
+                                        text!("{}: {}", x.name, x.chosen_name()),
+                                    )
+                                })
+                                .collect(),
+                            Err(x) => vec![primitives::GameStatus::NotifyUser(p, x.text())],
+                        }
+                    }
+                }
+                ThreadMessage::Start => {
+                    let status = game.start();
+                    let mut tmp = vec![status.clone()];
+                    if !matches!(status, primitives::GameStatus::InvalidMove(_)) {
+                        // Commit to the seed before anyone sees their cards, it will be revealed when the game ends.
+                        // The commitment depends only on the seed, which dealing doesn't change.
+                        let commitment = text!(
+                            "Impronta del mazzo: {}\nA fine partita verrà rivelato il seed con cui è stato mescolato, per verificare che non sia truccato",
+                            fairness::commitment_of(game.get_shuffler(), game.get_card_set())
+                        );
+                        send_to_players(&client, game, &languages, &commitment);
+                        play_ai_turns(game, &mut strategies, &levels, &mut tmp);
+                    }
+                    tmp.push(primitives::GameStatus::NotifyRoom(game.get_status()));
+                    tmp
+                }
                 ThreadMessage::HandleMove(p, c) => {
                     let mut tmp = game.handle_move(&p, c);
+                    play_ai_turns(game, &mut strategies, &levels, &mut tmp);
                     tmp.push(primitives::GameStatus::NotifyRoom(game.get_status()));
                     tmp
-                },
+                }
                 ThreadMessage::HandleStringMessage(from, text) => {
-                    for message in game.handle_message(text, from).iter()
-                        .map(|x| utils::dispatch_game_status(x.clone(), game))
-                        .flatten() // Flatten the double Vec
-                        .collect::<Vec<Message>>()
-                    {
-                        client.send_message(message);
-                    }
-                    vec![]
-                },
+                    // Some games are played writing commands, which work just like the moves
+                    let mut tmp = game.handle_message(text, from);
+                    play_ai_turns(game, &mut strategies, &levels, &mut tmp);
+                    tmp.push(primitives::GameStatus::NotifyRoom(game.get_status()));
+                    tmp
+                }
                 ThreadMessage::Kill => {
+                    if let Some(path) = snapshot_path(&game_id) {
+                        std::fs::remove_file(path).unwrap_or_default();
+                    }
                     break;
-                },
-                ThreadMessage::Ping => {vec![]},
-                ThreadMessage::AboutToKill => {vec![primitives::GameStatus::NotifyRoom("Questo gioco sarà terminato per inattività a breve!".to_owned())]},
+                }
+                ThreadMessage::Ping => {
+                    vec![]
+                }
+                ThreadMessage::AboutToKill => {
+                    vec![primitives::GameStatus::NotifyRoom(Text::new(
+                        "Questo gioco sarà terminato per inattività a breve!",
+                    ))]
+                }
             };
             for status in &status {
                 if let primitives::GameStatus::GameEnded = status {
                     game_is_running = false;
                 }
             }
-            for i in utils::compact_messages(status.iter()
-                .map(|x| utils::dispatch_game_status(x.clone(), game)) // find out who's the recipient of each message
-                .flatten() // Flatten the double Vec
-                .collect::<Vec<Message>>()
+            for i in utils::compact_messages(
+                status
+                    .iter()
+                    .flat_map(|x| utils::dispatch_game_status(x.clone(), game, &languages)) // find out who's the recipient of each message
+                    .collect::<Vec<Message>>(),
             ) {
                 match message_list.get_mut(&i.chat_id) {
                     Some(msg_id) => *msg_id = client.edit_message(i, *msg_id),
@@ -58,6 +235,38 @@ pub fn new_agent(game_tg_client: Telegram, game_index: usize, playable_games: &V
                     }
                 }
             }
+            if !game_is_running {
+                let shuffler = game.get_shuffler();
+                let text = text!(
+                    "I mazzi sono stati mescolati con il seed {}, puoi verificarli con:\n/verifica {} {} {}",
+                    shuffler.get_seed(),
+                    shuffler.get_seed(),
+                    fairness::commitment_of(shuffler, game.get_card_set()),
+                    fairness::record(shuffler)
+                );
+                send_to_players(&client, game, &languages, &text);
+            }
+            // Save the game, so that it can be restored if the bot restarts
+            if let Some(path) = snapshot_path(&game_id).filter(|_| !is_ping) {
+                if game_is_running {
+                    let saved = SavedGame {
+                        snapshot: game.snapshot(),
+                        ai_levels: levels.clone(),
+                    };
+                    if let Err(e) = std::fs::write(&path, serde_json::to_string(&saved).unwrap()) {
+                        eprintln!("Could not save {}: {}", path.display(), e);
+                    }
+                } else {
+                    std::fs::remove_file(&path).unwrap_or_default();
+                }
+            }
+        }
+        if let Some(path) = log_path(&game_id) {
+            if let Err(e) =
+                std::fs::write(&path, serde_json::to_string(recorder.get_log()).unwrap())
+            {
+                eprintln!("Could not save {}: {}", path.display(), e);
+            }
         }
     });
 }
@@ -5,6 +5,7 @@ mod telegram;
 mod threading;
 mod utils;
 
+use cardgames::multi_hand::{Match, Target};
 use cardgames::primitives::Game;
 use std::collections::HashMap;
 use std::panic;
@@ -12,6 +13,8 @@ use std::sync::mpsc;
 
 // A game can last up to 10 minutes since the last action
 static MAX_GAME_DURATION: u64 = 600;
+// If set, games are saved in this directory and restored when the bot restarts
+static SNAPSHOTS_DIR_VAR: &str = "SNAPSHOTS_DIR";
 
 #[cfg(not(feature = "github"))]
 #[inline(always)]
@@ -31,7 +34,7 @@ pub fn register_handler() {
     panic::set_hook(Box::new(move |x| {
         let output = format!("Version: {}\n{} {:?}", get_git_version(), x, x);
         std::fs::write(
-            &format!(
+            format!(
                 "cardgames_panic_{}.txt",
                 std::time::UNIX_EPOCH.elapsed().unwrap().as_secs()
             ),
@@ -50,23 +53,77 @@ fn main() {
     let mut game_channel: HashMap<String, std::sync::mpsc::SyncSender<threading::ThreadMessage>> =
         HashMap::new();
     let mut game_last_played: HashMap<String, std::time::Instant> = HashMap::new();
+    // Language chosen by each user
+    let languages: primitives::Languages = Default::default();
 
-    let mut playable_games: Vec<Box<dyn Game>> = Vec::new();
     // List of playable games
-    playable_games.push(Box::from(cardgames::games::briscola::Briscola::default()));
-    playable_games.push(Box::from(
-        cardgames::games::beccaccino::Beccaccino::default(),
-    ));
-    // Disable Scala 40
-    //playable_games.push(Box::from(cardgames::games::scala40::Scala40::default()));
+    let playable_games: Vec<Box<dyn Game>> = vec![
+        Box::from(cardgames::games::briscola::Briscola::default()),
+        Box::from(Match::new(
+            Box::from(cardgames::games::briscola::Briscola::default()),
+            Target::BestOf(3),
+        )),
+        Box::from(cardgames::games::briscola_chiamata::BriscolaChiamata::default()),
+        Box::from(cardgames::games::beccaccino::Beccaccino::default()),
+        Box::from(Match::new(
+            Box::from(cardgames::games::beccaccino::Beccaccino::default()),
+            Target::Points(41),
+        )),
+        Box::from(cardgames::games::marafone::new()),
+        Box::from(Match::new(
+            Box::from(cardgames::games::scala40::Scala40::default()),
+            Target::Elimination(101),
+        )),
+        Box::from(Match::new(
+            Box::from(cardgames::games::burraco::Burraco::default()),
+            Target::Points(2005),
+        )),
+        Box::from(cardgames::games::scopa::Scopa::default()),
+        Box::from(Match::new(
+            Box::from(cardgames::games::scopa::Scopa::default()),
+            Target::Points(11),
+        )),
+        Box::from(cardgames::games::scopone::new(false, 11)),
+        Box::from(cardgames::games::scopone::new(true, 21)),
+        Box::from(cardgames::games::tressette::Tressette::default()),
+        Box::from(cardgames::games::sette_e_mezzo::SetteEMezzo::default()),
+        Box::from(cardgames::games::rubamazzo::Rubamazzo::default()),
+    ];
 
     println!("Starting CardGamesBot...");
     let mut client = telegram::Telegram::init();
+    bot::restore_games(
+        &mut player_games,
+        &mut game_channel,
+        &mut game_last_played,
+        &client,
+        &languages,
+    );
     bot::main_bot_logic(
         &playable_games,
         &mut player_games,
         &mut game_channel,
         &mut game_last_played,
         &mut client,
+        &languages,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    /// Every message the bot writes with `text!` or `Text::new` has an English translation
+    #[test]
+    fn every_message_is_translated() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                cardgames::i18n::untranslated(&source),
+                Vec::<String>::new(),
+                "Mancano traduzioni in {}",
+                path.display()
+            );
+        }
+    }
+}
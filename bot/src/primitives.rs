@@ -1,3 +1,8 @@
+use cardgames::i18n::Language;
 use cardgames::primitives::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
-pub type DispatchableStatus = (Player, GameStatus);
+pub type DispatchableStatus = (Player, GameStatus, Language);
+/// The language chosen by each user, shared between the bot and the games
+pub type Languages = Arc<RwLock<HashMap<i64, Language>>>;
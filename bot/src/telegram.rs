@@ -1,5 +1,8 @@
 use super::primitives;
+use base64::prelude::*;
+use cardgames::i18n::{Language, Text};
 use cardgames::primitives::Game;
+use cardgames::text;
 use itertools::Itertools;
 use serde::Deserialize;
 use std::env;
@@ -65,6 +68,7 @@ impl Telegram {
             std::io::stdout().flush().unwrap();
             read!("{}\n")
         });
+        #[allow(dead_code)]
         #[derive(Deserialize, Debug)]
         struct Response {
             ok: bool,
@@ -84,6 +88,7 @@ impl Telegram {
     }
 
     pub fn send_message(&self, message: Message) -> i64 {
+        #[allow(dead_code)]
         #[derive(Deserialize, Debug)]
         struct Response {
             ok: bool,
@@ -132,11 +137,12 @@ impl Telegram {
         .into_string()
         .unwrap();
         let parsed: Result = serde_json::from_str(&res).unwrap();
-        if parsed.result.len() > 0 {
+        if !parsed.result.is_empty() {
             self.last_id = Some(parsed.clone().result.last().unwrap().id as u64);
         }
         parsed.result
     }
+    #[allow(dead_code)]
     pub fn ack_callback_query(&self, qry_id: &str) {
         ureq::post(&format!(
             "https://api.telegram.org/bot{}/editMessageText",
@@ -148,52 +154,65 @@ impl Telegram {
     }
 }
 
-/// This function converts a list of cards into an array of buttons
-fn deck_of_buttons(cards: Vec<cardgames::primitives::Card>) -> Vec<Vec<Button>> {
-    let mut res = vec![];
-    // Now add a row every 3 cards
-    for _ in (0..cards.len()).skip(3) {
-        res.push(vec![]);
-    }
-    res.push(vec![]);
-    // Let's add the cards
-    for (i, card) in cards.iter().enumerate() {
-        let row_number = i / 3;
-        res[row_number].push(Button {
-            text: cardgames::utils::get_card_name(card),
-            id: format!(
-                "handle_move:{}",
-                base64::encode(bincode::serialize(card).unwrap())
-            ), //                                                                  ^
-               //I'm serializing cards to deserialize later -----------------------|
-        });
+/// This function converts a list of cards into an array of buttons.
+/// If the legal moves are known, the cards that can't be played are marked.
+pub fn deck_of_buttons(
+    cards: Vec<cardgames::primitives::Card>,
+    legal_moves: Option<&[cardgames::primitives::Card]>,
+    language: Language,
+) -> Vec<Vec<Button>> {
+    // A row every 3 cards
+    cards
+        .chunks(3)
+        .map(|row| {
+            row.iter()
+                .map(|card| Button {
+                    text: match legal_moves {
+                        Some(legal) if !legal.contains(card) => {
+                            format!("🚫 {}", cardgames::utils::get_card_name(card, language))
+                        }
+                        _ => cardgames::utils::get_card_name(card, language),
+                    },
+                    // I'm serializing cards to deserialize them later
+                    id: format!(
+                        "handle_move:{}",
+                        BASE64_STANDARD.encode(bincode::serialize(card).unwrap())
+                    ),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// What the bot writes to tell the players about a status of the game
+pub fn status_text(status: &cardgames::primitives::GameStatus) -> Text {
+    use cardgames::primitives::GameStatus::*;
+    match status.clone() {
+        GameEnded => Text::new("La partita è finita!"),
+        RoundWon(p) => text!("{} ha vinto questo round", p.name),
+        InProgress(p) => text!("Tocca a {}", p.name),
+        WaitingForPlayers(_, p) => text!("{} si è unito alla partita", p.name),
+        WaitingForChoice(_, _) => Text::new("Scegli una carta:"),
+        InvalidMove(error) => text!("Questa mossa non è valida! {}", error.text()),
+        WaitingForChoiceCustomMessage(_, _, msg) => msg,
+        NotifyUser(_, msg) => msg,
+        NotifyRoom(msg) => msg,
+        CardPlayed(p, c) => text!("{} ha giocato {}", p.name, c),
+        PartnerRevealed(p, partner) => {
+            text!("{} gioca in coppia con {}", partner.name, p.name)
+        }
+        BriscolaChosen(p, suit) => text!("{} ha scelto la briscola: {}", p.name, suit),
+        RulesChanged(p, rules) => text!("{} ha cambiato le regole: {}", p.name, rules),
+        HandScored(number, _) => text!("Fine della mano {}", number),
     }
-    res
 }
 
 impl From<primitives::DispatchableStatus> for Message {
     fn from(status: primitives::DispatchableStatus) -> Self {
+        let language = status.2;
         Self {
             chat_id: status.0.id,
-            text: {
-                use cardgames::primitives::GameStatus::*;
-                match status.1.clone() {
-                    GameEnded => "La partita è finita!".to_owned(),
-                    RoundWon(p) => format!("{} ha vinto questo round", p.name),
-                    InProgress(p) => format!("Tocca a {}", p.name),
-                    WaitingForPlayers(_, p) => format!("{} si è unito alla partita", p.name),
-                    WaitingForChoice(_, _) => "Scegli una carta:".to_owned(),
-                    InvalidMove(msg) => format!("Questa mossa non è valida! {}", msg),
-                    WaitingForChoiceCustomMessage(_, _, msg) => msg.to_string(),
-                    NotifyUser(_, msg) => msg,
-                    NotifyRoom(msg) => msg,
-                    CardPlayed(p, c) => format!(
-                        "{} ha giocato {}",
-                        p.name,
-                        cardgames::utils::get_card_name(&c)
-                    ),
-                }
-            },
+            text: status_text(&status.1).translate(language),
             keyboard: {
                 use cardgames::primitives::GameStatus::*;
                 match status.1.clone() {
@@ -201,14 +220,16 @@ impl From<primitives::DispatchableStatus> for Message {
                         if ready {
                             Some(vec![vec![Button {
                                 id: "start".to_owned(),
-                                text: "Avvia partita".to_owned(),
+                                text: Text::new("Avvia partita").translate(language),
                             }]])
                         } else {
                             None
                         }
                     }
-                    WaitingForChoice(_, cards) => Some(deck_of_buttons(cards)),
-                    WaitingForChoiceCustomMessage(_, cards, _) => Some(deck_of_buttons(cards)),
+                    WaitingForChoice(_, cards) => Some(deck_of_buttons(cards, None, language)),
+                    WaitingForChoiceCustomMessage(_, cards, _) => {
+                        Some(deck_of_buttons(cards, None, language))
+                    }
                     _ => None,
                 }
             },
@@ -216,41 +237,34 @@ impl From<primitives::DispatchableStatus> for Message {
     }
 }
 
-impl From<(&str, telegram_bot_raw::types::refs::UserId)> for Message {
-    fn from(tuple: (&str, telegram_bot_raw::types::refs::UserId)) -> Self {
-        Self {
-            chat_id: tuple.1.into(),
-            text: tuple.0.to_owned(),
-            keyboard: None,
-        }
-    }
-}
-impl From<(String, telegram_bot_raw::types::refs::UserId)> for Message {
-    fn from(tuple: (String, telegram_bot_raw::types::refs::UserId)) -> Self {
+impl From<(Text, telegram_bot_raw::types::refs::UserId, Language)> for Message {
+    fn from(tuple: (Text, telegram_bot_raw::types::refs::UserId, Language)) -> Self {
         Self {
             chat_id: tuple.1.into(),
-            text: tuple.0.clone(),
+            text: tuple.0.translate(tuple.2),
             keyboard: None,
         }
     }
 }
 impl
     From<(
-        &str,
+        Text,
         telegram_bot_raw::types::refs::UserId,
         &Vec<Box<dyn Game>>,
+        Language,
     )> for Message
 {
     fn from(
         tuple: (
-            &str,
+            Text,
             telegram_bot_raw::types::refs::UserId,
             &Vec<Box<dyn Game>>,
+            Language,
         ),
     ) -> Self {
         Self {
             chat_id: tuple.1.into(),
-            text: tuple.0.to_owned(),
+            text: tuple.0.translate(tuple.3),
             keyboard: {
                 Some(
                     tuple
@@ -261,15 +275,16 @@ impl
                             let range = x.1.get_num_players();
                             vec![Button {
                                 id: format!("init_game:{}", x.0),
-                                text: format!(
+                                text: text!(
                                     "{} ({} giocatori)",
-                                    x.1.get_name(),
+                                    x.1.get_title(),
                                     if range.start == range.end {
                                         format!("{}", range.start)
                                     } else {
                                         format!("{} - {}", range.start, range.end)
                                     }
-                                ),
+                                )
+                                .translate(tuple.3),
                             }]
                         })
                         .collect(),
@@ -2,9 +2,13 @@ use cardgames::primitives::*;
 pub enum ThreadMessage {
     HandleMove(Player, Card),
     AddPlayer(Player),
+    /// The player asks to fill a seat with a computer player of the given level
+    AddAiPlayer(Player, cardgames::ai::Level),
+    /// The player chooses an option of a variant of the rules, by their codes
+    SetVariant(Player, String, String),
     Start,
     Kill,
     Ping,
     AboutToKill,
-    HandleStringMessage(Player, String)
-}
\ No newline at end of file
+    HandleStringMessage(Player, String),
+}
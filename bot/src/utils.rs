@@ -1,30 +1,55 @@
-use super::telegram::{Message, Button};
-use std::collections::HashMap;
+use super::primitives::Languages;
+use super::telegram::{Button, Message};
+use cardgames::ai::Level;
+use cardgames::i18n::{Language, Text};
 use cardgames::primitives::*;
+use cardgames::text;
+use std::collections::HashMap;
 
 pub fn get_user_name(name: &str, surname: &Option<String>) -> String {
-    name.to_owned() + if surname.is_some(){" "} else {""} + &(surname.clone()).unwrap_or_else(|| "".to_owned())
+    name.to_owned()
+        + if surname.is_some() { " " } else { "" }
+        + &(surname.clone()).unwrap_or_else(|| "".to_owned())
+}
+
+/// Computer players have negative ids, so that they can't be mistaken for Telegram users
+pub fn is_ai(id: i64) -> bool {
+    id < 0
+}
+
+/// The language the user reads messages in: the one they chose, or Italian
+pub fn language_of(languages: &Languages, user: i64) -> Language {
+    languages
+        .read()
+        .unwrap()
+        .get(&user)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Remembers the language of the user from their Telegram settings, unless they have already chosen one
+pub fn remember_language(languages: &Languages, user: &telegram_bot_raw::types::chat::User) {
+    languages
+        .write()
+        .unwrap()
+        .entry(user.id.into())
+        .or_insert_with(|| Language::from_code(user.language_code.as_deref()));
 }
 
 pub fn compact_messages(list: Vec<Message>) -> Vec<Message> {
     let mut map: HashMap<i64, Vec<Message>> = HashMap::new();
     for message in list {
-        if map.get(&message.chat_id).is_none() {
-            map.insert(message.chat_id, vec![]);
-        }
-        let v = map.get_mut(&message.chat_id).unwrap();
+        let v = map.entry(message.chat_id).or_default();
         v.push(message.clone());
     }
     map.iter()
         .map(|x| {
-            let concatenated_text = x.1.iter()
-                .map(|x| &x.text)
-                .fold(String::new(), |acc, x| acc + x + "\n");
-            let mut keyboards: Vec<Vec<Vec<Button>>> = x.1.iter()
-                .map(|x| x.keyboard.clone())
-                .filter(|x| x.is_some())
-                .map(|x| x.unwrap())
-                .collect();
+            let concatenated_text =
+                x.1.iter()
+                    .map(|x| &x.text)
+                    .fold(String::new(), |acc, x| acc + x + "\n");
+            let mut keyboards: Vec<Vec<Vec<Button>>> =
+                x.1.iter().filter_map(|x| x.keyboard.clone()).collect();
             let keyboard = if keyboards.is_empty() {
                 None
             } else {
@@ -34,20 +59,43 @@ pub fn compact_messages(list: Vec<Message>) -> Vec<Message> {
                 }
                 Some(tmp_keyboard)
             };
-            Message{chat_id: *(x.0), text: concatenated_text, keyboard}
+            Message {
+                chat_id: *(x.0),
+                text: concatenated_text,
+                keyboard,
+            }
         })
         .collect()
 }
 
 /// This function routes the status to the right players
-pub fn dispatch_game_status(status: GameStatus, game: &dyn Game) -> Vec<super::telegram::Message> {
-    match status.clone() {
+pub fn dispatch_game_status(
+    status: GameStatus,
+    game: &dyn Game,
+    languages: &Languages,
+) -> Vec<super::telegram::Message> {
+    let messages = match status.clone() {
         // Messages for selected players
         // GameStatus::InProgress(p) => vec![(p, self.clone())],
-        GameStatus::WaitingForChoice(p, _) => vec![(p, status.clone()).into()],
-        GameStatus::WaitingForChoiceCustomMessage(p, _, _) => vec![(p, status.clone()).into()],
-        GameStatus::NotifyUser(p, _) => vec![(p, status.clone()).into()],
-        GameStatus::WaitingForPlayers(_, _) => {
+        GameStatus::WaitingForChoice(p, cards)
+        | GameStatus::WaitingForChoiceCustomMessage(p, cards, _) => {
+            let language = language_of(languages, p.id);
+            let mut message: Message = (p.clone(), status.clone(), language).into();
+            // Grey out the cards that can't be played right now
+            let legal_moves = game.legal_moves(&p);
+            if !legal_moves.is_empty() {
+                message.keyboard = Some(super::telegram::deck_of_buttons(
+                    cards,
+                    Some(&legal_moves),
+                    language,
+                ));
+            }
+            vec![message]
+        }
+        GameStatus::NotifyUser(p, _) => {
+            vec![(p.clone(), status.clone(), language_of(languages, p.id)).into()]
+        }
+        GameStatus::WaitingForPlayers(_, _) | GameStatus::RulesChanged(_, _) => {
             // This closure makes sure that only the game initiator
             // gets the button to start the game.
             //use super::telegram::Message;
@@ -55,12 +103,70 @@ pub fn dispatch_game_status(status: GameStatus, game: &dyn Game) -> Vec<super::t
             let mut players = game.get_players();
             players.reverse();
             let player = players.pop().unwrap();
-            let text = Message::from((player.clone(), status.clone())).text;
-            res.push((player, status.clone()).into());
-            res.append(&mut players.iter().map(|x| (x.clone(), GameStatus::NotifyUser(x.clone(), text.clone())).into()).collect());
+            let text = super::telegram::status_text(&status);
+            let language = language_of(languages, player.id);
+            let mut message: Message = (player.clone(), status.clone(), language).into();
+            // After changing the rules the game can be started as before
+            if matches!(status, GameStatus::RulesChanged(_, _))
+                && game.get_players().len() >= game.get_num_players().start as usize
+            {
+                message
+                    .keyboard
+                    .get_or_insert_with(Vec::new)
+                    .push(vec![Button {
+                        id: "start".to_owned(),
+                        text: Text::new("Avvia partita").translate(language),
+                    }]);
+            }
+            // The empty seats can be taken by the computer
+            if cardgames::ai::strategy_for(game, Level::Medium).is_some()
+                && game.get_players().len() < game.get_num_players().end as usize
+            {
+                let keyboard = message.keyboard.get_or_insert_with(Vec::new);
+                for level in Level::ALL.iter() {
+                    keyboard.push(vec![Button {
+                        id: format!("add_ai:{}", level.code()),
+                        text: text!("Aggiungi un giocatore del computer ({})", level.name())
+                            .translate(language),
+                    }]);
+                }
+            }
+            // Each button moves a variant of the rules to its next option
+            for variant in game.variants() {
+                message
+                    .keyboard
+                    .get_or_insert_with(Vec::new)
+                    .push(vec![Button {
+                        id: format!("variant:{}:{}", variant.code, variant.next_option()),
+                        text: text!("{}: {}", variant.name, variant.chosen_name())
+                            .translate(language),
+                    }]);
+            }
+            res.push(message);
+            res.append(
+                &mut players
+                    .iter()
+                    .map(|x| {
+                        (
+                            x.clone(),
+                            GameStatus::NotifyUser(x.clone(), text.clone()),
+                            language_of(languages, x.id),
+                        )
+                            .into()
+                    })
+                    .collect(),
+            );
             res
         }
+        // The match tells how the hand went in its own message
+        GameStatus::HandScored(_, _) => vec![],
         // Everything else will sent to everybody in the game
-        _ => game.get_players().iter().map(|x| (x.clone(), status.clone()).into()).collect::<Vec<super::telegram::Message>>()
-    }
+        _ => game
+            .get_players()
+            .iter()
+            .map(|x| (x.clone(), status.clone(), language_of(languages, x.id)).into())
+            .collect::<Vec<super::telegram::Message>>(),
+    };
+    // Computer players don't read messages
+    messages.into_iter().filter(|x| !is_ai(x.chat_id)).collect()
 }
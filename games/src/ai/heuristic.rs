@@ -0,0 +1,251 @@
+use super::Strategy;
+use crate::primitives::*;
+use crate::view::PlayerView;
+use std::marker::PhantomData;
+
+/// A rule-based player for trick-taking games with a briscola, like Briscola and Beccaccino.
+/// The points and the strength of the cards are the ones of the game `G`.
+/// - Trumps are saved for the tricks that are worth them
+/// - Points go on the tricks the partner is winning
+/// - Tricks that can't be won get the cheapest card
+pub struct Heuristic<G> {
+    game: PhantomData<fn() -> G>,
+}
+
+impl<G> Default for Heuristic<G> {
+    fn default() -> Self {
+        Self { game: PhantomData }
+    }
+}
+
+impl<G: Game + CardValues> Heuristic<G> {
+    fn points(card: &Card) -> fraction::GenericFraction<u8> {
+        G::get_card_rank(&card.rank)
+    }
+    fn strength(card: &Card) -> u8 {
+        G::get_card_sorting_rank(&card.rank)
+    }
+    /// Whether `card` takes the trick from `best`, which is winning it so far
+    fn beats(card: &Card, best: &Card, briscola: Option<Suit>) -> bool {
+        if card.suit == best.suit {
+            Self::strength(card) > Self::strength(best)
+        } else {
+            Some(card.suit) == briscola
+        }
+    }
+    /// The suit in which the hand is strongest, to choose the briscola
+    fn best_suit(hand: &[Card]) -> Option<Suit> {
+        Suit::ITALIAN.iter().copied().max_by_key(|suit| {
+            hand.iter()
+                .filter(|x| &x.suit == suit)
+                .map(|x| Self::strength(x) as u32 + 1)
+                .sum::<u32>()
+        })
+    }
+    /// The card worth the least, saving trumps
+    fn cheapest(cards: &[Card], briscola: Option<Suit>) -> Option<Card> {
+        cards
+            .iter()
+            .copied()
+            .min_by_key(|x| (Some(x.suit) == briscola, Self::points(x), Self::strength(x)))
+    }
+}
+
+impl<G: Game + CardValues> Strategy for Heuristic<G> {
+    /// The choice only depends on the view of the player
+    fn choose_card(&self, game: &dyn Game, player: &Player) -> Option<Card> {
+        self.choose(&game.view_for(player))
+    }
+}
+
+impl<G: Game + CardValues> Heuristic<G> {
+    fn choose(&self, view: &PlayerView) -> Option<Card> {
+        let legal = &view.legal_moves;
+        if legal.len() <= 1 {
+            return legal.first().copied();
+        }
+        let briscola = match view.briscola {
+            Some(x) => Some(x),
+            // Nel Beccaccino la prima carta scelta indica il seme di briscola
+            None if view.trump_card.is_none() && view.table.is_empty() => {
+                let suit = Self::best_suit(&view.hand)?;
+                return legal
+                    .iter()
+                    .copied()
+                    .filter(|x| x.suit == suit)
+                    .max_by_key(Self::strength);
+            }
+            None => None,
+        };
+        let (first, rest) = match view.table.split_first() {
+            Some(x) => x,
+            // Chi apre la mano rischia il meno possibile
+            None => return Self::cheapest(legal, briscola),
+        };
+        let winner = rest.iter().fold(first, |best, x| {
+            if Self::beats(&x.1, &best.1, briscola) {
+                x
+            } else {
+                best
+            }
+        });
+        let partners: Vec<&Player> = view
+            .scores
+            .iter()
+            .find(|x| view.player.as_ref().map(|p| x.0.contains(p)) == Some(true))
+            .map(|x| {
+                x.0.iter()
+                    .filter(|y| Some(*y) != view.player.as_ref())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if winner.0.as_ref().map(|x| partners.contains(&x)) == Some(true) {
+            // Carico la presa del compagno, ma senza sprecare briscole
+            return legal
+                .iter()
+                .copied()
+                .filter(|x| Some(x.suit) != briscola)
+                .max_by_key(|x| (Self::points(x), std::cmp::Reverse(Self::strength(x))))
+                .or_else(|| Self::cheapest(legal, briscola));
+        }
+        let trick_points = view
+            .table
+            .iter()
+            .map(|x| Self::points(&x.1))
+            .fold(crate::utils::zero(), |acc, x| acc + x);
+        let winning: Vec<Card> = legal
+            .iter()
+            .copied()
+            .filter(|x| Self::beats(x, &winner.1, briscola))
+            .collect();
+        // Se posso prendere senza briscola uso la carta che vale di più
+        if let Some(card) = winning
+            .iter()
+            .copied()
+            .filter(|x| Some(x.suit) != briscola)
+            .max_by_key(|x| (Self::points(x), Self::strength(x)))
+        {
+            return Some(card);
+        }
+        // Una briscola si spende solo per una presa che vale almeno quanto un tre
+        if trick_points >= G::get_card_rank(&Rank::Three) {
+            if let Some(card) = winning
+                .iter()
+                .copied()
+                .min_by_key(|x| (Self::points(x), Self::strength(x)))
+            {
+                return Some(card);
+            }
+        }
+        Self::cheapest(legal, briscola)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{beccaccino::Beccaccino, briscola::Briscola};
+    use crate::testing::player;
+    use Rank::*;
+    use Suit::*;
+
+    /// The view of the first of four players, who plays with the third
+    fn view(hand: Vec<Card>, table: Vec<(i64, Card)>) -> PlayerView {
+        PlayerView {
+            player: Some(player(0)),
+            legal_moves: hand.clone(),
+            hand,
+            table: table
+                .into_iter()
+                .map(|x| (Some(player(x.0)), x.1))
+                .collect(),
+            briscola: Some(Coppe),
+            scores: vec![
+                (vec![player(0), player(2)], "0".to_owned()),
+                (vec![player(1), player(3)], "0".to_owned()),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn briscola_saves_trumps() {
+        let ai = Heuristic::<Briscola>::default();
+        let hand = vec![
+            Card::new(Ace, Coppe),
+            Card::new(Four, Spade),
+            Card::new(King, Denari),
+        ];
+        // Apre con la carta che vale meno
+        assert_eq!(
+            ai.choose(&view(hand.clone(), vec![])),
+            Some(Card::new(Four, Spade))
+        );
+        // Una presa senza punti non vale una briscola
+        let table = vec![(1, Card::new(Two, Bastoni))];
+        assert_eq!(
+            ai.choose(&view(hand.clone(), table)),
+            Some(Card::new(Four, Spade))
+        );
+        // Un asso sì
+        let table = vec![(1, Card::new(Ace, Bastoni))];
+        assert_eq!(ai.choose(&view(hand, table)), Some(Card::new(Ace, Coppe)));
+    }
+
+    #[test]
+    fn briscola_loads_partner_trick() {
+        let ai = Heuristic::<Briscola>::default();
+        let hand = vec![
+            Card::new(Ace, Coppe),
+            Card::new(Four, Spade),
+            Card::new(Three, Denari),
+        ];
+        let table = vec![(2, Card::new(King, Bastoni)), (3, Card::new(Two, Bastoni))];
+        assert_eq!(
+            ai.choose(&view(hand, table)),
+            Some(Card::new(Three, Denari))
+        );
+    }
+
+    #[test]
+    fn beccaccino_chooses_the_strongest_suit() {
+        let ai = Heuristic::<Beccaccino>::default();
+        let hand = vec![
+            Card::new(Three, Spade),
+            Card::new(Two, Spade),
+            Card::new(Four, Coppe),
+            Card::new(Ace, Denari),
+        ];
+        let view = PlayerView {
+            briscola: None,
+            ..view(hand, vec![])
+        };
+        assert_eq!(ai.choose(&view), Some(Card::new(Three, Spade)));
+    }
+
+    fn play_game(mut game: Box<dyn Game>, players: i64, ai: &dyn Strategy) {
+        game.init();
+        for id in 0..players {
+            game.add_player(player(id)).unwrap();
+        }
+        game.start();
+        loop {
+            let next = game.get_next_player().unwrap();
+            let card = ai.choose_card(game.as_ref(), &next).unwrap();
+            let status = game.handle_move(&next, card);
+            assert!(!matches!(status[0], GameStatus::InvalidMove(_)));
+            if status.iter().any(|x| matches!(x, GameStatus::GameEnded)) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn plays_whole_games() {
+        let beccaccino = Heuristic::<Beccaccino>::default();
+        let briscola = Heuristic::<Briscola>::default();
+        play_game(Box::new(Beccaccino::default()), 4, &beccaccino);
+        play_game(Box::new(Briscola::default()), 2, &briscola);
+        play_game(Box::new(Briscola::default()), 4, &briscola);
+    }
+}
@@ -0,0 +1,304 @@
+use super::Strategy;
+use crate::primitives::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Simulated games are cut short after this many moves
+const MAX_SIMULATION_MOVES: usize = 1000;
+
+/// How long the search lasts before every move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    /// A fixed number of simulated games
+    Iterations(u32),
+    /// As many simulated games as fit in this time
+    Time(Duration),
+}
+
+/// Information Set Monte Carlo Tree Search.
+/// Every iteration deals the cards hidden to the player again at random with [`Game::determinize`],
+/// goes down the tree of the moves legal in that deal and ends the game with random moves.
+/// The card played is the one that has been chosen most often.
+/// Any game that can be determinized and is played only with cards can be played.
+pub struct Ismcts {
+    budget: Budget,
+    /// How much the moves tried fewer times are preferred, in the UCB formula
+    exploration: f64,
+    /// Where the random choices come from, a random seed if it's not given
+    seed: Option<u64>,
+}
+
+/// A move in the tree of the search
+struct Node {
+    /// The card played to get here and who played it, `None` for the root
+    card: Option<(Player, Card)>,
+    children: Vec<usize>,
+    visits: u32,
+    /// How many times the move was legal when its parent was visited
+    available: u32,
+    /// The sum of the results of the player who made the move
+    reward: f64,
+}
+
+impl Node {
+    fn new(card: Option<(Player, Card)>) -> Self {
+        Self {
+            card,
+            children: vec![],
+            visits: 0,
+            available: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl Ismcts {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            exploration: 0.7,
+            seed: None,
+        }
+    }
+    /// Makes the same choices every time, as long as the budget is a number of iterations
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+    fn is_over(&self, iterations: u32, start: Instant) -> bool {
+        match self.budget {
+            Budget::Iterations(x) => iterations >= x,
+            Budget::Time(x) => start.elapsed() >= x,
+        }
+    }
+    /// The share of the points each player's team got, from 0 to 1
+    fn results(game: &dyn Game) -> Vec<(Vec<Player>, f64)> {
+        let scores: Vec<(Vec<Player>, f64)> = game
+            .get_scores()
+            .into_iter()
+            .map(|x| {
+                let score = match (x.1.numer(), x.1.denom()) {
+                    (Some(n), Some(d)) => *n as f64 / *d as f64,
+                    _ => 0.0,
+                };
+                (x.0, score)
+            })
+            .collect();
+        let total: f64 = scores.iter().map(|x| x.1).sum();
+        let teams = scores.len().max(1) as f64;
+        scores
+            .into_iter()
+            .map(|x| {
+                let share = if total > 0.0 {
+                    x.1 / total
+                } else {
+                    1.0 / teams
+                };
+                (x.0, share)
+            })
+            .collect()
+    }
+    /// One iteration of the search on a determinization of the game
+    fn iterate(&self, tree: &mut Vec<Node>, mut game: Box<dyn Game>, rng: &mut StdRng) {
+        let mut path = vec![0];
+        let mut node = 0;
+        let mut ended = false;
+        // Scendo nell'albero finché trovo una mossa mai provata
+        while let Some(player) = game.get_next_player() {
+            let moves = game.legal_moves(&player);
+            if moves.is_empty() {
+                break;
+            }
+            let available: Vec<usize> = tree[node]
+                .children
+                .iter()
+                .copied()
+                .filter(|x| match &tree[*x].card {
+                    Some((p, c)) => p == &player && moves.contains(c),
+                    None => false,
+                })
+                .collect();
+            let untried: Vec<Card> = moves
+                .iter()
+                .copied()
+                .filter(|c| {
+                    !available
+                        .iter()
+                        .any(|x| tree[*x].card.as_ref().unwrap().1 == *c)
+                })
+                .collect();
+            for x in &available {
+                tree[*x].available += 1;
+            }
+            let expanded = !untried.is_empty();
+            let child = match untried.choose(rng) {
+                Some(card) => {
+                    tree.push(Node::new(Some((player.clone(), *card))));
+                    let child = tree.len() - 1;
+                    tree[child].available = 1;
+                    tree[node].children.push(child);
+                    child
+                }
+                None => {
+                    let ucb = |x: &usize| {
+                        let n = &tree[*x];
+                        n.reward / n.visits as f64
+                            + self.exploration
+                                * ((n.available as f64).ln() / n.visits as f64).sqrt()
+                    };
+                    *available
+                        .iter()
+                        .max_by(|a, b| ucb(a).partial_cmp(&ucb(b)).unwrap())
+                        .unwrap()
+                }
+            };
+            let card = tree[child].card.as_ref().unwrap().1;
+            let status = game.handle_move(&player, card);
+            path.push(child);
+            node = child;
+            ended = status.iter().any(|x| matches!(x, GameStatus::GameEnded));
+            if ended || expanded {
+                break;
+            }
+        }
+        // Finisco la partita a caso
+        let mut moves = 0;
+        while !ended && moves < MAX_SIMULATION_MOVES {
+            let player = match game.get_next_player() {
+                Some(x) => x,
+                None => break,
+            };
+            let card = match game.legal_moves(&player).choose(rng) {
+                Some(x) => *x,
+                None => break,
+            };
+            let status = game.handle_move(&player, card);
+            ended = status.iter().any(|x| matches!(x, GameStatus::GameEnded));
+            moves += 1;
+        }
+        let results = Self::results(game.as_ref());
+        for x in path {
+            let node = &mut tree[x];
+            node.visits += 1;
+            if let Some((player, _)) = &node.card {
+                node.reward += results
+                    .iter()
+                    .find(|y| y.0.contains(player))
+                    .map(|y| y.1)
+                    .unwrap_or(0.0);
+            }
+        }
+    }
+}
+
+impl Strategy for Ismcts {
+    fn choose_card(&self, game: &dyn Game, player: &Player) -> Option<Card> {
+        let legal = game.legal_moves(player);
+        if legal.len() <= 1 {
+            return legal.first().copied();
+        }
+        let mut rng = match self.seed {
+            Some(x) => StdRng::seed_from_u64(x),
+            None => StdRng::from_entropy(),
+        };
+        let mut tree = vec![Node::new(None)];
+        let start = Instant::now();
+        let mut iterations = 0;
+        while !self.is_over(iterations, start) {
+            let determinization = match game.determinize(player, rng.gen()) {
+                Some(x) => x,
+                // Senza poter immaginare le carte degli altri gioco a caso
+                None => return legal.choose(&mut rng).copied(),
+            };
+            self.iterate(&mut tree, determinization, &mut rng);
+            iterations += 1;
+        }
+        tree[0]
+            .children
+            .iter()
+            .map(|x| &tree[*x])
+            .filter(|x| match &x.card {
+                Some((_, c)) => legal.contains(c),
+                None => false,
+            })
+            .max_by_key(|x| x.visits)
+            .and_then(|x| x.card.as_ref())
+            .map(|x| x.1)
+            .or_else(|| legal.first().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{beccaccino::Beccaccino, briscola::Briscola, tressette::Tressette};
+    use crate::testing;
+
+    fn new_game(mut game: Box<dyn Game>, players: i64) -> Box<dyn Game> {
+        game.init();
+        testing::add_players(game.as_mut(), players);
+        game.start();
+        game
+    }
+
+    #[test]
+    fn plays_whole_games() {
+        let ai = Ismcts::new(Budget::Iterations(10)).with_seed(1);
+        for (game, players) in [
+            (Box::new(Briscola::default()) as Box<dyn Game>, 2),
+            (Box::new(Beccaccino::default()), 4),
+            (Box::new(Tressette::default()), 4),
+        ] {
+            let mut game = new_game(game, players);
+            loop {
+                let next = game.get_next_player().unwrap();
+                let card = ai.choose_card(game.as_ref(), &next).unwrap();
+                let status = game.handle_move(&next, card);
+                assert!(!matches!(status[0], GameStatus::InvalidMove(_)));
+                if status.iter().any(|x| matches!(x, GameStatus::GameEnded)) {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn determinizations_keep_what_the_player_sees() {
+        let game = new_game(Box::new(Briscola::default()), 2);
+        let player = game.get_next_player().unwrap();
+        let other = game
+            .get_players()
+            .into_iter()
+            .find(|x| x != &player)
+            .unwrap();
+        let view = game.view_for(&player);
+        let changed = (0..10).any(|seed| {
+            let copy = game.determinize(&player, seed).unwrap();
+            let copy_view = copy.view_for(&player);
+            assert_eq!(copy_view, view);
+            copy.view_for(&other).hand != game.view_for(&other).hand
+        });
+        assert!(changed);
+    }
+
+    #[test]
+    fn same_seed_same_choice() {
+        let mut game = new_game(Box::new(Briscola::default()), 2);
+        let first = game.get_next_player().unwrap();
+        let lead = game.legal_moves(&first)[0];
+        game.handle_move(&first, lead);
+        let second = game.get_next_player().unwrap();
+        let choose = |seed| {
+            Ismcts::new(Budget::Iterations(100))
+                .with_seed(seed)
+                .choose_card(game.as_ref(), &second)
+                .unwrap()
+        };
+        assert!(game.legal_moves(&second).contains(&choose(3)));
+        assert_eq!(choose(3), choose(3));
+    }
+}
@@ -0,0 +1,124 @@
+//! Computer players, which choose their moves from what they can see of the game.
+//! Strategies get the whole game, but they must only look at what their player can see:
+//! the [`PlayerView`](crate::view::PlayerView) of the seat, or games where the cards hidden
+//! to the player have been dealt again at random by [`Game::determinize`].
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::GameState;
+use crate::utils::Shuffler;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+mod heuristic;
+mod ismcts;
+
+pub use heuristic::Heuristic;
+pub use ismcts::{Budget, Ismcts};
+
+/// How a computer player chooses its moves
+pub trait Strategy: Send {
+    /// The card `player` plays among their legal moves,
+    /// or `None` if they can't move by playing a card right now
+    fn choose_card(&self, game: &dyn Game, player: &Player) -> Option<Card>;
+}
+
+/// How strong a computer player is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Level {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Level {
+    pub const ALL: [Level; 3] = [Level::Easy, Level::Medium, Level::Hard];
+    /// How long the [`Ismcts`] player thinks before every move
+    pub fn budget(&self) -> Budget {
+        match self {
+            Level::Easy => Budget::Iterations(50),
+            Level::Medium => Budget::Iterations(500),
+            Level::Hard => Budget::Time(std::time::Duration::from_secs(2)),
+        }
+    }
+    /// The code of the level, to put it in the buttons of the bot
+    pub fn code(&self) -> &'static str {
+        match self {
+            Level::Easy => "easy",
+            Level::Medium => "medium",
+            Level::Hard => "hard",
+        }
+    }
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.code() == code)
+    }
+    pub fn name(&self) -> Text {
+        match self {
+            Level::Easy => Text::new("facile"),
+            Level::Medium => Text::new("medio"),
+            Level::Hard => Text::new("difficile"),
+        }
+    }
+}
+
+/// The strategy playing the game at the given level, if there's one.
+/// The easy level is the [`Heuristic`] player where the game has one, the others
+/// and the games without it are [`Ismcts`], as long as the game can be determinized.
+pub fn strategy_for(game: &dyn Game, level: Level) -> Option<Box<dyn Strategy>> {
+    let someone = Player {
+        id: 0,
+        name: String::new(),
+    };
+    let heuristic = heuristic_for(&game.snapshot().state);
+    if level == Level::Easy && heuristic.is_some() {
+        heuristic
+    } else if game.determinize(&someone, 0).is_some() {
+        Some(Box::new(Ismcts::new(level.budget())))
+    } else {
+        heuristic
+    }
+}
+
+fn heuristic_for(state: &GameState) -> Option<Box<dyn Strategy>> {
+    use crate::games::{beccaccino::Beccaccino, briscola::Briscola};
+    match state {
+        GameState::Briscola(_) => Some(Box::new(Heuristic::<Briscola>::default())),
+        GameState::Beccaccino(_) => Some(Box::new(Heuristic::<Beccaccino>::default())),
+        GameState::Match(x) => heuristic_for(x.template()),
+        _ => None,
+    }
+}
+
+/// Games whose hidden cards can be dealt again, to imagine what the other players might have
+pub trait HiddenCards: Game + Clone + 'static {
+    /// Where the cards `player` can't see are: the hands of the others, the stock...
+    fn hidden_cards(&mut self, player: &Player) -> Vec<&mut [Card]>;
+}
+
+/// A copy of the game where the cards hidden to `player` have been shuffled among the places they were in,
+/// and the decks of the next hands will be shuffled in a different way
+pub fn determinize<G: HiddenCards, R: Rng>(game: &G, player: &Player, rng: &mut R) -> G {
+    let mut res = game.clone();
+    res.set_shuffler(Shuffler::from_rng(rng));
+    let mut places = res.hidden_cards(player);
+    let mut cards: Vec<Card> = places.iter().flat_map(|x| x.iter().copied()).collect();
+    cards.shuffle(rng);
+    let mut cards = cards.into_iter();
+    for place in places.iter_mut() {
+        for card in place.iter_mut() {
+            *card = cards.next().unwrap();
+        }
+    }
+    res
+}
+
+/// [`determinize`] with a random number generator seeded with `seed`, for the implementations of [`Game::determinize`]
+pub(crate) fn determinize_boxed<G: HiddenCards>(
+    game: &G,
+    player: &Player,
+    seed: u64,
+) -> Option<Box<dyn Game>> {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    Some(Box::new(determinize(game, player, &mut rng)))
+}
@@ -0,0 +1,193 @@
+//! Commit-reveal for the shuffles of a game.
+//! A commitment to the seed of the [`Shuffler`] is announced before the cards are dealt
+//! and the seed is revealed when the game ends, together with the [`record`] of the shuffles,
+//! so that players can deal again every deck of the game from that seed and check
+//! that they weren't chosen while the game was going on.
+use crate::primitives::*;
+use crate::utils::{Shuffle, Shuffler};
+use sha2::{Digest, Sha256};
+
+/// The SHA-256, in hexadecimal, of the seed (eight bytes, big endian)
+/// followed by two bytes for each card of the deck: the number of the rank (see [`Rank::number`])
+/// and the position of the suit in [`Suit`] (spade 0, coppe 1, denari 2, bastoni 3,
+/// hearts 4, diamonds 5, clubs 6, spades 7, joker 8)
+pub fn commitment(seed: u64, deck: &[Card]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    for card in deck {
+        hasher.update([card.rank.number(), card.suit as u8]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+
+/// The commitment of a shuffler that hasn't been given stacked decks:
+/// the seed together with the first deck of the given type shuffled with it.
+/// Every other deck of the game, even the ones of the shufflers [`split`](Shuffler::split)
+/// from it, comes from the seed and is dealt again by [`verify`].
+pub fn commitment_of(shuffler: &Shuffler, of_type: CardDeckType) -> String {
+    let seed = shuffler.get_seed();
+    commitment(seed, &Shuffler::new(seed).deck(of_type))
+}
+
+/// The shuffles made by the shuffler, in a few letters: `b` for a deck of Italian cards,
+/// `p` for a poker deck, `m` for cards of the game shuffled again,
+/// and the shuffles of each game played inside this one, like a hand, between brackets
+pub fn record(shuffler: &Shuffler) -> String {
+    fn write(history: &[Shuffle], res: &mut String) {
+        for shuffle in history {
+            match shuffle {
+                Shuffle::Deck(CardDeckType::Briscola) => res.push('b'),
+                Shuffle::Deck(CardDeckType::Poker) => res.push('p'),
+                Shuffle::Cards => res.push('m'),
+                Shuffle::Split(x) => {
+                    res.push('(');
+                    write(x, res);
+                    res.push(')');
+                }
+            }
+        }
+    }
+    let mut res = String::new();
+    write(shuffler.get_history(), &mut res);
+    res
+}
+
+/// The shuffles written by [`record`], `None` if it isn't valid
+pub fn parse_record(record: &str) -> Option<Vec<Shuffle>> {
+    fn read(chars: &mut std::str::Chars, nested: bool) -> Option<Vec<Shuffle>> {
+        let mut res = vec![];
+        loop {
+            match chars.next() {
+                Some('b') => res.push(Shuffle::Deck(CardDeckType::Briscola)),
+                Some('p') => res.push(Shuffle::Deck(CardDeckType::Poker)),
+                Some('m') => res.push(Shuffle::Cards),
+                Some('(') => res.push(Shuffle::Split(read(chars, true)?)),
+                Some(')') if nested => return Some(res),
+                None if !nested => return Some(res),
+                _ => return None,
+            }
+        }
+    }
+    read(&mut record.trim().to_lowercase().chars(), false)
+}
+
+/// A deck dealt again by [`verify`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dealt {
+    /// The hand the deck was shuffled for, counting from one, in games made of several hands
+    pub hand: Option<usize>,
+    pub deck: Vec<Card>,
+}
+
+/// Checks a revealed seed against the commitment announced at the start of the game.
+/// If they match, shuffles again from the seed the decks of the [`record`], in the order
+/// they were used. Without a record, only the first deck is shuffled again.
+pub fn verify(seed: u64, commitment: &str, record: &[Shuffle]) -> Option<Vec<Dealt>> {
+    fn deal(
+        shuffler: &mut Shuffler,
+        record: &[Shuffle],
+        hand: Option<usize>,
+        res: &mut Vec<Dealt>,
+    ) {
+        let mut hands = 0;
+        for shuffle in record {
+            match shuffle {
+                Shuffle::Deck(x) => res.push(Dealt {
+                    hand,
+                    deck: shuffler.deck(*x),
+                }),
+                // Le carte rimescolate non si possono ricostruire, ma il mescolamento conta
+                Shuffle::Cards => shuffler.shuffle(&mut []),
+                Shuffle::Split(x) => {
+                    hands += 1;
+                    deal(&mut shuffler.split(), x, hand.or(Some(hands)), res);
+                }
+            }
+        }
+    }
+    let commitment = commitment.trim().to_lowercase();
+    let of_type = [CardDeckType::Briscola, CardDeckType::Poker]
+        .iter()
+        .copied()
+        .find(|x| self::commitment(seed, &Shuffler::new(seed).deck(*x)) == commitment)?;
+    let first = [Shuffle::Deck(of_type)];
+    let record = if record.is_empty() {
+        &first[..]
+    } else {
+        record
+    };
+    let mut res = vec![];
+    deal(&mut Shuffler::new(seed), record, None, &mut res);
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{briscola::Briscola, marafone};
+    use crate::testing;
+
+    #[test]
+    fn revealed_seed_matches_the_deal() {
+        let shuffler = Shuffler::new(1234);
+        let announced = commitment_of(&shuffler, CardDeckType::Briscola);
+        let mut game = Briscola::default();
+        game.set_shuffler(shuffler);
+        game.init();
+        let players = testing::add_players(&mut game, 2);
+        game.start();
+        // Il seed rivelato a fine partita ridà le carte distribuite
+        let seed = game.get_shuffler().get_seed();
+        let record = parse_record(&record(game.get_shuffler())).unwrap();
+        let dealt = verify(seed, &announced, &record).unwrap();
+        assert_eq!(dealt.len(), 1);
+        assert_eq!(
+            game.legal_moves(&players[0]),
+            dealt[0].deck[37..]
+                .iter()
+                .rev()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(verify(seed + 1, &announced, &record), None);
+        assert_eq!(verify(seed, &announced.to_uppercase(), &[]), Some(dealt));
+    }
+
+    #[test]
+    fn every_hand_is_dealt_again() {
+        let mut game = marafone::new();
+        game.set_shuffler(Shuffler::new(99));
+        let announced = commitment_of(game.get_shuffler(), game.get_card_set());
+        testing::add_players(&mut game, 4);
+        game.start();
+        let mut hands = vec![];
+        while game.get_next_player().is_some() {
+            let hand = game.view_for(&game.get_players()[0]).hand;
+            if hands.last() != Some(&hand) && hand.len() == 10 {
+                hands.push(hand);
+            }
+            let player = game.get_next_player().unwrap();
+            let card = game.legal_moves(&player)[0];
+            game.handle_move(&player, card);
+        }
+        let written = record(game.get_shuffler());
+        assert_eq!(written, "(b)".repeat(hands.len()));
+        let dealt = verify(99, &announced, &parse_record(&written).unwrap()).unwrap();
+        // Ogni mano ha il suo mazzo, da cui il primo giocatore ha ricevuto le sue carte
+        for (i, hand) in hands.iter_mut().enumerate() {
+            assert_eq!(dealt[i].hand, Some(i + 1));
+            hand.sort();
+            assert!(dealt[i].deck.chunks(10).any(|x| {
+                let mut x = x.to_vec();
+                x.sort();
+                &x == hand
+            }));
+        }
+        assert_eq!(parse_record("(b)(bm"), None);
+        assert_eq!(parse_record("x"), None);
+    }
+}
@@ -1,28 +1,121 @@
+use crate::i18n::Text;
 use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::tricks::{Played, Teams, TrickRules, Tricks};
 use crate::utils;
+use crate::view::PlayerView;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+/// Cards in hand for each player
+const HAND_SIZE: usize = 10;
+/// Points for choosing the briscola with its ace, two and three
+const MARAFONA_POINTS: u8 = 3;
+
+/// What's done with the thirds of a point left at the end of the hand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Thirds {
+    /// They're counted as they are
+    Exact,
+    /// They're dropped
+    Down,
+    /// They make a whole point
+    Up,
+}
+
+/// How Beccaccino is played where the game was created
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    /// The briscola is chosen by who has the 4 of denara, otherwise by the first player
+    pub four_of_denari: bool,
+    pub thirds: Thirds,
+    /// Who chooses the briscola with its ace, two and three gets three more points
+    pub marafona: bool,
+}
+
+impl Rules {
+    /// Marafone (or Beccaccino romagnolo): the first player chooses the briscola,
+    /// the thirds are dropped and the marafona is worth three points.
+    /// The match to 41 is made by [`marafone::new`](super::marafone::new).
+    pub const MARAFONE: Rules = Rules {
+        four_of_denari: false,
+        thirds: Thirds::Down,
+        marafona: true,
+    };
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            four_of_denari: true,
+            thirds: Thirds::Exact,
+            marafona: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Beccaccino {
-    players: Vec<Player>,
-    in_hand: Vec<Vec<Card>>,
-    briscola: Option<CardSuit>,
-    table: Vec<(Player, Card)>,
-    won_cards: Vec<(Vec<Card>, bool)>,
-    next_player: Option<usize>,
+    /// The first and third player against the second and fourth, the briscola is the trump
+    tricks: Tricks<Beccaccino>,
+    rules: Rules,
+    /// Who chose the briscola with its ace, two and three, when the marafona is played
+    marafona: Option<usize>,
+    shuffler: utils::Shuffler,
 }
 
 impl Beccaccino {
+    pub fn with_rules(rules: Rules) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
     /// WHo's got the 4 of denara? Well, he's to choose the briscola!!
     fn get_choosing_player(&self) -> usize {
-        self.in_hand
+        if !self.rules.four_of_denari {
+            return 0;
+        }
+        self.tricks
+            .hands()
             .iter()
-            .position(|x| {
-                x.iter()
-                    .any(|y| y == &(CardType::Numeric(4), CardSuit::Denari))
-            })
+            .position(|x| x.iter().any(|y| y == &Card::new(Rank::Four, Suit::Denari)))
             .unwrap()
     }
+    /// The briscola is chosen by playing a card of its suit, which stays in hand
+    fn choose_briscola(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let choosing_player = self.get_choosing_player();
+        let hand = &self.tricks.hands()[choosing_player];
+        if by != &self.tricks.players()[choosing_player] {
+            vec![GameStatus::InvalidMove(GameError::NotYourChoice)]
+        } else if !hand.contains(&card) {
+            vec![GameStatus::InvalidMove(GameError::CardNotInHand)]
+        } else {
+            let hand = hand.clone();
+            let marafona = [Rank::Ace, Rank::Two, Rank::Three]
+                .iter()
+                .all(|x| hand.contains(&Card::new(*x, card.suit)));
+            self.marafona = Some(choosing_player).filter(|_| self.rules.marafona && marafona);
+            self.tricks.set_trump(Some(card.suit));
+            let mut res = vec![
+                GameStatus::BriscolaChosen(by.clone(), card.suit),
+                GameStatus::WaitingForChoice(by.clone(), hand),
+            ];
+            if self.marafona.is_some() {
+                res.insert(
+                    0,
+                    GameStatus::NotifyRoom(text!("{} ha la marafona!", by.name)),
+                );
+            }
+            res
+        }
+    }
+}
+
+/// Players must follow the suit led if they can
+impl TrickRules for Beccaccino {
+    const FOLLOW_SUIT: bool = true;
 }
 
 impl Game for Beccaccino {
@@ -33,122 +126,114 @@ impl Game for Beccaccino {
         CardDeckType::Briscola
     }
     fn get_name(&self) -> &str {
-        "Beccaccino"
+        if self.rules.marafona {
+            "Marafone"
+        } else {
+            "Beccaccino"
+        }
     }
     fn init(&mut self) {}
-    fn add_player(&mut self, player: Player) -> Result<GameStatus, &str> {
-        if self.players.len() > 4 {
-            Err("La partita è al completo")
-        } else if !self.in_hand[0].is_empty() {
-            Err("La partita è già cominciata")
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.tricks.players().len() >= 4 {
+            Err(GameError::GameFull)
+        } else if self.tricks.is_dealt() {
+            Err(GameError::AlreadyStarted)
         } else {
-            self.players.push(player.clone());
-            if self.players.len() == 4 {
+            self.tricks.add_player(player.clone());
+            if self.tricks.players().len() == 4 {
                 Ok(GameStatus::WaitingForPlayers(true, player))
             } else {
                 Ok(GameStatus::WaitingForPlayers(false, player))
             }
         }
     }
-    fn get_card_rank(card: &CardType) -> fraction::GenericFraction<u8> {
-        match card {
-            CardType::Jack => fraction::GenericFraction::new(1u8, 3u8),
-            CardType::Queen => fraction::GenericFraction::new(1u8, 3u8),
-            CardType::King => fraction::GenericFraction::new(1u8, 3u8),
-            CardType::Numeric(x) => match x {
-                1 => fraction::GenericFraction::new(1u8, 1u8),
-                2 => fraction::GenericFraction::new(1u8, 3u8),
-                3 => fraction::GenericFraction::new(1u8, 3u8),
-                _ => fraction::GenericFraction::new(0u8, 3u8),
-            },
-            CardType::Jolly => fraction::GenericFraction::new(0u8, 0u8),
-        }
-    }
-    fn get_card_sorting_rank(card: &CardType) -> u8 {
-        match card {
-            CardType::Jack => 5,
-            CardType::Queen => 6,
-            CardType::King => 7,
-            CardType::Numeric(x) => match x {
-                1 => 8,
-                2 => 9,
-                3 => 10,
-                7 => 4,
-                6 => 3,
-                5 => 2,
-                _ => 1,
-            },
-            CardType::Jolly => 0,
-        }
-    }
     fn start(&mut self) -> GameStatus {
         // Se la partita è già cominciata segnalo l'errore
-        if !self.in_hand[0].is_empty() {
-            return GameStatus::InvalidMove("Il gioco è già iniziato, non puoi farlo reiniziare!");
+        if self.tricks.is_dealt() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
         }
         // Genero il mazzo e do le carte
-        let deck = utils::random_deck(CardDeckType::Briscola);
-        for i in 0..4 {
-            self.in_hand[i].extend_from_slice(&deck[i * 10..(i + 1) * 10]);
-        }
+        let deck = self.shuffler.deck(CardDeckType::Briscola);
+        self.tricks.deal(deck, HAND_SIZE);
         // Determino chi ha la briscola
         let choosing_player = self.get_choosing_player();
-        self.next_player = Some(choosing_player);
+        self.tricks.set_next_player(Some(choosing_player));
         GameStatus::WaitingForChoiceCustomMessage(
-            self.players[choosing_player].clone(),
-            self.in_hand[choosing_player].clone(),
-            "Scegli quale sarà il seme seme di briscola",
+            self.tricks.players()[choosing_player].clone(),
+            self.tricks.hands()[choosing_player].clone(),
+            Text::new("Scegli quale sarà il seme di briscola"),
         )
     }
     fn get_next_player(&self) -> Option<Player> {
-        self.next_player.map(|x| self.players[x].clone())
+        self.tricks
+            .next_player()
+            .map(|x| self.tricks.players()[x].clone())
     }
     fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
-        vec![vec![0usize, 2], vec![1, 3]]
-            .iter()
-            .zip(self.won_cards.iter())
+        self.tricks
+            .teams()
+            .into_iter()
+            .enumerate()
             .map(|y| {
-                let player_lst = y.0.iter().map(|z| self.players[*z].clone()).collect();
                 let score = {
-                    if (y.1).1 {
+                    if self.tricks.last_trick() == Some(y.0) {
                         utils::one()
                     } else {
                         utils::zero()
                     }
-                } + (y.1)
-                    .0
+                } + self
+                    .tricks
+                    .won_cards(y.0)
                     .iter()
-                    .map(|x| Self::get_card_rank(&x.0))
-                    .fold(fraction::GenericFraction::new(0u8, 0u8), |acc, x| acc + x);
-                (player_lst, score)
+                    .map(|x| Self::get_card_rank(&x.rank))
+                    .fold(utils::zero(), |acc, x| acc + x);
+                let score = match self.rules.thirds {
+                    Thirds::Exact => score,
+                    Thirds::Down => score.floor(),
+                    Thirds::Up => score.ceil(),
+                };
+                let score = match self.marafona {
+                    Some(x) if self.tricks.team_of(x) == y.0 => {
+                        score + fraction::GenericFraction::new(MARAFONA_POINTS, 1u8)
+                    }
+                    _ => score,
+                };
+                (y.1, score)
             })
             .collect()
     }
-    fn get_status(&self) -> String {
-        format!(
+    fn get_status(&self) -> Text {
+        text!(
             "Partita di {}\nPunteggi:\n{}\nBriscola è: {}\nTocca a: {}\nCarte sul tavolo:\n{}",
             self.get_name(),
-            self.get_scores()
-                .iter()
-                .enumerate()
-                .map(|x| format!(
+            Text::join(
+                self.get_scores().into_iter().map(|x| text!(
                     "{}: {} punti",
-                    (x.1).0.iter().map(|y| y.name.clone()).join(", "),
-                    (x.1).1
-                ))
-                .join("\n"),
-            &self
-                .briscola
-                .clone()
-                .map(|x| String::from(&x))
-                .unwrap_or_else(|| "non ancora scelta".to_owned()),
+                    x.0.iter().map(|y| y.name.clone()).join(", "),
+                    x.1
+                )),
+                "\n"
+            ),
+            self.tricks
+                .trump()
+                .map(|x| text!("{}", x))
+                .unwrap_or_else(|| Text::new("non ancora scelta")),
             self.get_next_player()
                 .map(|x| x.name)
                 .unwrap_or_else(|| "".to_owned()),
-            self.table
-                .iter()
-                .map(|x| format!("- {} ({})", utils::get_card_name(&x.1), x.0.name))
-                .join("\n")
+            Text::join(
+                self.tricks
+                    .table()
+                    .iter()
+                    .map(|x| text!("- {} ({})", x.1, &x.0.name)),
+                "\n"
+            )
         )
     }
     fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
@@ -157,130 +242,110 @@ impl Game for Beccaccino {
         e gioca una carta.
         Le carte sul tavolo devono essere dello stesso seme o, se uno le ha finite, di qualsiasi altro seme
         */
-        if (&self.briscola).is_none() {
-            let choosing_player = self.get_choosing_player();
-            if by == &self.players[choosing_player] {
-                self.briscola = Some(card.1);
-                vec![GameStatus::WaitingForChoice(
-                    by.clone(),
-                    self.in_hand[choosing_player].clone(),
-                )]
-            } else {
-                vec![GameStatus::InvalidMove(
-                    "Non tocca a te scegliere la briscola!",
-                )]
+        if !self.tricks.is_dealt() {
+            return vec![GameStatus::InvalidMove(GameError::GameNotStarted)];
+        } else if self.tricks.trump().is_none() {
+            return self.choose_briscola(by, card);
+        }
+        let next_player = match self.tricks.play(by, card) {
+            Err(GameError::MustFollowSuit) => {
+                // Sta barando, fermiamolo!
+                return vec![
+                    GameStatus::InvalidMove(GameError::MustFollowSuit),
+                    GameStatus::WaitingForChoice(by.clone(), self.tricks.hand_of(by)),
+                ];
             }
-        } else {
-            // bloccare mossa se non è il tuo turno
-            if self.get_next_player().is_none() {
-                return vec![GameStatus::InvalidMove("La partita non è ancora iniziata")];
-            } else if &self.get_next_player().unwrap() != by {
-                return vec![GameStatus::InvalidMove("Non è ancora il tuo turno!")];
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+            Ok(Played::Trick { winner, .. }) if self.tricks.is_over() => {
+                return vec![
+                    GameStatus::RoundWon(self.tricks.players()[winner].clone()),
+                    GameStatus::GameEnded,
+                    GameStatus::NotifyRoom(self.get_status()),
+                ];
             }
-            let player_index = self.players.iter().position(|x| x == by).unwrap();
-            let next_player_index = (player_index + 1) % 4;
-            if self.table.is_empty() {
-                // è la prima carta, salto le limitazioni del seme
-                let card_index = self.in_hand[player_index]
-                    .iter()
-                    .position(|x| x.clone() == card)
-                    .expect("Non trovo la carta");
-                self.in_hand[player_index].remove(card_index);
-                self.table.push((by.clone(), card));
-                self.next_player = Some(next_player_index);
-            } else if self.table.len() < 4 {
-                // Controllo il seme
-                if card.1 == (self.table[0].1).1 {
-                    // Il seme è giusto, aggiungo
-                    let card_index = self.in_hand[player_index]
-                        .iter()
-                        .position(|x| x.clone() == card)
-                        .expect("Non trovo la carta");
-                    self.in_hand[player_index].remove(card_index);
-                    self.table.push((by.clone(), card));
-                    self.next_player = Some(next_player_index);
-                //vec![GameStatus::WaitingForChoice(self.players[next_player_index].clone(), self.in_hand[next_player_index].clone())]
-                } else {
-                    let first_suit = &(self.table[0].1).1;
-                    if self.in_hand[player_index]
-                        .iter()
-                        .any(|x| &(x.1) == first_suit)
-                    {
-                        // Sta barando, fermiamolo!
-                        return vec![
-                            GameStatus::InvalidMove(
-                                "Devi giocare una carta dello stesso seme della prima!",
-                            ),
-                            GameStatus::WaitingForChoice(
-                                by.clone(),
-                                self.in_hand[player_index].clone(),
-                            ),
-                        ];
-                    } else {
-                        let card_index = self.in_hand[player_index]
-                            .iter()
-                            .position(|x| x.clone() == card)
-                            .expect("Non trovo la carta");
-                        self.in_hand[player_index].remove(card_index);
-                        self.next_player = Some(next_player_index);
-                        //vec![GameStatus::WaitingForChoice(self.players[next_player_index].clone(), self.in_hand[next_player_index].clone())]
-                    }
-                }
+            // Dopo una presa tocca a chi l'ha vinta
+            Ok(Played::Trick { winner, .. }) => winner,
+            Ok(Played::Next(x)) => x,
+        };
+        let next_player = self.tricks.players()[next_player].clone();
+        vec![
+            GameStatus::WaitingForChoice(next_player.clone(), self.tricks.hand_of(&next_player)),
+            GameStatus::NotifyUser(next_player, self.get_status()),
+        ]
+    }
+    /// While the briscola is being chosen, any card of the chooser gives its suit
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        self.tricks.legal_moves(player)
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.tricks.players().to_vec()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::with_rules(self.rules))
+    }
+    fn variants(&self) -> Vec<Variant> {
+        vec![
+            Variant::switch(
+                "quattro",
+                Text::new("Briscola scelta da chi ha il 4 di denara"),
+                self.rules.four_of_denari,
+            ),
+            Variant {
+                code: "terzi",
+                name: Text::new("Terzi di punto"),
+                options: vec![
+                    ("esatti", Text::new("contati esatti")),
+                    ("difetto", Text::new("arrotondati per difetto")),
+                    ("eccesso", Text::new("arrotondati per eccesso")),
+                ],
+                chosen: match self.rules.thirds {
+                    Thirds::Exact => "esatti",
+                    Thirds::Down => "difetto",
+                    Thirds::Up => "eccesso",
+                },
+            },
+            Variant::switch(
+                "marafona",
+                Text::new("Tre punti a chi sceglie la briscola con asso, due e tre"),
+                self.rules.marafona,
+            ),
+        ]
+    }
+    fn set_variant(&mut self, variant: &str, option: &str) -> Result<(), GameError> {
+        if self.tricks.is_dealt() {
+            return Err(GameError::AlreadyStarted);
+        }
+        match (variant, option) {
+            ("quattro", x) => {
+                self.rules.four_of_denari = Variant::is_on(x).ok_or(GameError::NoSuchVariant)?
             }
-            if self.table.len() == 4 {
-                // Se è il tavolo è pieno
-                // Calcolo il vincitore
-                let mut winner = (self.table[0]).clone().0;
-                let mut winning_suit = (&(self.table[0]).1).clone().1;
-                let mut max: i32 = -1;
-                for (player, card) in &self.table {
-                    if card.1 == self.briscola.clone().unwrap()
-                        && winning_suit != self.briscola.clone().unwrap()
-                    {
-                        let temp_suit = self.briscola.clone().unwrap();
-                        winning_suit = temp_suit;
-                        max = Self::get_card_sorting_rank(&card.0) as i32;
-                        winner = player.clone();
-                    }
-                    if card.1 == winning_suit && Self::get_card_sorting_rank(&card.0) as i32 > max {
-                        max = Self::get_card_sorting_rank(&card.0) as i32;
-                        winner = player.clone();
-                    }
-                }
-                // Abbiamo determinato chi ha vinto la mano, assegnamogliela
-                let winner_index = self.players.iter().position(|x| x == &winner).unwrap();
-                let winner_team_index = winner_index % 2;
-                self.won_cards[winner_team_index]
-                    .0
-                    .append(&mut self.table.iter().map(|x| x.1.clone()).collect());
-                self.table.clear(); // Just in case...
-                if self.in_hand.iter().map(|x| x.len()).max().unwrap() == 0 {
-                    self.won_cards[winner_team_index].1 = true;
-                    return vec![
-                        GameStatus::RoundWon(self.players[winner_index].clone()),
-                        GameStatus::GameEnded,
-                        GameStatus::NotifyRoom(self.get_status()),
-                    ];
-                } else {
-                    self.next_player = Some(winner_index);
-                }
+            ("terzi", "esatti") => self.rules.thirds = Thirds::Exact,
+            ("terzi", "difetto") => self.rules.thirds = Thirds::Down,
+            ("terzi", "eccesso") => self.rules.thirds = Thirds::Up,
+            ("marafona", x) => {
+                self.rules.marafona = Variant::is_on(x).ok_or(GameError::NoSuchVariant)?
             }
-            vec![
-                GameStatus::WaitingForChoice(
-                    self.players[next_player_index].clone(),
-                    self.in_hand[next_player_index].clone(),
-                ),
-                GameStatus::NotifyUser(self.players[next_player_index].clone(), self.get_status()),
-            ]
+            _ => return Err(GameError::NoSuchVariant),
         }
+        Ok(())
     }
-    fn get_players(&self) -> Vec<Player> {
-        self.players.clone()
+    fn determinize(&self, player: &Player, seed: u64) -> Option<Box<dyn Game>> {
+        crate::ai::determinize_boxed(self, player, seed)
     }
-    fn get_new_instance(&self) -> Box<dyn Game> {
-        Box::new(Self::default())
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.tricks.hand_of(player),
+            table: self.tricks.table_view(),
+            briscola: self.tricks.trump(),
+            captured: self.tricks.captured(),
+            hand_sizes: self.tricks.hand_sizes(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Beccaccino(self.clone()).into()
     }
-    fn handle_message(&self, message: String, from: Player) -> Vec<GameStatus> {
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
         // Check if the message is valid (striscio, busso or volo)
         match message
             .to_lowercase()
@@ -290,35 +355,157 @@ impl Game for Beccaccino {
             .collect::<String>()
             .as_str()
         {
-            "busso" => vec![GameStatus::NotifyRoom(format!(
+            "busso" => vec![GameStatus::NotifyRoom(text!(
                 "{} ha detto: Busso",
                 from.name
             ))],
-            "striscio" => vec![GameStatus::NotifyRoom(format!(
+            "striscio" => vec![GameStatus::NotifyRoom(text!(
                 "{} ha detto: Striscio",
                 from.name
             ))],
-            "volo" => vec![GameStatus::NotifyRoom(format!(
+            "volo" => vec![GameStatus::NotifyRoom(text!(
                 "{} ha detto: Volo",
                 from.name
             ))],
             _ => vec![GameStatus::NotifyUser(
                 from,
-                "Puoi dire solo busso, striscio o volo.".to_owned(),
+                Text::new("Puoi dire solo busso, striscio o volo."),
             )],
         }
     }
 }
 
+impl CardValues for Beccaccino {
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        match card {
+            Rank::Jack => fraction::GenericFraction::new(1u8, 3u8),
+            Rank::Queen => fraction::GenericFraction::new(1u8, 3u8),
+            Rank::King => fraction::GenericFraction::new(1u8, 3u8),
+            Rank::Ace => fraction::GenericFraction::new(1u8, 1u8),
+            Rank::Two => fraction::GenericFraction::new(1u8, 3u8),
+            Rank::Three => fraction::GenericFraction::new(1u8, 3u8),
+            _ => fraction::GenericFraction::new(0u8, 3u8),
+        }
+    }
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        match card {
+            Rank::Jack => 5,
+            Rank::Queen => 6,
+            Rank::King => 7,
+            Rank::Ace => 8,
+            Rank::Two => 9,
+            Rank::Three => 10,
+            Rank::Seven => 4,
+            Rank::Six => 3,
+            Rank::Five => 2,
+            Rank::Four => 1,
+            _ => 0,
+        }
+    }
+}
+
 impl Default for Beccaccino {
     fn default() -> Self {
         Self {
-            players: vec![],
-            in_hand: vec![vec![], vec![], vec![], vec![]],
-            briscola: None,
-            table: vec![],
-            won_cards: vec![(vec![], false), (vec![], false)],
-            next_player: None,
+            tricks: Tricks::new(Teams::Alternate),
+            rules: Rules::default(),
+            marafona: None,
+            shuffler: utils::Shuffler::default(),
         }
     }
 }
+
+impl crate::ai::HiddenCards for Beccaccino {
+    fn hidden_cards(&mut self, player: &Player) -> Vec<&mut [Card]> {
+        self.tricks.hidden_cards(player, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn started_game(rules: Rules) -> Beccaccino {
+        let mut game = Beccaccino::with_rules(rules);
+        game.set_shuffler(utils::Shuffler::new(42));
+        testing::started(game, 4)
+    }
+
+    #[test]
+    fn first_player_chooses_without_the_four_of_denari() {
+        let mut game = Beccaccino::default();
+        game.set_variant("quattro", "no").unwrap();
+        game.set_variant("terzi", "difetto").unwrap();
+        assert_eq!(
+            game.set_variant("terzi", "boh"),
+            Err(GameError::NoSuchVariant)
+        );
+        let game = started_game(game.rules);
+        assert_eq!(game.get_next_player(), Some(game.get_players()[0].clone()));
+    }
+
+    #[test]
+    fn thirds_are_rounded() {
+        let mut game = started_game(Rules::default());
+        // Un asso e un re: uno e un terzo
+        game.tricks.won_cards_mut(0).extend(vec![
+            Card::new(Rank::Ace, Suit::Coppe),
+            Card::new(Rank::King, Suit::Coppe),
+        ]);
+        let points = |game: &Beccaccino| game.get_scores()[0].1;
+        assert_eq!(points(&game), fraction::GenericFraction::new(4u8, 3u8));
+        game.rules.thirds = Thirds::Down;
+        assert_eq!(points(&game), utils::one());
+        game.rules.thirds = Thirds::Up;
+        assert_eq!(points(&game), fraction::GenericFraction::new(2u8, 1u8));
+    }
+
+    /// A hand of Marafone dealt with these cards to the first players
+    fn stacked_marafone(hands: &[&[Card]]) -> Beccaccino {
+        let given = hands.concat();
+        let mut rest: Vec<Card> = utils::new_deck(CardDeckType::Briscola)
+            .into_iter()
+            .filter(|x| !given.contains(x))
+            .collect();
+        // Le carte si distribuiscono dalla fine del mazzo, a partire dal primo giocatore
+        let mut deck = vec![];
+        for player in (0..4).rev() {
+            let mut hand = hands.get(player).map(|x| x.to_vec()).unwrap_or_default();
+            while hand.len() < HAND_SIZE {
+                hand.push(rest.pop().unwrap());
+            }
+            deck.extend(hand);
+        }
+        let mut game = Beccaccino::with_rules(Rules::MARAFONE);
+        game.set_shuffler(utils::Shuffler::stacked(vec![deck]));
+        testing::started(game, 4)
+    }
+
+    const MARAFONA: [Card; 3] = [
+        Card::new(Rank::Ace, Suit::Coppe),
+        Card::new(Rank::Two, Suit::Coppe),
+        Card::new(Rank::Three, Suit::Coppe),
+    ];
+
+    #[test]
+    fn marafona() {
+        let mut game = stacked_marafone(&[&MARAFONA]);
+        let chooser = game.get_players()[0].clone();
+        let res = game.handle_move(&chooser, MARAFONA[0]);
+        assert!(matches!(&res[0], GameStatus::NotifyRoom(x) if x.to_string().contains("marafona")));
+        assert_eq!(
+            game.get_scores()[0].1,
+            fraction::GenericFraction::new(MARAFONA_POINTS, 1u8)
+        );
+    }
+
+    #[test]
+    fn only_who_chooses_scores_the_marafona() {
+        let mut game = stacked_marafone(&[&[Card::new(Rank::Four, Suit::Coppe)], &MARAFONA]);
+        let chooser = game.get_players()[0].clone();
+        let res = game.handle_move(&chooser, Card::new(Rank::Four, Suit::Coppe));
+        assert!(!matches!(&res[0], GameStatus::NotifyRoom(_)));
+        assert!(game.get_scores().iter().all(|x| x.1 == utils::zero()));
+    }
+}
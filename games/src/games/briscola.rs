@@ -1,65 +1,75 @@
+use crate::i18n::Text;
 use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::tricks::{Played, Teams, TrickRules, Tricks};
 use crate::utils;
+use crate::view::PlayerView;
 use itertools::Itertools;
-use std::collections::hash_map::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+/// Cards in hand for each player
+const HAND_SIZE: usize = 3;
+
+/// How Briscola is played where the game was created
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    /// Cards in hand for each player when playing in two, usually 3 but some play with 5
+    pub hand_size_in_two: usize,
+    /// Everybody plays with their cards face up ("Briscola scoperta")
+    pub face_up: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            hand_size_in_two: HAND_SIZE,
+            face_up: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Briscola {
-    /// The cards on the table, associated with the player
-    table: Vec<(Player, Card)>,
-    /// List of players
-    players: Vec<Player>,
-    /// Card in hand for each player
-    in_hand: HashMap<Player, Vec<Card>>,
-    /// Teams
-    teams: Vec<Vec<Player>>,
-    player_team: HashMap<Player, usize>,
-    won_cards: Vec<Vec<Card>>,
-    deck: CardDeck,
-    briscola: CardSuit,
-    next_player: Option<Player>,
-    started: bool,
+    /// The players in two teams, or each for themselves when they're three
+    tricks: Tricks<Briscola>,
+    rules: Rules,
+    shuffler: utils::Shuffler,
 }
 
-impl Game for Briscola {
-    fn get_card_rank(card: &CardType) -> fraction::GenericFraction<u8> {
-        fraction::GenericFraction::new(
-            match card {
-                CardType::Jack => 2,
-                CardType::Queen => 3,
-                CardType::King => 4,
-                CardType::Numeric(x) => match x {
-                    1 => 11,
-                    3 => 10,
-                    _ => 0,
-                },
-                CardType::Jolly => 0,
-            } as u8,
-            1u8,
-        )
+impl Briscola {
+    pub fn with_rules(rules: Rules) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
     }
-    fn get_card_sorting_rank(card: &CardType) -> u8 {
-        match card {
-            CardType::Jack => 6,
-            CardType::Queen => 7,
-            CardType::King => 8,
-            CardType::Numeric(x) => match x {
-                1 => 10,
-                3 => 9,
-                7 => 5,
-                6 => 4,
-                5 => 3,
-                4 => 2,
-                2 => 1,
-                _ => 0,
-            },
-            CardType::Jolly => 0,
+    /// The hands of everybody, when they're played face up
+    fn face_up(&self) -> Vec<(Player, Vec<Card>)> {
+        if !self.rules.face_up {
+            return vec![];
         }
+        self.tricks
+            .players()
+            .iter()
+            .cloned()
+            .zip(self.tricks.hands().iter().cloned())
+            .collect()
     }
-    fn init(&mut self) {
-        let deck = utils::random_deck(CardDeckType::Briscola);
-        self.deck = deck.clone();
-        self.briscola = deck.first().unwrap().1.clone();
+}
+
+/// Any card can be played, the briscola is the suit of the last card of the stock
+impl TrickRules for Briscola {
+    const FOLLOW_SUIT: bool = false;
+}
+
+impl Game for Briscola {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
     }
     fn get_name(&self) -> &str {
         "Briscola"
@@ -71,213 +81,353 @@ impl Game for Briscola {
         2..4
     }
     fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
-        // bloccare mossa se non è il tuo turno
-        if self.get_next_player().is_none() {
-            return vec![GameStatus::InvalidMove("La partita non è ancora iniziata")];
-        } else if &self.get_next_player().unwrap() != by {
-            return vec![GameStatus::InvalidMove("Non è ancora il tuo turno!")];
-        }
-        let player_index = self.players.iter().position(|x| x == by).unwrap();
-        let next_player = self.players.clone()[(player_index + 1) % self.players.len()].clone();
-        self.next_player = Some(next_player.clone());
-        // Tolgo la carta dalle carte in mano
-        let card_index = self
-            .in_hand
-            .get(by)
-            .unwrap()
-            .iter()
-            .position(|x| x.clone() == card)
-            .expect("Non trovo la carta");
-        self.in_hand.get_mut(by).unwrap().remove(card_index);
-        // E la metto sul tavolo
-        self.table.push((by.clone(), card.clone()));
-        if self.table.len() == self.players.len() {
-            // Se tutti hanno messo una carta
-            // Determinare la carta vincente
-            let mut winner = (self.table[0]).clone().0;
-            let mut winning_suit = &(&(self.table[0]).1).clone().1;
-            let mut max: i32 = -1;
-            for (player, card) in &self.table {
-                if card.1 == self.briscola && winning_suit != &self.briscola {
-                    winning_suit = &self.briscola;
-                    max = Self::get_card_sorting_rank(&card.0) as i32;
-                    winner = player.clone();
-                }
-                if &card.1 == winning_suit && Self::get_card_sorting_rank(&card.0) as i32 > max {
-                    max = Self::get_card_sorting_rank(&card.0) as i32;
-                    winner = player.clone();
-                }
+        match self.tricks.play(by, card) {
+            Err(e) => vec![GameStatus::InvalidMove(e)],
+            Ok(Played::Next(next_player)) => {
+                let next_player = self.tricks.players()[next_player].clone();
+                vec![
+                    GameStatus::WaitingForChoice(
+                        next_player.clone(),
+                        self.tricks.hand_of(&next_player),
+                    ),
+                    GameStatus::InProgress(next_player),
+                ]
             }
-            // Abbiamo determinato chi ha vinto la mano, assegnamogliela
-            self.won_cards[*self.player_team.get(&winner).unwrap()]
-                .append(&mut self.table.iter().map(|x| x.1.clone()).collect());
-            self.table.clear(); // Just in case...
-            if self.deck.len() >= self.players.len() {
-                for i in 0..self.players.len() {
-                    let receiving_player_position =
-                        (i + self.players.iter().position(|x| x == &winner).unwrap())
-                            % self.players.len();
-                    self.in_hand
-                        .get_mut(&self.players[receiving_player_position])
-                        .unwrap()
-                        .push(self.deck.pop().unwrap());
+            Ok(Played::Trick { winner, .. }) => {
+                let winner = self.tricks.players()[winner].clone();
+                let mut res = vec![
+                    GameStatus::CardPlayed(by.clone(), card),
+                    GameStatus::WaitingForChoice(winner.clone(), self.tricks.hand_of(&winner)),
+                    GameStatus::RoundWon(winner),
+                ];
+                if self.tricks.is_over() {
+                    res.push(GameStatus::GameEnded);
                 }
+                res
             }
-            self.next_player = Some(winner.clone());
-            let game_ended = self.in_hand.iter().map(|x| x.1.len()).max().unwrap() == 0;
-            let mut res = vec![
-                GameStatus::CardPlayed(by.clone(), card),
-                GameStatus::WaitingForChoice(
-                    winner.clone(),
-                    self.in_hand.get(&winner).unwrap().clone(),
-                ),
-                GameStatus::RoundWon(winner),
-            ];
-            if game_ended {
-                res.push(GameStatus::GameEnded);
-            }
-            res
-        } else {
-            self.next_player = Some(next_player.clone());
-            vec![
-                GameStatus::WaitingForChoice(
-                    next_player.clone(),
-                    self.in_hand.get(&next_player).unwrap().clone(),
-                ),
-                GameStatus::InProgress(next_player),
-            ]
         }
     }
-    fn add_player(&mut self, player: Player) -> Result<GameStatus, &str> {
-        if self.deck.len() < 40 {
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        self.tricks.legal_moves(player)
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.tricks.is_dealt() {
             // La partita è gia cominciata, errore!
-            return Err("Non è possibile aggiungersi ad una partita già cominciata!");
+            return Err(GameError::AlreadyStarted);
         }
-        if self.players.len() <= self.get_num_players().end as usize {
-            if self.players.is_empty() {
-                self.next_player = Some(player.clone());
-            }
+        let players = self.tricks.players().len() + 1;
+        if players <= self.get_num_players().end as usize {
             // Aggiungo il giocatore
-            self.players.push(player.clone());
-            let is_ready = self.players.len() <= self.get_num_players().end as usize
-                && self.get_num_players().start as usize <= self.players.len();
-            let hand = Vec::new();
-            self.in_hand.insert(player.clone(), hand);
-            // Lo assegno ad un team
-            self.teams[self.players.len() % 2].push(player.clone());
-            self.player_team
-                .insert(player.clone(), self.players.len() % 2);
+            self.tricks.add_player(player.clone());
+            let is_ready = self.get_num_players().start as usize <= players;
             Ok(GameStatus::WaitingForPlayers(is_ready, player))
         } else {
-            Err("Il gioco è pieno")
+            Err(GameError::GameFull)
         }
     }
     fn get_next_player(&self) -> Option<Player> {
-        self.next_player.clone()
+        self.tricks
+            .next_player()
+            .map(|x| self.tricks.players()[x].clone())
     }
     fn start(&mut self) -> GameStatus {
-        if self.started {
-            return GameStatus::InvalidMove("Il gioco è già iniziato, non puoi farlo reiniziare!");
+        if self.tricks.is_dealt() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
         }
-        if self.players.len() == 3 {
+        let mut deck = self.shuffler.deck(CardDeckType::Briscola);
+        if self.tricks.players().len() == 3 {
             // FIXME controllare che non sia di briscola
-            // Spostare il terzo giocatore in un team a se stante e togliere una carta
-            if let Some(i) = self.deck.iter().position(|x| x.0 == CardType::Numeric(2)) {
-                self.deck.remove(i);
+            // In tre si gioca ognuno per sé e si toglie una carta
+            if let Some(i) = deck.iter().position(|x| x.rank == Rank::Two) {
+                deck.remove(i);
             } else {
                 // Terminiamo in anticipo il gioco, ma non dovrebbe mai succedere
                 // Ma piutòst che gnit, l'è mej piutòst
                 return GameStatus::GameEnded;
             }
-            self.teams.push(Vec::new());
-            self.won_cards.push(Vec::new());
-            let moved_player = self.teams[1].pop().unwrap();
-            self.teams[2].push(moved_player);
-            self.player_team.remove(&self.teams[2][0].clone());
-            self.player_team.insert(self.teams[2][0].clone(), 2);
-        }
-        // Scelgo la briscola
-        self.briscola = self.deck.first().unwrap().1.clone();
-        // Do le carte
-        for player in &self.players {
-            for _ in 0..3 {
-                self.in_hand
-                    .get_mut(player)
-                    .unwrap()
-                    .push(self.deck.pop().unwrap());
-            }
+            self.tricks.set_teams(Teams::Single);
         }
-        let player = self.players[0].clone();
-        self.started = true;
-        GameStatus::WaitingForChoice(player.clone(), self.in_hand.get(&player).unwrap().clone())
+        // In due si può giocare con più carte in mano
+        let hand_size = if self.tricks.players().len() == 2 {
+            self.rules.hand_size_in_two
+        } else {
+            HAND_SIZE
+        };
+        // La briscola è l'ultima carta del mazzo, che resta scoperta
+        self.tricks.set_trump(deck.first().map(|x| x.suit));
+        self.tricks.deal(deck, hand_size);
+        let player = self.tricks.players()[0].clone();
+        GameStatus::WaitingForChoice(player.clone(), self.tricks.hand_of(&player))
     }
     fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
-        self.teams
-            .iter()
-            .zip(self.won_cards.iter())
+        self.tricks
+            .teams()
+            .into_iter()
+            .enumerate()
             .map(|x| {
-                let player_lst = x.0.clone();
-                let score = x.1.iter().map(|x| Self::get_card_rank(&x.0)).sum();
-                (player_lst, score)
+                let score = self
+                    .tricks
+                    .won_cards(x.0)
+                    .iter()
+                    .map(|y| Self::get_card_rank(&y.rank))
+                    .sum();
+                (x.1, score)
             })
             .collect()
     }
-    fn get_status(&self) -> String {
-        format!(
+    fn get_status(&self) -> Text {
+        let status = text!(
             "Partita di {}\nPunteggi:\n{}\nBriscola è: {}\nTocca a: {}\nCarte sul tavolo:\n{}",
             self.get_name(),
-            self.get_scores()
-                .iter()
-                .enumerate()
-                .map(|x| format!(
+            Text::join(
+                self.get_scores().into_iter().map(|x| text!(
                     "{}: {} punti",
-                    (x.1).0.iter().map(|y| y.name.clone()).join(", "),
-                    (x.1).1
-                ))
-                .join("\n"),
-            String::from(&self.briscola),
+                    x.0.iter().map(|y| y.name.clone()).join(", "),
+                    x.1
+                )),
+                "\n"
+            ),
+            self.tricks
+                .trump()
+                .map(|x| text!("{}", x))
+                .unwrap_or_else(|| Text::new("non ancora scelta")),
             self.get_next_player()
                 .map(|x| x.name)
                 .unwrap_or_else(|| "".to_owned()),
-            self.table
-                .iter()
-                .map(|x| format!("- {} ({})", utils::get_card_name(&x.1), x.0.name))
-                .join("\n")
+            Text::join(
+                self.tricks
+                    .table()
+                    .iter()
+                    .map(|x| text!("- {} ({})", x.1, &x.0.name)),
+                "\n"
+            )
+        );
+        if !self.rules.face_up {
+            return status;
+        }
+        text!(
+            "{}\nCarte in mano:\n{}",
+            status,
+            Text::join(
+                self.face_up().into_iter().map(|x| text!(
+                    "{}: {}",
+                    x.0.name,
+                    Text::join(x.1, ", ")
+                )),
+                "\n"
+            )
         )
     }
     fn get_players(&self) -> Vec<Player> {
-        self.players.clone()
+        self.tricks.players().to_vec()
     }
     fn get_new_instance(&self) -> Box<dyn Game> {
-        Box::new(Self::default())
+        Box::new(Self::with_rules(self.rules))
     }
-    fn handle_message(&self, message: String, from: Player) -> Vec<GameStatus> {
-        vec![GameStatus::NotifyRoom(format!(
+    fn variants(&self) -> Vec<Variant> {
+        vec![
+            Variant {
+                code: "carte",
+                name: Text::new("Carte in mano in due"),
+                options: vec![("3", Text::new("3")), ("5", Text::new("5"))],
+                chosen: if self.rules.hand_size_in_two == 5 {
+                    "5"
+                } else {
+                    "3"
+                },
+            },
+            Variant::switch(
+                "scoperta",
+                Text::new("Briscola scoperta"),
+                self.rules.face_up,
+            ),
+        ]
+    }
+    fn set_variant(&mut self, variant: &str, option: &str) -> Result<(), GameError> {
+        if self.tricks.is_dealt() {
+            return Err(GameError::AlreadyStarted);
+        }
+        match (variant, option) {
+            ("carte", "3") => self.rules.hand_size_in_two = 3,
+            ("carte", "5") => self.rules.hand_size_in_two = 5,
+            ("scoperta", x) => {
+                self.rules.face_up = Variant::is_on(x).ok_or(GameError::NoSuchVariant)?
+            }
+            _ => return Err(GameError::NoSuchVariant),
+        }
+        Ok(())
+    }
+    fn determinize(&self, player: &Player, seed: u64) -> Option<Box<dyn Game>> {
+        crate::ai::determinize_boxed(self, player, seed)
+    }
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.tricks.hand_of(player),
+            table: self.tricks.table_view(),
+            briscola: self.tricks.trump(),
+            trump_card: self.tricks.stock().first().cloned(),
+            captured: self.tricks.captured(),
+            hand_sizes: self.tricks.hand_sizes(),
+            stock: self.tricks.stock().len(),
+            face_up: self.face_up(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Briscola(self.clone()).into()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        vec![GameStatus::NotifyRoom(text!(
             "{} ha detto: {}",
-            from.name, message
+            from.name,
+            message
         ))]
     }
 }
 
+impl CardValues for Briscola {
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        fraction::GenericFraction::new(
+            match card {
+                Rank::Jack => 2,
+                Rank::Queen => 3,
+                Rank::King => 4,
+                Rank::Ace => 11,
+                Rank::Three => 10,
+                _ => 0,
+            } as u8,
+            1u8,
+        )
+    }
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        match card {
+            Rank::Jack => 6,
+            Rank::Queen => 7,
+            Rank::King => 8,
+            Rank::Ace => 10,
+            Rank::Three => 9,
+            Rank::Seven => 5,
+            Rank::Six => 4,
+            Rank::Five => 3,
+            Rank::Four => 2,
+            Rank::Two => 1,
+            _ => 0,
+        }
+    }
+}
+
 impl Default for Briscola {
     fn default() -> Self {
-        let mut teams = Vec::new();
-        teams.push(Vec::new());
-        teams.push(Vec::new());
-        let mut wc = Vec::new();
-        wc.push(Vec::new());
-        wc.push(Vec::new());
         Self {
-            table: Vec::new(),
-            players: Vec::new(),
-            in_hand: HashMap::new(),
-            teams,
-            player_team: HashMap::new(),
-            won_cards: wc,
-            deck: vec![],
-            briscola: CardSuit::Coppe,
-            next_player: None,
-            started: false,
+            tricks: Tricks::new(Teams::Alternate),
+            rules: Rules::default(),
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+}
+
+impl crate::ai::HiddenCards for Briscola {
+    /// The hands of the others and the stock, apart from the briscola at its bottom.
+    /// When the hands are face up only the stock is hidden.
+    fn hidden_cards(&mut self, player: &Player) -> Vec<&mut [Card]> {
+        if self.rules.face_up {
+            self.tricks.hidden_stock(1)
+        } else {
+            self.tricks.hidden_cards(player, 1)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    fn started_game(shuffler: utils::Shuffler) -> Briscola {
+        start(Briscola::default(), shuffler)
+    }
+
+    fn start(mut game: Briscola, shuffler: utils::Shuffler) -> Briscola {
+        game.set_shuffler(shuffler);
+        game.init();
+        testing::started(game, 2)
+    }
+
+    #[test]
+    fn stacked_deck() {
+        // L'ultima carta del mazzo è l'asso di briscola
+        let mut deck = utils::seeded_deck(CardDeckType::Briscola, 0);
+        let ace = deck
+            .iter()
+            .position(|x| x == &Card::new(Ace, Coppe))
+            .unwrap();
+        deck.swap(0, ace);
+        let game = started_game(utils::Shuffler::stacked(vec![deck.clone()]));
+        assert_eq!(game.tricks.trump(), Some(Coppe));
+        assert_eq!(game.tricks.stock().first(), Some(&Card::new(Ace, Coppe)));
+        assert_eq!(
+            game.tricks.hands()[0],
+            deck[37..].iter().rev().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn same_seed_same_deal() {
+        let a = started_game(utils::Shuffler::new(42));
+        let b = started_game(utils::Shuffler::new(42));
+        assert_eq!(a.tricks.stock(), b.tricks.stock());
+        assert_eq!(a.tricks.hands(), b.tricks.hands());
+        assert_ne!(
+            a.tricks.stock(),
+            started_game(utils::Shuffler::new(43)).tricks.stock()
+        );
+    }
+
+    #[test]
+    fn card_not_in_hand() {
+        let mut game = started_game(utils::Shuffler::new(42));
+        let players = game.get_players();
+        let (first, second) = (players[0].clone(), players[1].clone());
+        let card = game.tricks.hands()[1][0];
+        let res = game.handle_move(&first, card);
+        assert!(matches!(
+            res[0],
+            GameStatus::InvalidMove(GameError::CardNotInHand)
+        ));
+        // Il turno non passa all'altro giocatore
+        assert_eq!(game.get_next_player(), Some(first));
+        let res = game.handle_move(&second, card);
+        assert!(matches!(
+            res[0],
+            GameStatus::InvalidMove(GameError::NotYourTurn)
+        ));
+    }
+
+    #[test]
+    fn five_cards_face_up() {
+        let mut game = Briscola::default();
+        game.set_variant("carte", "5").unwrap();
+        game.set_variant("scoperta", "si").unwrap();
+        assert_eq!(
+            game.set_variant("scoperta", "forse"),
+            Err(GameError::NoSuchVariant)
+        );
+        // Le mani successive si giocano con le stesse regole
+        assert_eq!(game.get_new_instance().variants(), game.variants());
+        let mut game = start(game, utils::Shuffler::new(42));
+        assert!(game.tricks.hands().iter().all(|x| x.len() == 5));
+        assert_eq!(
+            game.set_variant("carte", "3"),
+            Err(GameError::AlreadyStarted)
+        );
+        // Si vedono le carte dell'avversario, ma non il mazzo
+        let players = game.get_players();
+        let view = game.view_for(&players[0]);
+        assert_eq!(view.face_up[1].1, game.tricks.hands()[1]);
+        let stock = game.tricks.stock().len();
+        let hidden = crate::ai::HiddenCards::hidden_cards(&mut game, &players[0]);
+        assert_eq!(hidden.iter().map(|x| x.len()).sum::<usize>(), stock - 1);
+    }
+}
@@ -0,0 +1,502 @@
+use super::briscola::Briscola;
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::tricks::{Played, Teams, TrickRules, Tricks};
+use crate::utils;
+use crate::view::PlayerView;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// The lowest bid allowed in the auction
+const MIN_BID: u8 = 61;
+/// All the points in the deck
+const MAX_BID: u8 = 120;
+/// Cards in hand for each player
+const HAND_SIZE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Phase {
+    /// Players bid the score they think they'll make
+    Auction,
+    /// The winner of the auction calls a card
+    Calling,
+    Playing,
+    Ended,
+}
+
+/// Briscola for five players: the winner of the auction calls a card
+/// and whoever has it secretly becomes their partner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BriscolaChiamata {
+    /// Everybody plays for themselves until the partner is revealed,
+    /// so the cards won are kept for each player
+    tricks: Tricks<BriscolaChiamata>,
+    phase: Phase,
+    /// Players who left the auction
+    passed: Vec<bool>,
+    /// The highest bid and who made it
+    bid: Option<(usize, u8)>,
+    called_card: Option<Card>,
+    /// The holder of the called card, known to everybody only once the card is played
+    partner: Option<usize>,
+    partner_revealed: bool,
+    shuffler: utils::Shuffler,
+}
+
+impl BriscolaChiamata {
+    fn deal(&mut self) {
+        self.tricks
+            .deal(self.shuffler.deck(CardDeckType::Briscola), HAND_SIZE);
+        self.passed = vec![false; self.tricks.players().len()];
+        self.bid = None;
+    }
+    fn caller(&self) -> Option<usize> {
+        self.bid.map(|x| x.0)
+    }
+    /// The players in the team of the caller, as far as everybody knows
+    fn known_caller_team(&self) -> Vec<usize> {
+        let mut res: Vec<usize> = self.caller().into_iter().collect();
+        if self.partner_revealed {
+            res.extend(self.partner.filter(|x| !res.contains(x)));
+        }
+        res
+    }
+    fn bid_request(&self, player: usize) -> GameStatus {
+        GameStatus::WaitingForChoiceCustomMessage(
+            self.tricks.players()[player].clone(),
+            self.tricks.hands()[player].clone(),
+            Text::new("Tocca a te: scrivi quanti punti pensi di fare (da 61 a 120) o «passo»"),
+        )
+    }
+    fn handle_bid(&mut self, from: &Player, bid: Option<u8>) -> Vec<GameStatus> {
+        let player = match self.tricks.next_player() {
+            Some(x) if &self.tricks.players()[x] == from => x,
+            _ => return vec![GameStatus::InvalidMove(GameError::NotYourTurn)],
+        };
+        let mut res = vec![];
+        match bid {
+            Some(x) => {
+                let min = self.bid.map(|y| y.1 + 1).unwrap_or(MIN_BID);
+                if x < min || x > MAX_BID {
+                    return vec![GameStatus::InvalidMove(GameError::InvalidBid)];
+                }
+                self.bid = Some((player, x));
+                res.push(GameStatus::NotifyRoom(text!("{} offre {}", from.name, x)));
+            }
+            None => {
+                self.passed[player] = true;
+                res.push(GameStatus::NotifyRoom(text!("{} passa", from.name)));
+            }
+        }
+        let in_auction: Vec<usize> = (0..self.tricks.players().len())
+            .filter(|x| !self.passed[*x])
+            .collect();
+        match self.bid {
+            None if in_auction.is_empty() => {
+                res.push(GameStatus::NotifyRoom(Text::new(
+                    "Hanno passato tutti, si ridistribuiscono le carte",
+                )));
+                self.deal();
+                res.push(self.bid_request(0));
+            }
+            Some((caller, bid)) if in_auction.len() == 1 || bid == MAX_BID => {
+                self.phase = Phase::Calling;
+                self.tricks.set_next_player(Some(caller));
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} ha vinto l'asta con {} punti",
+                    self.tricks.players()[caller].name,
+                    bid
+                )));
+                res.push(GameStatus::WaitingForChoiceCustomMessage(
+                    self.tricks.players()[caller].clone(),
+                    self.callable_cards(caller),
+                    Text::new("Chiama una carta: chi ce l'ha sarà il tuo compagno e il suo seme sarà la briscola"),
+                ));
+            }
+            _ => {
+                let next_player = (1..self.tricks.players().len())
+                    .map(|x| (player + x) % self.tricks.players().len())
+                    .find(|x| !self.passed[*x])
+                    .unwrap();
+                self.tricks.set_next_player(Some(next_player));
+                res.push(self.bid_request(next_player));
+            }
+        }
+        res
+    }
+    /// The cards the winner of the auction can call, i.e. the ones they don't have
+    fn callable_cards(&self, caller: usize) -> Vec<Card> {
+        utils::new_deck(CardDeckType::Briscola)
+            .into_iter()
+            .filter(|x| !self.tricks.hands()[caller].contains(x))
+            .sorted_by_key(|x| {
+                (
+                    x.suit,
+                    std::cmp::Reverse(Self::get_card_sorting_rank(&x.rank)),
+                )
+            })
+            .collect()
+    }
+    fn call_card(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let caller = self.caller().unwrap();
+        if by != &self.tricks.players()[caller] {
+            return vec![GameStatus::InvalidMove(GameError::NotYourChoice)];
+        }
+        let partner = match self.tricks.hands().iter().position(|x| x.contains(&card)) {
+            Some(x) if x != caller => x,
+            _ => return vec![GameStatus::InvalidMove(GameError::CannotCallOwnCard)],
+        };
+        self.partner = Some(partner);
+        self.tricks.set_trump(Some(card.suit));
+        self.called_card = Some(card);
+        self.phase = Phase::Playing;
+        self.tricks.set_next_player(Some(0));
+        vec![
+            GameStatus::NotifyRoom(text!("{} chiama {}", &by.name, card)),
+            GameStatus::BriscolaChosen(by.clone(), card.suit),
+            GameStatus::WaitingForChoice(
+                self.tricks.players()[0].clone(),
+                self.tricks.hands()[0].clone(),
+            ),
+            GameStatus::InProgress(self.tricks.players()[0].clone()),
+        ]
+    }
+    fn points(&self, players: &[usize]) -> fraction::GenericFraction<u8> {
+        players
+            .iter()
+            .flat_map(|x| self.tricks.won_cards(*x).iter())
+            .map(|x| Self::get_card_rank(&x.rank))
+            .sum()
+    }
+    fn end_game(&mut self) -> Vec<GameStatus> {
+        self.phase = Phase::Ended;
+        let (_, bid) = self.bid.unwrap();
+        let team = self.known_caller_team();
+        let points = self.points(&team);
+        vec![
+            GameStatus::NotifyRoom(text!(
+                "{} hanno fatto {} punti su {}: {}",
+                Text::join(team.iter().map(|x| &self.tricks.players()[*x].name), " e "),
+                points,
+                bid,
+                if points >= fraction::GenericFraction::new(bid, 1u8) {
+                    Text::new("hanno vinto!")
+                } else {
+                    Text::new("hanno perso!")
+                }
+            )),
+            GameStatus::GameEnded,
+        ]
+    }
+}
+
+/// Any card can be played, the briscola is the suit of the called card
+impl TrickRules for BriscolaChiamata {
+    const FOLLOW_SUIT: bool = false;
+}
+
+impl Game for BriscolaChiamata {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        "Briscola chiamata"
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Briscola
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        5..5
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        let index = match self.tricks.next_player() {
+            Some(x) if &self.tricks.players()[x] == player => x,
+            _ => return vec![],
+        };
+        match self.phase {
+            Phase::Calling => self.callable_cards(index),
+            Phase::Playing => self.tricks.hands()[index].clone(),
+            _ => vec![],
+        }
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        match self.phase {
+            Phase::Auction => {
+                return vec![
+                    GameStatus::InvalidMove(GameError::CardNotExpected),
+                    GameStatus::NotifyUser(
+                        by.clone(),
+                        Text::new("Durante l'asta devi scrivere la tua offerta o «passo»"),
+                    ),
+                ]
+            }
+            Phase::Calling => return self.call_card(by, card),
+            Phase::Ended => return vec![GameStatus::InvalidMove(GameError::GameEnded)],
+            Phase::Playing => {}
+        }
+        let played = match self.tricks.play(by, card) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        if self.called_card.as_ref() == Some(&card) {
+            self.partner_revealed = true;
+            res.push(GameStatus::PartnerRevealed(
+                self.tricks.players()[self.caller().unwrap()].clone(),
+                by.clone(),
+            ));
+        }
+        let next_player = match played {
+            Played::Trick { winner, .. } => {
+                res.push(GameStatus::RoundWon(self.tricks.players()[winner].clone()));
+                if self.tricks.is_over() {
+                    res.append(&mut self.end_game());
+                    return res;
+                }
+                winner
+            }
+            Played::Next(x) => x,
+        };
+        let next_player = self.tricks.players()[next_player].clone();
+        res.push(GameStatus::WaitingForChoice(
+            next_player.clone(),
+            self.tricks.hand_of(&next_player),
+        ));
+        res.push(GameStatus::InProgress(next_player));
+        res
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.tricks.is_dealt() {
+            Err(GameError::AlreadyStarted)
+        } else if self.tricks.players().len() >= 5 {
+            Err(GameError::GameFull)
+        } else {
+            self.tricks.add_player(player.clone());
+            Ok(GameStatus::WaitingForPlayers(
+                self.tricks.players().len() == 5,
+                player,
+            ))
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.tricks
+            .next_player()
+            .map(|x| self.tricks.players()[x].clone())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.tricks.is_dealt() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.tricks.players().len() != 5 {
+            return GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(vec![5]));
+        }
+        self.deal();
+        self.bid_request(0)
+    }
+    /// Until the partner is revealed, the points of each player; then the points of the two teams
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        let caller_team = self.known_caller_team();
+        if caller_team.len() < 2 {
+            (0..self.tricks.players().len())
+                .map(|x| (vec![self.tricks.players()[x].clone()], self.points(&[x])))
+                .collect()
+        } else {
+            let others: Vec<usize> = (0..self.tricks.players().len())
+                .filter(|x| !caller_team.contains(x))
+                .collect();
+            [caller_team, others]
+                .iter()
+                .map(|x| {
+                    (
+                        x.iter()
+                            .map(|y| self.tricks.players()[*y].clone())
+                            .collect(),
+                        self.points(x),
+                    )
+                })
+                .collect()
+        }
+    }
+    fn get_status(&self) -> Text {
+        let contract = match (&self.bid, &self.phase) {
+            (None, _) => Text::new("Asta in corso"),
+            (Some((player, bid)), Phase::Auction) => text!(
+                "Asta in corso, offerta più alta: {} ({})",
+                *bid,
+                &self.tricks.players()[*player].name
+            ),
+            (Some((player, bid)), _) => text!(
+                "{} deve fare {} punti\nCarta chiamata: {}",
+                &self.tricks.players()[*player].name,
+                *bid,
+                self.called_card
+                    .map(|x| text!("{}", x))
+                    .unwrap_or_else(|| Text::new("non ancora scelta"))
+            ),
+        };
+        text!(
+            "Partita di {}\n{}\nPunteggi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+            self.get_name(),
+            contract,
+            Text::join(
+                self.get_scores().into_iter().map(|x| text!(
+                    "{}: {} punti",
+                    x.0.iter().map(|y| y.name.clone()).join(", "),
+                    x.1
+                )),
+                "\n"
+            ),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            Text::join(
+                self.tricks
+                    .table()
+                    .iter()
+                    .map(|x| text!("- {} ({})", x.1, &x.0.name)),
+                "\n"
+            )
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.tricks.players().to_vec()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::default())
+    }
+    /// Cards taken are counted for each player, since teams may still be secret
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.tricks.hand_of(player),
+            table: self.tricks.table_view(),
+            briscola: self.tricks.trump(),
+            captured: self.tricks.captured(),
+            hand_sizes: self.tricks.hand_sizes(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::BriscolaChiamata(self.clone()).into()
+    }
+    /// During the auction players bid by writing a number or «passo»
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        if self.phase == Phase::Auction && self.tricks.is_dealt() {
+            let text = message.trim().to_lowercase();
+            if text == "passo" {
+                return self.handle_bid(&from, None);
+            } else if let Ok(bid) = text.parse::<u8>() {
+                return self.handle_bid(&from, Some(bid));
+            }
+        }
+        vec![GameStatus::NotifyRoom(text!(
+            "{} ha detto: {}",
+            from.name,
+            message
+        ))]
+    }
+}
+
+impl CardValues for BriscolaChiamata {
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        Briscola::get_card_rank(card)
+    }
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        Briscola::get_card_sorting_rank(card)
+    }
+}
+
+impl Default for BriscolaChiamata {
+    fn default() -> Self {
+        Self {
+            tricks: Tricks::new(Teams::Single),
+            phase: Phase::Auction,
+            passed: vec![],
+            bid: None,
+            called_card: None,
+            partner: None,
+            partner_revealed: false,
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn started_game() -> BriscolaChiamata {
+        testing::started(BriscolaChiamata::default(), 5)
+    }
+
+    #[test]
+    fn auction() {
+        let mut game = started_game();
+        let players = game.get_players();
+        game.handle_message("70".to_owned(), players[0].clone());
+        // Un'offerta deve superare quella precedente
+        let res = game.handle_message("65".to_owned(), players[1].clone());
+        assert!(matches!(res[0], GameStatus::InvalidMove(_)));
+        game.handle_message("75".to_owned(), players[1].clone());
+        for player in &players[2..] {
+            game.handle_message("passo".to_owned(), player.clone());
+        }
+        game.handle_message("passo".to_owned(), players[0].clone());
+        assert_eq!(game.phase, Phase::Calling);
+        assert_eq!(game.bid, Some((1, 75)));
+        assert_eq!(game.get_next_player(), Some(players[1].clone()));
+    }
+
+    #[test]
+    fn everybody_passes() {
+        let mut game = started_game();
+        for player in game.get_players() {
+            game.handle_message("passo".to_owned(), player);
+        }
+        assert_eq!(game.phase, Phase::Auction);
+        assert_eq!(game.passed, vec![false; 5]);
+        assert_eq!(game.tricks.next_player(), Some(0));
+    }
+
+    #[test]
+    fn partner_is_revealed_when_the_called_card_is_played() {
+        let mut game = started_game();
+        let players = game.get_players();
+        game.handle_message("120".to_owned(), players[0].clone());
+        let called = game.tricks.hands()[3][0];
+        // Non si può chiamare una carta che si ha in mano
+        let own = game.tricks.hands()[0][0];
+        assert!(matches!(
+            game.handle_move(&players[0], own)[0],
+            GameStatus::InvalidMove(_)
+        ));
+        game.handle_move(&players[0], called);
+        assert_eq!(game.partner, Some(3));
+        assert_eq!(game.get_scores().len(), 5);
+        for player in &players[..3] {
+            let card = game.tricks.hands()[player.id as usize][0];
+            game.handle_move(player, card);
+        }
+        let res = game.handle_move(&players[3], called);
+        assert!(res.iter().any(|x| matches!(
+            x,
+            GameStatus::PartnerRevealed(caller, partner) if caller == &players[0] && partner == &players[3]
+        )));
+        assert_eq!(game.get_scores().len(), 2);
+    }
+
+    #[test]
+    fn callable_cards_are_sorted_by_suit_and_strength() {
+        let game = started_game();
+        let cards = game.callable_cards(0);
+        assert_eq!(cards.len(), 40 - game.tricks.hands()[0].len());
+        assert!(cards.windows(2).all(|x| x[0].suit <= x[1].suit));
+        assert_eq!(cards, game.callable_cards(0));
+    }
+}
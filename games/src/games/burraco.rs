@@ -0,0 +1,633 @@
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::rummy::{card_number, suit_index, Rummy, RummyRules};
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::PlayerView;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Cards dealt to each player and put in each pozzetto
+const HAND_SIZE: usize = 11;
+/// Minimum length of a meld to be a burraco
+const BURRACO_SIZE: usize = 7;
+const CLEAN_BURRACO_BONUS: i32 = 200;
+const DIRTY_BURRACO_BONUS: i32 = 100;
+const CLOSING_BONUS: i32 = 100;
+/// Penalty for a team that didn't take its pozzetto
+const POZZETTO_PENALTY: i32 = 100;
+
+/// A hand of Burraco, played by two players or two teams with two Poker decks.
+/// Jokers and twos are wildcards, and a team needs a burraco (a meld of at least seven cards)
+/// and to have taken its pozzetto before closing.
+/// It's played in a [`Match`](crate::multi_hand::Match) to 2005 points.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Burraco {
+    /// Hands, stock, discard pile and turns
+    rummy: Rummy<Burraco>,
+    /// The two hands set aside for the players who run out of cards
+    pozzetti: Vec<Vec<Card>>,
+    /// Melds on the table of each team
+    melds: Vec<Vec<Vec<Card>>>,
+    /// Whether each team has taken its pozzetto in this hand
+    took_pozzetto: Vec<bool>,
+    /// Points of each team, counted when the hand ends
+    points: Vec<i32>,
+    started: bool,
+    shuffler: utils::Shuffler,
+}
+
+impl RummyRules for Burraco {
+    const PICK_UP_ALL: bool = true;
+    const TEAM_MELDS: bool = true;
+    fn sort_hand(hand: &mut [Card]) {
+        hand.sort_by_key(|x| (x.is_joker(), suit_index(&x.suit), card_number(&x.rank)));
+    }
+}
+
+impl Burraco {
+    /// Teams are made by the first and third player and by the second and fourth player.
+    /// With two players each one is a team.
+    fn teams(&self) -> Vec<Vec<Player>> {
+        (0..2)
+            .map(|team| {
+                self.rummy
+                    .players()
+                    .iter()
+                    .enumerate()
+                    .filter(|x| x.0 % 2 == team)
+                    .map(|x| x.1.clone())
+                    .collect()
+            })
+            .collect()
+    }
+    fn has_burraco(&self, team: usize) -> bool {
+        self.melds[team].iter().any(|x| x.len() >= BURRACO_SIZE)
+    }
+    /// Shuffles two decks, deals the hands and sets the pozzetti aside, the first player starts
+    fn deal(&mut self) {
+        let mut deck = self.shuffler.deck(CardDeckType::Poker);
+        deck.append(&mut self.shuffler.deck(CardDeckType::Poker));
+        self.melds = vec![vec![], vec![]];
+        self.took_pozzetto = vec![false, false];
+        self.rummy.deal(deck, HAND_SIZE);
+        self.pozzetti = (0..2)
+            .map(|_| self.rummy.take_from_stock(HAND_SIZE))
+            .collect();
+    }
+    /// A player who can't close must keep enough cards to discard without running out of them
+    fn check_cards_left(
+        &self,
+        team: usize,
+        left: usize,
+        has_burraco: bool,
+    ) -> Result<(), GameError> {
+        if !self.took_pozzetto[team] {
+            Ok(())
+        } else if left == 0 {
+            Err(GameError::MustKeepADiscard)
+        } else if left == 1 && !has_burraco {
+            Err(GameError::BurracoNeeded)
+        } else {
+            Ok(())
+        }
+    }
+    fn take_pozzetto(&mut self, player: usize) {
+        self.took_pozzetto[player % 2] = true;
+        let pozzetto = self.pozzetti.pop().unwrap();
+        self.rummy.set_hand(player, pozzetto);
+    }
+    fn draw(&mut self, by: &Player, pick_up: bool) -> Vec<GameStatus> {
+        match self.rummy.draw(by, pick_up) {
+            Ok(mut res) => {
+                res.push(GameStatus::NotifyRoom(self.get_status()));
+                res
+            }
+            Err(e) => vec![GameStatus::InvalidMove(e)],
+        }
+    }
+    fn meld(&mut self, by: &Player, groups: Vec<Vec<Card>>) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, true) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let team = player % 2;
+        if groups.is_empty() || groups.iter().any(|x| meld_is_clean(x).is_none()) {
+            return vec![GameStatus::InvalidMove(GameError::InvalidMeld)];
+        }
+        let hand = match self.rummy.hand_without(player, &groups.concat()) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let has_burraco = self.has_burraco(team) || groups.iter().any(|x| x.len() >= BURRACO_SIZE);
+        if let Err(e) = self.check_cards_left(team, hand.len(), has_burraco) {
+            return vec![GameStatus::InvalidMove(e)];
+        }
+        self.rummy.set_hand(player, hand);
+        self.melds[team].extend(groups);
+        self.after_table_move(player)
+    }
+    fn attach(&mut self, by: &Player, meld: usize, cards: Vec<Card>) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, true) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let team = player % 2;
+        if meld >= self.melds[team].len() {
+            return vec![GameStatus::InvalidMove(GameError::NoSuchMeld)];
+        }
+        let mut new_meld = self.melds[team][meld].clone();
+        new_meld.extend(cards.iter().cloned());
+        if cards.is_empty() || meld_is_clean(&new_meld).is_none() {
+            return vec![GameStatus::InvalidMove(GameError::CannotAttach)];
+        }
+        let hand = match self.rummy.hand_without(player, &cards) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let has_burraco = self.has_burraco(team) || new_meld.len() >= BURRACO_SIZE;
+        if let Err(e) = self.check_cards_left(team, hand.len(), has_burraco) {
+            return vec![GameStatus::InvalidMove(e)];
+        }
+        self.rummy.set_hand(player, hand);
+        self.melds[team][meld] = new_meld;
+        self.after_table_move(player)
+    }
+    fn after_table_move(&mut self, player: usize) -> Vec<GameStatus> {
+        let mut res = vec![];
+        if self.rummy.hands()[player].is_empty() {
+            // Ha calato tutto: prende il pozzetto al volo e continua a giocare
+            self.take_pozzetto(player);
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} prende il pozzetto al volo",
+                self.rummy.players()[player].name
+            )));
+        }
+        res.append(&mut self.rummy.play_message(player));
+        res.push(GameStatus::NotifyRoom(self.get_status()));
+        res
+    }
+    /// Points made by a team in the hand that has just ended
+    fn hand_points(&self, team: usize, closed: bool) -> i32 {
+        let melds: i32 = self.melds[team]
+            .iter()
+            .map(|x| {
+                x.iter().map(|y| card_points(&y.rank)).sum::<i32>()
+                    + match (x.len() >= BURRACO_SIZE, meld_is_clean(x)) {
+                        (true, Some(true)) => CLEAN_BURRACO_BONUS,
+                        (true, _) => DIRTY_BURRACO_BONUS,
+                        _ => 0,
+                    }
+            })
+            .sum();
+        let in_hand: i32 = (team..self.rummy.players().len())
+            .step_by(2)
+            .flat_map(|x| self.rummy.hands()[x].iter())
+            .map(|x| card_points(&x.rank))
+            .sum();
+        melds - in_hand + if closed { CLOSING_BONUS } else { 0 }
+            - if self.took_pozzetto[team] {
+                0
+            } else {
+                POZZETTO_PENALTY
+            }
+    }
+    /// Someone closed or the stock ran out: adds up the points and ends the hand
+    fn end_hand(&mut self, closed_by: Option<usize>) -> Vec<GameStatus> {
+        let mut res = vec![];
+        if let Some(player) = closed_by {
+            res.push(GameStatus::RoundWon(self.rummy.players()[player].clone()));
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} ha chiuso!",
+                self.rummy.players()[player].name
+            )));
+        } else {
+            res.push(GameStatus::NotifyRoom(Text::new(
+                "Il mazzo è finito, la mano si chiude senza bonus",
+            )));
+        }
+        self.points = (0..2)
+            .map(|team| self.hand_points(team, closed_by.map(|x| x % 2) == Some(team)))
+            .collect();
+        self.rummy.set_next_player(None);
+        res.push(GameStatus::GameEnded);
+        res
+    }
+}
+
+/// Jokers are always wildcards, twos are unless they're in their place in a run
+fn is_wildcard(card: &Card) -> bool {
+    matches!(card.rank, Rank::Joker | Rank::Two)
+}
+
+/// How much a card is worth, both in a meld and as a penalty when it's left in hand
+fn card_points(card: &Rank) -> i32 {
+    match card {
+        Rank::Joker => 30,
+        Rank::Two => 20,
+        Rank::Ace => 15,
+        x if x.number() >= 8 => 10,
+        _ => 5,
+    }
+}
+
+/// Checks whether the cards form a valid set (at least three cards of the same rank)
+/// or run (at least three cards in sequence of the same suit), with at most one wildcard.
+/// If the meld is valid, returns whether it's clean, i.e. it has no wildcard.
+fn meld_is_clean(cards: &[Card]) -> Option<bool> {
+    if cards.len() < 3 {
+        return None;
+    }
+    let wildcards = cards.iter().filter(|x| is_wildcard(x)).count();
+    let naturals: Vec<&Card> = cards.iter().filter(|x| !is_wildcard(x)).collect();
+    if wildcards <= 1 && !naturals.is_empty() && naturals.iter().all(|x| x.rank == naturals[0].rank)
+    {
+        return Some(wildcards == 0);
+    }
+    // Al massimo un due può stare al suo posto nella scala
+    std::iter::once(None)
+        .chain(cards.iter().positions(|x| x.rank == Rank::Two).map(Some))
+        .filter_map(|natural_two| run_is_clean(cards, natural_two))
+        .max()
+}
+
+/// Checks whether the cards form a run where the card in position `natural_two`,
+/// if any, isn't used as a wildcard
+fn run_is_clean(cards: &[Card], natural_two: Option<usize>) -> Option<bool> {
+    let naturals: Vec<&Card> = cards
+        .iter()
+        .enumerate()
+        .filter(|x| Some(x.0) == natural_two || !is_wildcard(x.1))
+        .map(|x| x.1)
+        .collect();
+    let wildcards = (cards.len() - naturals.len()) as u8;
+    if wildcards > 1 || naturals.is_empty() || !naturals.iter().all(|x| x.suit == naturals[0].suit)
+    {
+        return None;
+    }
+    // L'asso può stare prima del due o dopo il re
+    [false, true]
+        .iter()
+        .find(|ace_high| {
+            let numbers: Vec<u8> = naturals
+                .iter()
+                .map(|x| match card_number(&x.rank) {
+                    Some(1) if **ace_high => 14,
+                    x => x.unwrap(),
+                })
+                .sorted()
+                .collect();
+            if !numbers.iter().all_unique() {
+                return false;
+            }
+            let gaps: u8 = numbers.windows(2).map(|x| x[1] - x[0] - 1).sum();
+            // Se non riempie un buco, la matta allunga la scala
+            gaps == wildcards
+                || (gaps < wildcards && (numbers[0] > 1 || numbers[numbers.len() - 1] < 14))
+        })
+        .map(|_| wildcards == 0)
+}
+
+impl Game for Burraco {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+
+    fn get_name(&self) -> &str {
+        "Burraco"
+    }
+
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Poker
+    }
+
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        2..4
+    }
+
+    /// Playing a card means discarding it, which ends the turn
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, true) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let team = player % 2;
+        if self.rummy.hands()[player] == [card] && self.took_pozzetto[team] {
+            if !self.has_burraco(team) {
+                return vec![GameStatus::InvalidMove(GameError::BurracoNeeded)];
+            } else if is_wildcard(&card) {
+                return vec![GameStatus::InvalidMove(GameError::CannotDiscardWildcard)];
+            }
+        }
+        if let Err(e) = self.rummy.discard(by, card) {
+            return vec![GameStatus::InvalidMove(e)];
+        }
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        if self.rummy.hands()[player].is_empty() {
+            if self.took_pozzetto[team] {
+                res.append(&mut self.end_hand(Some(player)));
+                return res;
+            }
+            self.take_pozzetto(player);
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} prende il pozzetto",
+                by.name
+            )));
+        }
+        if self.rummy.stock().is_empty() {
+            res.append(&mut self.end_hand(None));
+            return res;
+        }
+        res.append(&mut self.rummy.next_turn(player));
+        res
+    }
+
+    /// After drawing, any card in hand can be discarded
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        self.rummy.legal_moves(player)
+    }
+
+    fn handle_action(&mut self, by: &Player, action: Action) -> Vec<GameStatus> {
+        match action {
+            Action::PlayCard(card) => self.handle_move(by, card),
+            Action::Draw => self.draw(by, false),
+            Action::PickUp => self.draw(by, true),
+            Action::Meld(groups) => self.meld(by, groups),
+            Action::Attach(meld, cards) => self.attach(by, meld, cards),
+        }
+    }
+
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.started {
+            Err(GameError::AlreadyStarted)
+        } else if self.rummy.players().len() < self.get_num_players().end as usize {
+            self.rummy.add_player(player.clone());
+            Ok(GameStatus::WaitingForPlayers(
+                self.rummy.players().len().is_multiple_of(2),
+                player,
+            ))
+        } else {
+            Err(GameError::GameFull)
+        }
+    }
+
+    fn get_next_player(&self) -> Option<Player> {
+        self.rummy
+            .next_player()
+            .map(|x| self.rummy.players()[x].clone())
+    }
+
+    fn start(&mut self) -> GameStatus {
+        if self.started {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.rummy.players().len() != 2 && self.rummy.players().len() != 4 {
+            return GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(vec![2, 4]));
+        }
+        self.started = true;
+        self.points = vec![0, 0];
+        // Il mazziere è l'ultimo giocatore, così inizia il primo
+        self.deal();
+        self.rummy.turn_message(0)
+    }
+
+    /// The points of each team, capped to what fits in the fraction.
+    /// Use [`Game::get_points`] for the actual points.
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.get_points()
+            .into_iter()
+            .map(|x| {
+                (
+                    x.0,
+                    fraction::GenericFraction::new(x.1.clamp(0, u8::MAX as i32) as u8, 1u8),
+                )
+            })
+            .collect()
+    }
+
+    /// The points of each team in the hand, which can be negative
+    fn get_points(&self) -> Vec<(Vec<Player>, i32)> {
+        self.teams()
+            .into_iter()
+            .zip(self.points.iter().cloned())
+            .collect()
+    }
+
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nTocca a: {}\nScarto: {} ({} carte)\nCarte nel mazzo: {}\n{}",
+            self.get_name(),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            self.rummy
+                .discarded()
+                .last()
+                .map(|x| text!("{}", x))
+                .unwrap_or_else(|| Text::new("nessuno")),
+            self.rummy.discarded().len(),
+            self.rummy.stock().len(),
+            Text::join(
+                self.teams()
+                    .iter()
+                    .zip(self.melds.iter())
+                    .enumerate()
+                    .map(|x| text!(
+                        "Combinazioni di {}{}:\n{}",
+                        Text::join((x.1).0.iter().map(|y| &y.name), " e "),
+                        if self.took_pozzetto[x.0] {
+                            Text::new(" (pozzetto preso)")
+                        } else {
+                            Text::new("")
+                        },
+                        Text::join(
+                            (x.1).1.iter().enumerate().map(|y| text!(
+                                "{}. {}{}",
+                                y.0 + 1,
+                                Text::join(y.1, ", "),
+                                match (y.1.len() >= BURRACO_SIZE, meld_is_clean(y.1)) {
+                                    (true, Some(true)) => Text::new(" (burraco pulito)"),
+                                    (true, _) => Text::new(" (burraco sporco)"),
+                                    _ => Text::new(""),
+                                }
+                            )),
+                            "\n"
+                        )
+                    )),
+                "\n"
+            )
+        )
+    }
+
+    fn get_players(&self) -> Vec<Player> {
+        self.rummy.players().to_vec()
+    }
+
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::default())
+    }
+
+    /// Only the card on top of the discard pile can be seen
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.rummy.hand_of(player),
+            table: self.rummy.table_view(),
+            melds: self
+                .teams()
+                .into_iter()
+                .zip(self.melds.iter().cloned())
+                .collect(),
+            hand_sizes: self.rummy.hand_sizes(),
+            stock: self.rummy.stock().len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Burraco(self.clone()).into()
+    }
+
+    /// Players draw, meld and attach cards by writing commands, everything else is chat
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        match self.rummy.parse_message(&message, &from) {
+            Some(Ok(action)) => self.handle_action(&from, action),
+            Some(Err(e)) => vec![GameStatus::InvalidMove(e)],
+            None => vec![GameStatus::NotifyRoom(text!(
+                "{} ha detto: {}",
+                from.name,
+                message
+            ))],
+        }
+    }
+}
+
+impl CardValues for Burraco {
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        fraction::GenericFraction::new(card_points(card) as u8, 1u8)
+    }
+
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        card_number(card).unwrap_or(14)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    fn started_game() -> Burraco {
+        testing::started(Burraco::default(), 4)
+    }
+
+    #[test]
+    fn melds() {
+        // Tris sporco
+        assert_eq!(
+            meld_is_clean(&[
+                Card::new(King, Hearts),
+                Card::new(King, Hearts),
+                Card::JOKER
+            ]),
+            Some(false)
+        );
+        // Il due al suo posto non è una matta
+        assert_eq!(
+            meld_is_clean(&[
+                Card::new(Ace, Spades),
+                Card::new(Two, Spades),
+                Card::new(Three, Spades)
+            ]),
+            Some(true)
+        );
+        // Un due come matta in una scala con l'asso alto
+        assert_eq!(
+            meld_is_clean(&[
+                Card::new(Queen, Diamonds),
+                Card::new(Two, Hearts),
+                Card::new(Ace, Diamonds)
+            ]),
+            Some(false)
+        );
+        // Due matte non sono ammesse
+        assert_eq!(
+            meld_is_clean(&[Card::new(Five, Hearts), Card::new(Two, Hearts), Card::JOKER]),
+            None
+        );
+        // Semi diversi
+        assert_eq!(
+            meld_is_clean(&[
+                Card::new(Four, Hearts),
+                Card::new(Five, Spades),
+                Card::new(Six, Hearts)
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn pozzetto_and_closing() {
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        game.handle_message("pesca".to_owned(), first.clone());
+        *game.rummy.hand_mut(index) = vec![
+            Card::new(Three, Hearts),
+            Card::new(Four, Hearts),
+            Card::new(Five, Hearts),
+            Card::new(Six, Hearts),
+            Card::new(Seven, Hearts),
+            Card::new(Eight, Hearts),
+            Card::new(Nine, Hearts),
+            Card::new(King, Spades),
+        ];
+        // Calando tutto tranne una carta fa un burraco pulito
+        game.handle_message("cala 1 2 3 4 5 6 7".to_owned(), first.clone());
+        assert!(game.has_burraco(index % 2));
+        game.handle_move(&first, Card::new(King, Spades));
+        assert!(game.took_pozzetto[index % 2]);
+        assert_eq!(game.rummy.hands()[index].len(), HAND_SIZE);
+        assert_ne!(game.get_next_player().unwrap(), first);
+        // Chi ha già preso il pozzetto deve tenere una carta da scartare
+        game.rummy.set_turn(index, true);
+        *game.rummy.hand_mut(index) = vec![Card::new(Ten, Hearts), Card::new(Jack, Hearts)];
+        game.rummy.hand_mut((index + 2) % 4).clear();
+        let res = game.handle_message("attacca 1 1 2".to_owned(), first.clone());
+        assert!(matches!(res[0], GameStatus::InvalidMove(_)));
+        game.handle_message("attacca 1 1".to_owned(), first.clone());
+        assert_eq!(game.melds[index % 2][0].len(), 8);
+        let res = game.handle_move(&first, Card::new(Jack, Hearts));
+        assert!(res.iter().any(|x| matches!(x, GameStatus::RoundWon(_))));
+        assert!(res.iter().any(|x| matches!(x, GameStatus::GameEnded)));
+        // 8 carte (3-7 valgono 5, 8-10 valgono 10) più burraco pulito e chiusura
+        assert_eq!(
+            game.get_points()[index % 2].1,
+            55 + CLEAN_BURRACO_BONUS + CLOSING_BONUS
+        );
+    }
+
+    #[test]
+    fn actions() {
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let second = game.rummy.players()[(game.rummy.next_player().unwrap() + 1) % 4].clone();
+        assert!(matches!(
+            game.handle_action(&second, Action::Draw)[0],
+            GameStatus::InvalidMove(_)
+        ));
+        let discarded = game.rummy.discarded().to_vec();
+        game.handle_action(&first, Action::PickUp);
+        assert!(game.rummy.discarded().is_empty());
+        assert!(game.rummy.hands()[game.rummy.next_player().unwrap()].contains(&discarded[0]));
+        assert!(matches!(
+            game.handle_action(&first, Action::Draw)[0],
+            GameStatus::InvalidMove(_)
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+//! Marafone, or Beccaccino romagnolo: hands of [`Beccaccino`] played with the [`Rules::MARAFONE`],
+//! where who chooses the briscola scores the marafona, until a team reaches 41 points.
+use super::beccaccino::{Beccaccino, Rules};
+use crate::multi_hand::{Match, Target};
+
+/// Points needed to win a match
+pub const TARGET: u16 = 41;
+
+/// A match of Marafone, where the first player moves one seat to the right at every hand
+pub fn new() -> Match {
+    Match::new(
+        Box::new(Beccaccino::with_rules(Rules::MARAFONE)),
+        Target::Points(TARGET),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::*;
+
+    #[test]
+    fn played_to_41() {
+        let game = new();
+        assert_eq!(game.get_name(), "Marafone");
+        assert_eq!(game.get_title().to_string(), "Marafone ai 41");
+    }
+}
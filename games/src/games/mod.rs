@@ -1,3 +1,11 @@
 pub mod beccaccino;
 pub mod briscola;
-//pub mod scala40;
+pub mod briscola_chiamata;
+pub mod burraco;
+pub mod marafone;
+pub mod rubamazzo;
+pub mod scala40;
+pub mod scopa;
+pub mod scopone;
+pub mod sette_e_mezzo;
+pub mod tressette;
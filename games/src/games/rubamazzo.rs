@@ -0,0 +1,293 @@
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::{self, PlayerView};
+use serde::{Deserialize, Serialize};
+
+/// Cards dealt to each player every time hands are empty
+const HAND_SIZE: usize = 3;
+/// Cards put face up on the table at the start of the game
+const TABLE_SIZE: usize = 4;
+
+/// Rubamazzo: a card takes a card of the same rank from the table
+/// or steals an opponent's pile if it matches the card on top of it.
+/// Whoever has the biggest pile at the end wins.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Rubamazzo {
+    players: Vec<Player>,
+    in_hand: Vec<Vec<Card>>,
+    deck: CardDeck,
+    table: Vec<Card>,
+    /// The pile of each player, the last card is the one on top
+    piles: Vec<Vec<Card>>,
+    next_player: Option<usize>,
+    shuffler: utils::Shuffler,
+}
+
+impl Rubamazzo {
+    fn deal(&mut self) {
+        for hand in self.in_hand.iter_mut() {
+            let cards = self.deck.len().min(HAND_SIZE);
+            hand.extend(self.deck.split_off(self.deck.len() - cards));
+        }
+    }
+    /// The opponent with the biggest pile whose top card has the same rank of `card`
+    fn pile_to_steal(&self, player: usize, card: &Card) -> Option<usize> {
+        (0..self.players.len())
+            .filter(|x| *x != player)
+            .filter(|x| {
+                self.piles[*x]
+                    .last()
+                    .map(|y| y.rank == card.rank)
+                    .unwrap_or(false)
+            })
+            .max_by_key(|x| self.piles[*x].len())
+    }
+}
+
+impl Game for Rubamazzo {
+    fn init(&mut self) {
+        self.deck = self.shuffler.deck(CardDeckType::Briscola);
+    }
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        "Rubamazzo"
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Briscola
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        2..4
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let player = match self.next_player {
+            None => return vec![GameStatus::InvalidMove(GameError::GameNotStarted)],
+            Some(x) => x,
+        };
+        if &self.players[player] != by {
+            return vec![GameStatus::InvalidMove(GameError::NotYourTurn)];
+        }
+        let card_index = match self.in_hand[player].iter().position(|x| x == &card) {
+            Some(x) => x,
+            None => return vec![GameStatus::InvalidMove(GameError::CardNotInHand)],
+        };
+        self.in_hand[player].remove(card_index);
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        // Rubare un mazzo vale più di una presa dal tavolo
+        if let Some(victim) = self.pile_to_steal(player, &card) {
+            let mut stolen = std::mem::take(&mut self.piles[victim]);
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} ruba il mazzo di {} ({} carte)",
+                by.name,
+                self.players[victim].name,
+                stolen.len()
+            )));
+            self.piles[player].append(&mut stolen);
+            self.piles[player].push(card);
+        } else if let Some(i) = self.table.iter().position(|x| x.rank == card.rank) {
+            let taken = self.table.remove(i);
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} prende {}",
+                by.name,
+                taken
+            )));
+            self.piles[player].push(taken);
+            self.piles[player].push(card);
+        } else {
+            self.table.push(card);
+        }
+        if self.in_hand.iter().all(|x| x.is_empty()) {
+            if self.deck.is_empty() {
+                self.next_player = None;
+                res.push(GameStatus::GameEnded);
+                return res;
+            }
+            self.deal();
+        }
+        let next_player = (player + 1) % self.players.len();
+        self.next_player = Some(next_player);
+        res.push(GameStatus::WaitingForChoice(
+            self.players[next_player].clone(),
+            self.in_hand[next_player].clone(),
+        ));
+        res.push(GameStatus::InProgress(self.players[next_player].clone()));
+        res
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match self.next_player {
+            Some(x) if &self.players[x] == player => self.in_hand[x].clone(),
+            _ => vec![],
+        }
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.next_player.is_some() {
+            Err(GameError::AlreadyStarted)
+        } else if self.players.len() >= self.get_num_players().end as usize {
+            Err(GameError::GameFull)
+        } else {
+            self.players.push(player.clone());
+            self.in_hand.push(vec![]);
+            self.piles.push(vec![]);
+            Ok(GameStatus::WaitingForPlayers(
+                self.players.len() >= self.get_num_players().start as usize,
+                player,
+            ))
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.next_player.map(|x| self.players[x].clone())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.next_player.is_some() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.players.len() < self.get_num_players().start as usize {
+            return GameStatus::InvalidMove(GameError::NotEnoughPlayers);
+        }
+        self.table = self.deck.split_off(self.deck.len() - TABLE_SIZE);
+        self.deal();
+        self.next_player = Some(0);
+        GameStatus::WaitingForChoice(self.players[0].clone(), self.in_hand[0].clone())
+    }
+    /// The number of cards in each pile
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.players
+            .iter()
+            .zip(self.piles.iter())
+            .map(|x| {
+                (
+                    vec![x.0.clone()],
+                    fraction::GenericFraction::new(x.1.len() as u8, 1u8),
+                )
+            })
+            .collect()
+    }
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nMazzi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+            self.get_name(),
+            Text::join(
+                self.players.iter().zip(self.piles.iter()).map(|x| text!(
+                    "{}: {} carte{}",
+                    &x.0.name,
+                    x.1.len(),
+                    x.1.last()
+                        .map(|y| text!(", in cima {}", y))
+                        .unwrap_or_else(|| Text::new(""))
+                )),
+                "\n"
+            ),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            Text::join(self.table.iter().map(|x| text!("- {}", x)), "\n")
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::default())
+    }
+    /// Only the card on top of each pile can be seen
+    fn view_for(&self, player: &Player) -> PlayerView {
+        let me = self.players.iter().position(|x| x == player);
+        PlayerView {
+            hand: me.map(|x| self.in_hand[x].clone()).unwrap_or_default(),
+            table: self.table.iter().map(|x| (None, *x)).collect(),
+            face_up: self
+                .players
+                .iter()
+                .cloned()
+                .zip(
+                    self.piles
+                        .iter()
+                        .map(|x| x.last().cloned().into_iter().collect()),
+                )
+                .collect(),
+            captured: self
+                .players
+                .iter()
+                .map(|x| vec![x.clone()])
+                .zip(self.piles.iter().map(|x| x.len()))
+                .collect(),
+            hand_sizes: view::hand_sizes(&self.players, &self.in_hand),
+            stock: self.deck.len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Rubamazzo(self.clone()).into()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        vec![GameStatus::NotifyRoom(text!(
+            "{} ha detto: {}",
+            from.name,
+            message
+        ))]
+    }
+}
+
+impl CardValues for Rubamazzo {
+    /// Every card is worth one point
+    fn get_card_rank(_card: &Rank) -> fraction::GenericFraction<u8> {
+        utils::one()
+    }
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        match card {
+            Rank::Jack => 8,
+            Rank::Queen => 9,
+            Rank::King => 10,
+            x => x.number(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    fn started_game() -> Rubamazzo {
+        let mut game = Rubamazzo::default();
+        game.init();
+        testing::started(game, 3)
+    }
+
+    #[test]
+    fn capture_from_the_table() {
+        let mut game = started_game();
+        game.table = vec![Card::new(Five, Coppe), Card::new(King, Spade)];
+        game.in_hand[0][0] = Card::new(King, Denari);
+        game.handle_move(&game.players[0].clone(), Card::new(King, Denari));
+        assert_eq!(game.table, vec![Card::new(Five, Coppe)]);
+        assert_eq!(
+            game.piles[0],
+            vec![Card::new(King, Spade), Card::new(King, Denari)]
+        );
+    }
+
+    #[test]
+    fn steal_the_biggest_pile() {
+        let mut game = started_game();
+        game.table = vec![Card::new(King, Spade)];
+        game.piles[1] = vec![Card::new(Two, Coppe), Card::new(King, Coppe)];
+        game.piles[2] = vec![Card::new(King, Bastoni)];
+        game.in_hand[0][0] = Card::new(King, Denari);
+        game.handle_move(&game.players[0].clone(), Card::new(King, Denari));
+        assert_eq!(game.table, vec![Card::new(King, Spade)]);
+        assert!(game.piles[1].is_empty());
+        assert_eq!(game.piles[0].len(), 3);
+        assert_eq!(game.piles[0].last(), Some(&Card::new(King, Denari)));
+        assert!(game.get_status().to_string().contains("in cima"));
+    }
+}
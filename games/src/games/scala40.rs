@@ -1,30 +1,362 @@
+use crate::i18n::Text;
 use crate::primitives::*;
-#[derive(Default)]
+use crate::rummy::{card_number, suit_index, Rummy, RummyRules};
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::PlayerView;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Points needed to open
+const OPENING_POINTS: u16 = 40;
+/// Penalty for a player who didn't open before someone closed
+const NOT_OPENED_PENALTY: u16 = 100;
+/// Cards dealt to each player
+const HAND_SIZE: usize = 13;
+
+/// A hand of Scala 40, which ends when someone closes and charges the others with the cards left in hand.
+/// It's played in a [`Match`](crate::multi_hand::Match) where players reaching 101 penalty points are out.
+/// Who hasn't opened can pick up the discard only to open with it, and who has opened
+/// can take a joker from the table by attaching the card it stands for.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Scala40 {
-    discarded: Vec<Card>,
-    scale: std::collections::HashMap<Player, Vec<Vec<Card>>>,
-    /// List of players
-    players: Vec<Player>,
-    /// Card in hand for each player
-    in_hand: std::collections::HashMap<Player, Vec<Card>>,
-    /// Teams
-    teams: Vec<Vec<Player>>,
-    player_team: std::collections::HashMap<Player, usize>,
+    /// Hands, stock, discard pile and turns
+    rummy: Rummy<Scala40>,
+    /// Melds (scale and tris) on the table
+    scale: Vec<Vec<Card>>,
+    /// Whether each player has already opened in this hand
+    opened: Vec<bool>,
+    /// The discard picked up by who hasn't opened yet, who has to open with it
+    picked_up: Option<Card>,
+    /// Penalty points of each player, charged when the hand ends
+    penalties: Vec<u16>,
     started: bool,
+    shuffler: utils::Shuffler,
+}
+
+impl RummyRules for Scala40 {
+    const PICK_UP_ALL: bool = false;
+    const TEAM_MELDS: bool = false;
+    fn sort_hand(hand: &mut [Card]) {
+        hand.sort_by_key(|x| (suit_index(&x.suit), Self::get_card_sorting_rank(&x.rank)));
+    }
 }
 
 impl Scala40 {
-    fn game_has_been_won(&self) -> bool {
-        false
+    fn is_card_stackable(&self, meld: usize, card: &Card) -> bool {
+        let mut cards = self.scale[meld].clone();
+        cards.push(*card);
+        meld_points(&cards).is_some()
+    }
+    /// Whether the card stands where the joker in position `joker` of the meld does,
+    /// so that the meld is worth the same with the card in its place
+    fn can_replace_joker(&self, meld: usize, joker: usize, card: &Card) -> bool {
+        let mut cards = self.scale[meld].clone();
+        let points = meld_points(&cards);
+        cards[joker] = *card;
+        meld_points(&cards) == points
+    }
+    /// Shuffles two decks and deals the hands, the first player starts
+    fn deal(&mut self) {
+        let mut deck = self.shuffler.deck(CardDeckType::Poker);
+        deck.append(&mut self.shuffler.deck(CardDeckType::Poker));
+        self.rummy.deal(deck, HAND_SIZE);
+    }
+    /// Who hasn't opened can pick up the discard only to open with it. When the stock is over
+    /// the discards are shuffled into a new one, and the hand ends if there are none.
+    fn draw(&mut self, by: &Player, pick_up: bool) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, false) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        if pick_up && !self.opened[player] {
+            self.picked_up = self.rummy.discarded().last().cloned();
+        }
+        if !pick_up {
+            self.rummy.reshuffle_discards(&mut self.shuffler);
+            if self.rummy.stock().is_empty() {
+                // Non ci sono più carte da pescare, la mano finisce senza vincitori
+                return self.close_hand(None);
+            }
+        }
+        match self.rummy.draw(by, pick_up) {
+            Ok(mut res) => {
+                if self.picked_up.is_some() {
+                    res.push(GameStatus::NotifyUser(
+                        by.clone(),
+                        GameError::MustOpenWithPickedUp.text(),
+                    ));
+                }
+                res.push(GameStatus::NotifyRoom(self.get_status()));
+                res
+            }
+            Err(e) => vec![GameStatus::InvalidMove(e)],
+        }
+    }
+    /// Who picked up the discard without opening puts it back and draws from the stock instead,
+    /// then goes on with their turn
+    fn give_back(&mut self, by: &Player, card: Card, picked_up: Card) -> Vec<GameStatus> {
+        if let Err(e) = self.rummy.check_turn(by, true) {
+            return vec![GameStatus::InvalidMove(e)];
+        }
+        if card != picked_up {
+            return vec![GameStatus::InvalidMove(GameError::MustOpenWithPickedUp)];
+        }
+        let player = match self.rummy.discard(by, card) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        self.picked_up = None;
+        self.rummy.reshuffle_discards(&mut self.shuffler);
+        let mut drawn = self.rummy.take_from_stock(1);
+        if drawn.is_empty() {
+            return self.close_hand(None);
+        }
+        let mut hand = self.rummy.hands()[player].clone();
+        hand.append(&mut drawn);
+        self.rummy.set_hand(player, hand);
+        let mut res = vec![GameStatus::NotifyRoom(text!(
+            "{} rimette {} negli scarti e pesca dal mazzo",
+            by.name,
+            card
+        ))];
+        res.append(&mut self.rummy.play_message(player));
+        res.push(GameStatus::NotifyRoom(self.get_status()));
+        res
+    }
+    fn meld(&mut self, by: &Player, groups: Vec<Vec<Card>>) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, true) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        if groups.is_empty() {
+            return vec![GameStatus::InvalidMove(GameError::NoCardsSelected)];
+        }
+        let points = match groups
+            .iter()
+            .map(|x| meld_points(x))
+            .collect::<Option<Vec<u8>>>()
+        {
+            Some(x) => x.iter().map(|y| *y as u16).sum::<u16>(),
+            None => return vec![GameStatus::InvalidMove(GameError::InvalidMeld)],
+        };
+        if !self.opened[player] && points < OPENING_POINTS {
+            return vec![GameStatus::InvalidMove(GameError::NotEnoughPointsToOpen(
+                OPENING_POINTS,
+            ))];
+        }
+        if let Some(card) = self.picked_up {
+            if !groups.iter().flatten().any(|x| x == &card) {
+                return vec![GameStatus::InvalidMove(GameError::MustOpenWithPickedUp)];
+            }
+        }
+        let hand = match self.rummy.hand_without(player, &groups.concat()) {
+            Ok(x) if x.is_empty() => {
+                return vec![GameStatus::InvalidMove(GameError::MustKeepADiscard)]
+            }
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        self.rummy.set_hand(player, hand);
+        self.opened[player] = true;
+        self.picked_up = None;
+        self.scale.extend(groups);
+        self.after_table_move(player)
+    }
+    fn attach(&mut self, by: &Player, meld: usize, cards: Vec<Card>) -> Vec<GameStatus> {
+        let player = match self.rummy.check_turn(by, true) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        if !self.opened[player] {
+            return vec![GameStatus::InvalidMove(GameError::NotOpenedYet)];
+        }
+        if meld >= self.scale.len() {
+            return vec![GameStatus::InvalidMove(GameError::NoSuchMeld)];
+        }
+        let joker = self.scale[meld].iter().position(|x| x.rank == Rank::Joker);
+        if let ([card], Some(joker)) = (cards.as_slice(), joker) {
+            if self.can_replace_joker(meld, joker, card) {
+                return self.replace_joker(player, meld, joker, *card);
+            }
+        }
+        let stackable = match cards.as_slice() {
+            [] => false,
+            [x] => self.is_card_stackable(meld, x),
+            _ => {
+                let mut meld = self.scale[meld].clone();
+                meld.extend(cards.iter().cloned());
+                meld_points(&meld).is_some()
+            }
+        };
+        if !stackable {
+            return vec![GameStatus::InvalidMove(GameError::CannotAttach)];
+        }
+        let hand = match self.rummy.hand_without(player, &cards) {
+            Ok(x) if x.is_empty() => {
+                return vec![GameStatus::InvalidMove(GameError::MustKeepADiscard)]
+            }
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        self.rummy.set_hand(player, hand);
+        self.scale[meld].extend(cards);
+        self.after_table_move(player)
+    }
+    /// The card takes the place of the joker in the meld, the joker goes in the hand of the player
+    fn replace_joker(
+        &mut self,
+        player: usize,
+        meld: usize,
+        joker: usize,
+        card: Card,
+    ) -> Vec<GameStatus> {
+        let mut hand = match self.rummy.hand_without(player, &[card]) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        hand.push(self.scale[meld][joker]);
+        self.rummy.set_hand(player, hand);
+        self.scale[meld][joker] = card;
+        let mut res = vec![GameStatus::NotifyRoom(text!(
+            "{} prende il jolly e mette al suo posto {}",
+            self.rummy.players()[player].name,
+            card
+        ))];
+        res.append(&mut self.after_table_move(player));
+        res
+    }
+    /// A meld or an attach always leaves a card to discard, closing is done by discarding
+    fn after_table_move(&mut self, player: usize) -> Vec<GameStatus> {
+        let mut res = self.rummy.play_message(player);
+        res.push(GameStatus::NotifyRoom(self.get_status()));
+        res
+    }
+    /// Someone discarded their last card: charge the penalties and end the hand.
+    /// Without a winner the cards ran out and nobody is charged.
+    fn close_hand(&mut self, winner: Option<usize>) -> Vec<GameStatus> {
+        let mut res = match winner {
+            Some(winner) => vec![
+                GameStatus::RoundWon(self.rummy.players()[winner].clone()),
+                GameStatus::NotifyRoom(text!("{} ha chiuso!", self.rummy.players()[winner].name)),
+            ],
+            None => vec![GameStatus::NotifyRoom(Text::new(
+                "Le carte sono finite, la mano si chiude senza vincitori",
+            ))],
+        };
+        for player in 0..self.rummy.players().len() {
+            if winner.is_none() || Some(player) == winner {
+                continue;
+            }
+            let penalty = if self.opened[player] {
+                self.rummy.hands()[player]
+                    .iter()
+                    .map(|x| penalty_points(&x.rank) as u16)
+                    .sum()
+            } else {
+                NOT_OPENED_PENALTY
+            };
+            self.penalties[player] = penalty;
+        }
+        self.rummy.set_next_player(None);
+        res.push(GameStatus::NotifyRoom(self.get_status()));
+        res.push(GameStatus::GameEnded);
+        res
+    }
+}
+
+/// How much the card is worth when it's melded in a set
+fn set_points(card: &Rank) -> u8 {
+    match card_number(card) {
+        Some(1) => 11,
+        Some(x) if x > 10 => 10,
+        Some(x) => x,
+        None => 0,
     }
-    fn is_card_stackable(&self, player: Player, card: Card) -> bool {
-        false
+}
+
+/// How much the card in position `number` is worth when it's melded in a run.
+/// The ace is worth one after the two and eleven after the king.
+fn run_points(number: u8) -> u8 {
+    match number {
+        14 => 11,
+        11..=13 => 10,
+        x => x,
     }
 }
 
+/// Penalty for a card left in hand when someone closes
+fn penalty_points(card: &Rank) -> u8 {
+    match card {
+        Rank::Joker => 25,
+        x => set_points(x),
+    }
+}
+
+/// Checks whether the cards form a valid set (three or four cards of the same rank
+/// and different suits) or run (at least three cards in sequence of the same suit).
+/// A meld can contain at most one joker.
+/// If the meld is valid its value in points is returned.
+fn meld_points(cards: &[Card]) -> Option<u8> {
+    let jokers = cards.iter().filter(|x| x.rank == Rank::Joker).count() as u8;
+    let naturals: Vec<&Card> = cards.iter().filter(|x| x.rank != Rank::Joker).collect();
+    if jokers > 1 || naturals.is_empty() || cards.len() < 3 {
+        return None;
+    }
+    // Tris o poker
+    if cards.len() <= 4
+        && naturals.iter().all(|x| x.rank == naturals[0].rank)
+        && naturals.iter().map(|x| suit_index(&x.suit)).all_unique()
+    {
+        return Some(set_points(&naturals[0].rank) * cards.len() as u8);
+    }
+    // Scala
+    if !naturals.iter().all(|x| x.suit == naturals[0].suit) {
+        return None;
+    }
+    [false, true]
+        .iter()
+        .filter_map(|ace_high| {
+            let numbers: Vec<u8> = naturals
+                .iter()
+                .map(|x| match card_number(&x.rank) {
+                    Some(1) if *ace_high => 14,
+                    x => x.unwrap(),
+                })
+                .sorted()
+                .collect();
+            if !numbers.iter().all_unique() {
+                return None;
+            }
+            let gaps: u8 = numbers.windows(2).map(|x| x[1] - x[0] - 1).sum();
+            if gaps > jokers {
+                return None;
+            }
+            let mut low = numbers[0];
+            let mut high = numbers[numbers.len() - 1];
+            if gaps < jokers {
+                // Il jolly allunga la scala
+                if high < 14 {
+                    high += 1;
+                } else if low > 1 {
+                    low -= 1;
+                } else {
+                    return None;
+                }
+            }
+            Some((low..=high).map(run_points).sum())
+        })
+        .max()
+}
+
 impl Game for Scala40 {
-    fn init(&mut self) {
-        //
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
     }
 
     fn get_name(&self) -> &str {
@@ -39,74 +371,417 @@ impl Game for Scala40 {
         2..6
     }
 
+    /// Playing a card means discarding it, which ends the turn
     fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
-        todo!()
-    }
-
-    fn get_card_rank(card: &CardType) -> fraction::Fraction
-    where
-        Self: Sized,
-    {
-        fraction::Fraction::new(Self::get_card_sorting_rank(card), 1u8)
-    }
-
-    fn get_card_sorting_rank(card: &CardType) -> u8
-    where
-        Self: Sized,
-    {
-        match card {
-            CardType::Numeric(x) => *x,
-            CardType::King => 10,
-            CardType::Queen => 10,
-            CardType::Jack => 10,
-            CardType::Jolly => 0,
+        if let Some(picked_up) = self.picked_up {
+            return self.give_back(by, card, picked_up);
         }
+        let player = match self.rummy.discard(by, card) {
+            Ok(x) => x,
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+        };
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        if self.rummy.hands()[player].is_empty() {
+            res.append(&mut self.close_hand(Some(player)));
+        } else {
+            res.append(&mut self.rummy.next_turn(player));
+        }
+        res
     }
 
-    fn add_player(&mut self, player: Player) -> Result<GameStatus, &str> {
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
         if self.started {
-            Err("Game has already been started")
-        } else if self.players.len() <= self.get_num_players().end as usize {
-            self.players.push(player);
-            //self.in_hand.insert(&player, vec![]);
+            Err(GameError::AlreadyStarted)
+        } else if self.rummy.players().len() < self.get_num_players().end as usize {
+            self.rummy.add_player(player.clone());
+            self.opened.push(false);
+            self.penalties.push(0);
             Ok(GameStatus::WaitingForPlayers(
-                self.players.len() >= self.get_num_players().start as usize,
+                self.rummy.players().len() >= self.get_num_players().start as usize,
                 player,
             ))
         } else {
-            Err("The game is full")
+            Err(GameError::GameFull)
         }
     }
 
     fn get_next_player(&self) -> Option<Player> {
-        todo!()
+        self.rummy
+            .next_player()
+            .map(|x| self.rummy.players()[x].clone())
     }
 
     fn start(&mut self) -> GameStatus {
-        let mut deck = crate::utils::random_deck(CardDeckType::Poker);
-        deck.append(&mut crate::utils::random_deck(CardDeckType::Poker));
+        if self.started {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.rummy.players().len() < self.get_num_players().start as usize {
+            return GameStatus::InvalidMove(GameError::NotEnoughPlayers);
+        }
+        self.started = true;
+        // Il mazziere è l'ultimo giocatore, così inizia il primo
+        self.deal();
+        self.rummy.turn_message(0)
+    }
+
+    /// Penalty points of each player: the lower, the better
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.rummy
+            .players()
+            .iter()
+            .zip(self.penalties.iter())
+            .map(|x| {
+                (
+                    vec![x.0.clone()],
+                    fraction::GenericFraction::new((*x.1).min(u8::MAX as u16) as u8, 1u8),
+                )
+            })
+            .collect()
     }
 
-    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::Fraction)> {
-        todo!()
+    /// Penalty points of each player, even the ones that don't fit in the scores
+    fn get_points(&self) -> Vec<(Vec<Player>, i32)> {
+        self.rummy
+            .players()
+            .iter()
+            .zip(self.penalties.iter())
+            .map(|x| (vec![x.0.clone()], *x.1 as i32))
+            .collect()
     }
 
-    fn get_status(&self) -> String {
-        todo!()
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nPenalità:\n{}\nTocca a: {}\nScarto: {}\nCarte nel mazzo: {}\nCombinazioni sul tavolo:\n{}",
+            self.get_name(),
+            Text::join(
+                self.rummy
+                    .players()
+                    .iter()
+                    .zip(self.penalties.iter())
+                    .map(|x| text!("{}: {} punti", &x.0.name, x.1)),
+                "\n"
+            ),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            self.rummy
+                .discarded()
+                .last()
+                .map(|x| text!("{}", x))
+                .unwrap_or_else(|| Text::new("")),
+            self.rummy.stock().len(),
+            Text::join(
+                self.scale
+                    .iter()
+                    .enumerate()
+                    .map(|x| text!("{}. {}", x.0 + 1, Text::join(x.1, ", "))),
+                "\n"
+            )
+        )
     }
 
     fn get_players(&self) -> Vec<Player> {
-        todo!()
+        self.rummy.players().to_vec()
     }
 
     fn get_new_instance(&self) -> Box<dyn Game> {
-        todo!()
+        Box::new(Self::default())
+    }
+
+    /// Only the card on top of the discard pile can be seen
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.rummy.hand_of(player),
+            table: self.rummy.table_view(),
+            melds: vec![(self.rummy.players().to_vec(), self.scale.clone())],
+            hand_sizes: self.rummy.hand_sizes(),
+            stock: self.rummy.stock().len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Scala40(self.clone()).into()
+    }
+
+    /// After drawing, any card in hand can be discarded.
+    /// Who picked up the discard without opening can only give it back.
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match self.picked_up {
+            Some(card) if !self.rummy.legal_moves(player).is_empty() => vec![card],
+            _ => self.rummy.legal_moves(player),
+        }
+    }
+
+    fn handle_action(&mut self, by: &Player, action: Action) -> Vec<GameStatus> {
+        match action {
+            Action::PlayCard(card) => self.handle_move(by, card),
+            Action::Draw => self.draw(by, false),
+            Action::PickUp => self.draw(by, true),
+            Action::Meld(groups) => self.meld(by, groups),
+            Action::Attach(meld, cards) => self.attach(by, meld, cards),
+        }
+    }
+
+    /// Players draw, meld and attach cards by writing commands, everything else is chat
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        match self.rummy.parse_message(&message, &from) {
+            Some(Ok(action)) => self.handle_action(&from, action),
+            Some(Err(e)) => vec![GameStatus::InvalidMove(e)],
+            None => vec![GameStatus::NotifyRoom(text!(
+                "{} ha detto: {}",
+                from.name,
+                message
+            ))],
+        }
+    }
+}
+
+impl CardValues for Scala40 {
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        fraction::GenericFraction::new(penalty_points(card), 1u8)
+    }
+
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        card_number(card).unwrap_or(14)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn started_game() -> Scala40 {
+        let mut game = Scala40::default();
+        game.init();
+        testing::started(game, 2)
+    }
+
+    #[test]
+    fn melds() {
+        use Rank::*;
+        use Suit::*;
+        // Tris di re
+        assert_eq!(
+            meld_points(&[
+                Card::new(King, Hearts),
+                Card::new(King, Spades),
+                Card::new(King, Diamonds)
+            ]),
+            Some(30)
+        );
+        // Due carte dello stesso seme non fanno un tris
+        assert_eq!(
+            meld_points(&[
+                Card::new(King, Hearts),
+                Card::new(King, Hearts),
+                Card::new(King, Diamonds)
+            ]),
+            None
+        );
+        // Scala bassa con l'asso
+        assert_eq!(
+            meld_points(&[
+                Card::new(Two, Hearts),
+                Card::new(Ace, Hearts),
+                Card::new(Three, Hearts)
+            ]),
+            Some(6)
+        );
+        // Scala alta con l'asso e un jolly al posto del re
+        assert_eq!(
+            meld_points(&[
+                Card::new(Ace, Hearts),
+                Card::JOKER,
+                Card::new(Queen, Hearts)
+            ]),
+            Some(31)
+        );
+        // Due jolly non sono ammessi
+        assert_eq!(
+            meld_points(&[Card::JOKER, Card::JOKER, Card::new(Queen, Hearts)]),
+            None
+        );
+        // Semi diversi
+        assert_eq!(
+            meld_points(&[
+                Card::new(Four, Hearts),
+                Card::new(Five, Spades),
+                Card::new(Six, Hearts)
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn opening_needs_forty_points() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        game.handle_message("pesca".to_owned(), first.clone());
+        *game.rummy.hand_mut(index) = vec![
+            Card::new(Ace, Hearts),
+            Card::new(Ace, Diamonds),
+            Card::new(Ace, Spades),
+            Card::new(King, Hearts),
+            Card::new(King, Diamonds),
+            Card::new(King, Spades),
+            Card::new(Five, Clubs),
+        ];
+        let res = game.handle_message("cala 1 2 3".to_owned(), first.clone());
+        assert!(matches!(res[0], GameStatus::InvalidMove(_)));
+        game.handle_message("cala 1 2 3 / 4 5 6".to_owned(), first.clone());
+        assert!(game.opened[index]);
+        assert_eq!(game.scale.len(), 2);
+        assert_eq!(game.rummy.hands()[index], vec![Card::new(Five, Clubs)]);
+        // Scartando l'ultima carta si chiude
+        let res = game.handle_move(&first, Card::new(Five, Clubs));
+        assert!(res.iter().any(|x| matches!(x, GameStatus::GameEnded)));
+        assert_eq!(game.penalties[index], 0);
+        assert_eq!(game.penalties[1 - index], NOT_OPENED_PENALTY);
+        assert_eq!(game.get_next_player(), None);
+    }
+
+    #[test]
+    fn picking_up_to_open() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        *game.rummy.discarded_mut() = vec![Card::new(King, Spades)];
+        *game.rummy.hand_mut(index) = vec![
+            Card::new(Ace, Clubs),
+            Card::new(Ace, Hearts),
+            Card::new(Ace, Diamonds),
+            Card::new(Ace, Spades),
+            Card::new(King, Hearts),
+            Card::new(King, Diamonds),
+            Card::new(Five, Clubs),
+        ];
+        game.handle_message("raccogli".to_owned(), first.clone());
+        assert_eq!(game.legal_moves(&first), vec![Card::new(King, Spades)]);
+        // Il poker di assi basta per aprire, ma la carta raccolta va usata
+        let res = game.handle_message("cala 1 3 5 7".to_owned(), first.clone());
+        assert!(matches!(
+            res[0],
+            GameStatus::InvalidMove(GameError::MustOpenWithPickedUp)
+        ));
+        let res = game.handle_move(&first, Card::new(Five, Clubs));
+        assert!(matches!(
+            res[0],
+            GameStatus::InvalidMove(GameError::MustOpenWithPickedUp)
+        ));
+        game.handle_message("cala 1 3 5 7 / 4 6 8".to_owned(), first.clone());
+        assert!(game.opened[index]);
+        assert_eq!(game.rummy.hands()[index], vec![Card::new(Five, Clubs)]);
+    }
+
+    #[test]
+    fn giving_back_the_picked_up_card() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        let top = *game.rummy.stock().last().unwrap();
+        *game.rummy.discarded_mut() = vec![Card::new(King, Spades)];
+        game.handle_message("raccogli".to_owned(), first.clone());
+        game.handle_move(&first, Card::new(King, Spades));
+        assert_eq!(game.rummy.discarded(), [Card::new(King, Spades)]);
+        assert!(game.rummy.hands()[index].contains(&top));
+        // Il turno continua, ora si può scartare qualsiasi carta
+        assert_eq!(game.get_next_player(), Some(first.clone()));
+        assert_eq!(game.legal_moves(&first).len(), HAND_SIZE + 1);
+    }
+
+    #[test]
+    fn taking_the_joker() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        game.opened[index] = true;
+        game.scale = vec![vec![
+            Card::new(Five, Hearts),
+            Card::new(Six, Hearts),
+            Card::JOKER,
+        ]];
+        game.handle_message("pesca".to_owned(), first.clone());
+        *game.rummy.hand_mut(index) = vec![
+            Card::new(Four, Hearts),
+            Card::new(Seven, Hearts),
+            Card::new(Five, Clubs),
+        ];
+        // Il quattro allunga la scala, il sette prende il posto del jolly
+        game.handle_message("attacca 1 2".to_owned(), first.clone());
+        assert_eq!(
+            game.scale[0],
+            vec![
+                Card::new(Five, Hearts),
+                Card::new(Six, Hearts),
+                Card::new(Seven, Hearts)
+            ]
+        );
+        assert!(game.rummy.hands()[index].contains(&Card::JOKER));
+        game.handle_message("attacca 1 2".to_owned(), first.clone());
+        assert_eq!(game.scale[0].len(), 4);
+        assert_eq!(game.rummy.hands()[index].len(), 2);
+    }
+
+    #[test]
+    fn turn_order() {
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let card = game.rummy.hands()[game.rummy.next_player().unwrap()][0];
+        assert!(matches!(
+            game.handle_move(&first, card)[0],
+            GameStatus::InvalidMove(_)
+        ));
+        game.handle_message("pesca".to_owned(), first.clone());
+        game.handle_move(&first, card);
+        assert_ne!(game.get_next_player().unwrap(), first);
+    }
+
+    #[test]
+    fn closing_needs_a_discard() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        let index = game.rummy.next_player().unwrap();
+        game.handle_message("pesca".to_owned(), first.clone());
+        *game.rummy.hand_mut(index) = vec![
+            Card::new(Ace, Hearts),
+            Card::new(Ace, Diamonds),
+            Card::new(Ace, Spades),
+            Card::new(King, Hearts),
+            Card::new(King, Diamonds),
+            Card::new(King, Spades),
+        ];
+        // Calando tutto non resterebbe la carta da scartare
+        let res = game.handle_message("cala 1 2 3 / 4 5 6".to_owned(), first.clone());
+        assert!(matches!(
+            res[0],
+            GameStatus::InvalidMove(GameError::MustKeepADiscard)
+        ));
+        assert_eq!(game.rummy.hands()[index].len(), 6);
+        assert!(game.scale.is_empty());
     }
 
-    fn handle_message(&self, message: String, from: Player) -> Vec<GameStatus> {
-        vec![GameStatus::NotifyRoom(format!(
-            "{} ha detto: {}",
-            from.name, message
-        ))]
+    #[test]
+    fn no_cards_left_to_draw() {
+        let mut game = started_game();
+        let first = game.get_next_player().unwrap();
+        game.rummy.stock_mut().clear();
+        game.rummy.discarded_mut().truncate(1);
+        let res = game.handle_message("pesca".to_owned(), first.clone());
+        assert!(!res.iter().any(|x| matches!(x, GameStatus::RoundWon(_))));
+        // La mano finisce e nessuno paga penalità
+        assert!(res.iter().any(|x| matches!(x, GameStatus::GameEnded)));
+        assert_eq!(game.penalties, vec![0, 0]);
     }
 }
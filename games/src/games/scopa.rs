@@ -0,0 +1,600 @@
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::{self, PlayerView};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Cards dealt to each player every time hands are empty
+const HAND_SIZE: usize = 3;
+/// Cards put face up on the table at the start of the hand
+const TABLE_SIZE: usize = 4;
+
+/// A card that has been played when more than one capture was possible,
+/// waiting for the player to choose which cards to take
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PendingCapture {
+    pub(crate) card: Card,
+    pub(crate) options: Vec<Vec<Card>>,
+    pub(crate) chosen: Vec<Card>,
+}
+
+impl PendingCapture {
+    /// The table cards that can still be chosen
+    pub(crate) fn candidates(&self) -> Vec<Card> {
+        let mut res: Vec<Card> = vec![];
+        for card in self.options.iter().flatten() {
+            if !self.chosen.contains(card) && !res.contains(card) {
+                res.push(*card);
+            }
+        }
+        res
+    }
+    /// Adds a card to the chosen ones, returning the capture if it's now unambiguous
+    pub(crate) fn choose(&mut self, card: Card) -> Option<Vec<Card>> {
+        self.chosen.push(card);
+        let chosen = &self.chosen;
+        self.options
+            .retain(|x| chosen.iter().all(|y| x.contains(y)));
+        if self.options.len() == 1 {
+            self.options.first().cloned()
+        } else {
+            self.options
+                .iter()
+                .find(|x| x.len() == chosen.len())
+                .cloned()
+        }
+    }
+}
+
+/// The value of the card when capturing
+pub(crate) fn card_value(card: &Rank) -> u8 {
+    match card {
+        Rank::Jack => 8,
+        Rank::Queen => 9,
+        Rank::King => 10,
+        x => x.number(),
+    }
+}
+
+/// The value of the card when computing the primiera
+pub(crate) fn primiera_value(card: &Rank) -> u8 {
+    match card {
+        Rank::Seven => 21,
+        Rank::Six => 18,
+        Rank::Ace => 16,
+        Rank::Jack | Rank::Queen | Rank::King => 10,
+        x => 10 + x.number(),
+    }
+}
+
+/// All the set of cards that `card` can capture from the table.
+/// If there are cards of the same value only one of them can be taken,
+/// otherwise every combination of cards whose values add up to the played card is valid.
+pub(crate) fn capture_options(table: &[Card], card: &Card) -> Vec<Vec<Card>> {
+    let value = card_value(&card.rank);
+    let same_value: Vec<Vec<Card>> = table
+        .iter()
+        .filter(|x| card_value(&x.rank) == value)
+        .map(|x| vec![*x])
+        .collect();
+    if !same_value.is_empty() {
+        return same_value;
+    }
+    (2..=table.len())
+        .flat_map(|x| table.iter().cloned().combinations(x))
+        .filter(|x| x.iter().map(|y| card_value(&y.rank)).sum::<u8>() == value)
+        .collect()
+}
+
+/// The primiera of a set of cards: the sum of the best card of each suit.
+/// If a suit is missing there's no primiera.
+fn primiera(cards: &[Card]) -> Option<u8> {
+    Suit::ITALIAN
+        .iter()
+        .map(|suit| {
+            cards
+                .iter()
+                .filter(|x| &x.suit == suit)
+                .map(|x| primiera_value(&x.rank))
+                .max()
+        })
+        .sum()
+}
+
+/// Assigns carte, denari, settebello and primiera to the teams according to the cards they took.
+/// Carte, denari and primiera aren't assigned in case of a tie.
+pub(crate) fn awarded_points(won_cards: &[Vec<Card>]) -> Vec<Vec<&'static str>> {
+    let mut res = vec![vec![]; won_cards.len()];
+    let mut award = |scores: Vec<u8>, name: &'static str| {
+        let max = *scores.iter().max().unwrap_or(&0);
+        if max > 0 && scores.iter().filter(|x| **x == max).count() == 1 {
+            let team = scores.iter().position(|x| *x == max).unwrap();
+            res[team].push(name);
+        }
+    };
+    award(won_cards.iter().map(|x| x.len() as u8).collect(), "carte");
+    award(
+        won_cards
+            .iter()
+            .map(|x| x.iter().filter(|y| y.suit == Suit::Denari).count() as u8)
+            .collect(),
+        "denari",
+    );
+    award(
+        won_cards
+            .iter()
+            .map(|x| x.contains(&Card::new(Rank::Seven, Suit::Denari)) as u8)
+            .collect(),
+        "settebello",
+    );
+    award(
+        won_cards.iter().map(|x| primiera(x).unwrap_or(0)).collect(),
+        "primiera",
+    );
+    res
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scopa {
+    players: Vec<Player>,
+    in_hand: Vec<Vec<Card>>,
+    deck: CardDeck,
+    table: Vec<Card>,
+    /// Cards taken by each team
+    won_cards: Vec<Vec<Card>>,
+    /// Scope made by each team
+    scope: Vec<u8>,
+    /// The last player who took some cards, who will take what's left on the table
+    last_capture: Option<usize>,
+    next_player: Option<usize>,
+    pending: Option<PendingCapture>,
+    started: bool,
+    /// Cards dealt to each player every time hands are empty
+    hand_size: usize,
+    /// Cards put face up on the table at the start of the hand
+    table_size: usize,
+    shuffler: utils::Shuffler,
+}
+
+impl Scopa {
+    /// A hand where each player gets `hand_size` cards at a time
+    /// and `table_size` cards are put on the table at the beginning
+    fn with_deal(hand_size: usize, table_size: usize) -> Self {
+        Self {
+            hand_size,
+            table_size,
+            ..Default::default()
+        }
+    }
+    /// A hand of Scopone, played by two teams of two where all the cards are dealt at once:
+    /// nine to each player and four on the table, or ten to each player in the scientifico.
+    /// The match is made by [`scopone::new`](super::scopone::new).
+    pub fn scopone(scientifico: bool) -> Self {
+        if scientifico {
+            Self::with_deal(10, 0)
+        } else {
+            Self::with_deal(9, 4)
+        }
+    }
+    /// Whether all the cards are dealt at once
+    fn is_scopone(&self) -> bool {
+        self.hand_size > HAND_SIZE
+    }
+    fn team_of(&self, player: usize) -> usize {
+        player % self.won_cards.len()
+    }
+    fn teams(&self) -> Vec<Vec<Player>> {
+        (0..self.won_cards.len())
+            .map(|x| {
+                self.players
+                    .iter()
+                    .enumerate()
+                    .filter(|y| self.team_of(y.0) == x)
+                    .map(|y| y.1.clone())
+                    .collect()
+            })
+            .collect()
+    }
+    fn deal(&mut self) {
+        let hand_size = self.hand_size;
+        for hand in self.in_hand.iter_mut() {
+            hand.extend(self.deck.split_off(self.deck.len() - hand_size));
+        }
+    }
+    /// Takes the cards from the table, checking whether it's a scopa
+    fn capture(&mut self, player: usize, card: Card, cards: Vec<Card>) -> Vec<GameStatus> {
+        let team = self.team_of(player);
+        self.table.retain(|x| !cards.contains(x));
+        let mut res = vec![GameStatus::NotifyRoom(text!(
+            "{} prende {}",
+            self.players[player].name,
+            Text::join(&cards, ", ")
+        ))];
+        self.won_cards[team].push(card);
+        self.won_cards[team].extend(cards);
+        self.last_capture = Some(player);
+        // L'ultima presa della mano non vale come scopa
+        let last_play = self.deck.is_empty() && self.in_hand.iter().all(|x| x.is_empty());
+        if self.table.is_empty() && !last_play {
+            self.scope[team] += 1;
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} ha fatto scopa!",
+                self.players[player].name
+            )));
+        }
+        res
+    }
+    /// Passes the turn to the next player, dealing new cards or ending the hand if needed
+    fn end_turn(&mut self, player: usize) -> Vec<GameStatus> {
+        if self.in_hand.iter().all(|x| x.is_empty()) {
+            if self.deck.is_empty() {
+                return self.end_hand();
+            }
+            self.deal();
+        }
+        let next_player = (player + 1) % self.players.len();
+        self.next_player = Some(next_player);
+        vec![
+            GameStatus::WaitingForChoice(
+                self.players[next_player].clone(),
+                self.in_hand[next_player].clone(),
+            ),
+            GameStatus::InProgress(self.players[next_player].clone()),
+        ]
+    }
+    fn end_hand(&mut self) -> Vec<GameStatus> {
+        let mut res = vec![];
+        if let Some(player) = self.last_capture {
+            if !self.table.is_empty() {
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} prende le carte rimaste sul tavolo",
+                    self.players[player].name
+                )));
+                let team = self.team_of(player);
+                self.won_cards[team].append(&mut self.table);
+            }
+        }
+        self.next_player = None;
+        let teams = self.teams();
+        res.push(GameStatus::NotifyRoom(Text::join(
+            awarded_points(&self.won_cards)
+                .iter()
+                .zip(self.scope.iter())
+                .zip(teams.iter())
+                .map(|x| {
+                    text!(
+                        "{}: {} scope {}",
+                        (x.1).iter().map(|y| y.name.clone()).join(", "),
+                        (x.0).1,
+                        Text::join((x.0).0.iter().map(|y| Text::new(y)), ", ")
+                    )
+                }),
+            "\n",
+        )));
+        res.push(GameStatus::GameEnded);
+        res
+    }
+}
+
+impl Game for Scopa {
+    fn init(&mut self) {
+        self.deck = self.shuffler.deck(CardDeckType::Briscola);
+    }
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        match (self.is_scopone(), self.table_size) {
+            (false, _) => "Scopa",
+            (true, 0) => "Scopone scientifico",
+            (true, _) => "Scopone",
+        }
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Briscola
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        if self.is_scopone() {
+            4..4
+        } else {
+            2..4
+        }
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let player = match self.next_player {
+            None => return vec![GameStatus::InvalidMove(GameError::GameNotStarted)],
+            Some(x) => x,
+        };
+        if &self.players[player] != by {
+            return vec![GameStatus::InvalidMove(GameError::NotYourTurn)];
+        }
+        if let Some(pending) = self.pending.as_mut() {
+            // Il giocatore sta scegliendo cosa prendere
+            if !pending.candidates().contains(&card) {
+                return vec![GameStatus::InvalidMove(GameError::CannotCapture)];
+            }
+            return match pending.choose(card) {
+                Some(cards) => {
+                    let played = self.pending.take().unwrap().card;
+                    let mut res = self.capture(player, played, cards);
+                    res.append(&mut self.end_turn(player));
+                    res
+                }
+                None => vec![GameStatus::WaitingForChoiceCustomMessage(
+                    by.clone(),
+                    pending.candidates(),
+                    Text::new("Scegli le altre carte da prendere:"),
+                )],
+            };
+        }
+        let card_index = match self.in_hand[player].iter().position(|x| x == &card) {
+            Some(x) => x,
+            None => return vec![GameStatus::InvalidMove(GameError::CardNotInHand)],
+        };
+        self.in_hand[player].remove(card_index);
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        let mut options = capture_options(&self.table, &card);
+        match options.len() {
+            0 => {
+                self.table.push(card);
+            }
+            1 => {
+                res.append(&mut self.capture(player, card, options.pop().unwrap()));
+            }
+            _ => {
+                let pending = PendingCapture {
+                    card,
+                    options,
+                    chosen: vec![],
+                };
+                res.push(GameStatus::WaitingForChoiceCustomMessage(
+                    by.clone(),
+                    pending.candidates(),
+                    Text::new("Puoi fare più prese, scegli le carte da prendere:"),
+                ));
+                self.pending = Some(pending);
+                return res;
+            }
+        }
+        res.append(&mut self.end_turn(player));
+        res
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match (self.next_player, self.pending.as_ref()) {
+            (Some(x), Some(pending)) if &self.players[x] == player => pending.candidates(),
+            (Some(x), None) if &self.players[x] == player => self.in_hand[x].clone(),
+            _ => vec![],
+        }
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.started {
+            Err(GameError::AlreadyStarted)
+        } else if self.players.len() < self.get_num_players().end as usize {
+            self.players.push(player.clone());
+            self.in_hand.push(vec![]);
+            Ok(GameStatus::WaitingForPlayers(
+                self.players.len() >= self.get_num_players().start as usize
+                    && self.players.len().is_multiple_of(2),
+                player,
+            ))
+        } else {
+            Err(GameError::GameFull)
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.next_player.map(|x| self.players[x].clone())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.started {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.is_scopone() && self.players.len() != 4 {
+            return GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(vec![4]));
+        } else if self.players.len() != 2 && self.players.len() != 4 {
+            return GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(vec![2, 4]));
+        }
+        self.started = true;
+        let teams = self.players.len().min(2);
+        self.won_cards = vec![vec![]; teams];
+        self.scope = vec![0; teams];
+        self.table = self.deck.split_off(self.deck.len() - self.table_size);
+        self.deal();
+        self.next_player = Some(0);
+        GameStatus::WaitingForChoice(self.players[0].clone(), self.in_hand[0].clone())
+    }
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.teams()
+            .into_iter()
+            .zip(awarded_points(&self.won_cards).iter())
+            .zip(self.scope.iter())
+            .map(|x| {
+                (
+                    (x.0).0,
+                    fraction::GenericFraction::new((x.0).1.len() as u8 + x.1, 1u8),
+                )
+            })
+            .collect()
+    }
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nPunteggi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+            self.get_name(),
+            Text::join(
+                self.get_scores()
+                    .iter()
+                    .zip(self.scope.iter())
+                    .map(|x| text!(
+                        "{}: {} punti ({} scope)",
+                        (x.0).0.iter().map(|y| y.name.clone()).join(", "),
+                        (x.0).1,
+                        x.1
+                    )),
+                "\n"
+            ),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            Text::join(self.table.iter().map(|x| text!("- {}", x)), "\n")
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::with_deal(self.hand_size, self.table_size))
+    }
+    fn view_for(&self, player: &Player) -> PlayerView {
+        let me = self.players.iter().position(|x| x == player);
+        PlayerView {
+            hand: me.map(|x| self.in_hand[x].clone()).unwrap_or_default(),
+            table: self.table.iter().map(|x| (None, *x)).collect(),
+            captured: self
+                .teams()
+                .into_iter()
+                .zip(self.won_cards.iter().map(|x| x.len()))
+                .collect(),
+            hand_sizes: view::hand_sizes(&self.players, &self.in_hand),
+            stock: self.deck.len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Scopa(self.clone()).into()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        vec![GameStatus::NotifyRoom(text!(
+            "{} ha detto: {}",
+            from.name,
+            message
+        ))]
+    }
+}
+
+impl CardValues for Scopa {
+    /// The value of the card in the primiera
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        fraction::GenericFraction::new(primiera_value(card), 1u8)
+    }
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        card_value(card)
+    }
+}
+
+impl Default for Scopa {
+    fn default() -> Self {
+        Self {
+            players: vec![],
+            in_hand: vec![],
+            deck: vec![],
+            table: vec![],
+            won_cards: vec![vec![], vec![]],
+            scope: vec![0, 0],
+            last_capture: None,
+            next_player: None,
+            pending: None,
+            started: false,
+            hand_size: HAND_SIZE,
+            table_size: TABLE_SIZE,
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    fn started_game() -> Scopa {
+        let mut game = Scopa::default();
+        game.init();
+        testing::started(game, 2)
+    }
+
+    #[test]
+    fn same_value_takes_precedence() {
+        let table = vec![
+            Card::new(Three, Coppe),
+            Card::new(Four, Spade),
+            Card::new(Seven, Bastoni),
+        ];
+        assert_eq!(
+            capture_options(&table, &Card::new(Seven, Denari)),
+            vec![vec![Card::new(Seven, Bastoni)]]
+        );
+        assert_eq!(capture_options(&table, &Card::new(King, Denari)).len(), 1);
+        assert!(capture_options(&table, &Card::new(Two, Denari)).is_empty());
+    }
+
+    #[test]
+    fn points() {
+        let won_cards = vec![
+            vec![
+                Card::new(Seven, Denari),
+                Card::new(Seven, Coppe),
+                Card::new(Seven, Spade),
+                Card::new(Six, Bastoni),
+            ],
+            vec![
+                Card::new(Ace, Denari),
+                Card::new(Two, Denari),
+                Card::new(King, Coppe),
+                Card::new(Ace, Spade),
+                Card::new(Five, Bastoni),
+            ],
+        ];
+        assert_eq!(primiera(&won_cards[0]), Some(81));
+        assert_eq!(primiera(&won_cards[1]), Some(57));
+        assert_eq!(
+            awarded_points(&won_cards),
+            vec![vec!["settebello", "primiera"], vec!["carte", "denari"]]
+        );
+    }
+
+    #[test]
+    fn choose_capture() {
+        let mut game = started_game();
+        let first = game.players[0].clone();
+        game.table = vec![
+            Card::new(Ace, Coppe),
+            Card::new(Five, Spade),
+            Card::new(Two, Bastoni),
+            Card::new(Four, Bastoni),
+        ];
+        game.in_hand[0][0] = Card::new(Six, Denari);
+        // 6 = 1 + 5 = 2 + 4
+        let res = game.handle_move(&first, Card::new(Six, Denari));
+        assert!(matches!(
+            res.last(),
+            Some(GameStatus::WaitingForChoiceCustomMessage(_, _, _))
+        ));
+        assert_eq!(game.get_next_player(), Some(first.clone()));
+        game.handle_move(&first, Card::new(Four, Bastoni));
+        assert!(game.pending.is_none());
+        assert_eq!(
+            game.table,
+            vec![Card::new(Ace, Coppe), Card::new(Five, Spade)]
+        );
+        assert_eq!(game.won_cards[0].len(), 3);
+        assert_eq!(game.next_player, Some(1));
+    }
+
+    #[test]
+    fn scopa() {
+        let mut game = started_game();
+        let first = game.players[0].clone();
+        game.table = vec![Card::new(Three, Coppe), Card::new(Four, Spade)];
+        game.in_hand[0][0] = Card::new(Seven, Denari);
+        game.handle_move(&first, Card::new(Seven, Denari));
+        assert_eq!(game.scope[0], 1);
+        assert!(game.table.is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+//! Scopone: [`Scopa`] for two fixed teams of two where all the cards are dealt at once,
+//! played over several hands until a team reaches the target.
+use super::scopa::Scopa;
+use crate::multi_hand::{Match, Target};
+
+/// A match of Scopone to `target` points, usually 11 or 21.
+/// Each player gets nine cards and four go on the table,
+/// or ten cards each and none on the table in the scientifico.
+pub fn new(scientifico: bool, target: u16) -> Match {
+    Match::new(
+        Box::new(Scopa::scopone(scientifico)),
+        Target::Points(target),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::*;
+    use crate::testing;
+
+    #[test]
+    fn all_the_cards_are_dealt() {
+        for (scientifico, in_hand, on_table) in [(false, 9, 4), (true, 10, 0)] {
+            let mut game = new(scientifico, 21);
+            let players = testing::add_players(&mut game, 4);
+            game.start();
+            let view = game.view_for(&players[0]);
+            assert_eq!(view.hand.len(), in_hand);
+            assert_eq!(view.table.len(), on_table);
+        }
+        assert_eq!(
+            new(true, 21).get_title().to_string(),
+            "Scopone scientifico ai 21"
+        );
+    }
+}
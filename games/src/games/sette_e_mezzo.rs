@@ -0,0 +1,569 @@
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::{self, PlayerView};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Chips each player has at the beginning
+const STARTING_CHIPS: i32 = 100;
+/// The highest bet allowed in a round
+const MAX_BET: i32 = 10;
+/// Seven and a half, counted in half points
+const SETTE_E_MEZZO: u8 = 15;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Phase {
+    /// Players who aren't the banker place their bets
+    Betting,
+    /// Players ask for cards, the banker plays last
+    Playing,
+    Ended,
+}
+
+fn is_matta(card: &Card) -> bool {
+    card == &Card::new(Rank::King, Suit::Denari)
+}
+
+/// The value of a hand in half points: figures are worth half a point
+/// and the king of denari (the matta) is worth whatever suits the player best.
+fn hand_value(hand: &[Card]) -> u8 {
+    let value: u8 = hand
+        .iter()
+        .filter(|x| !is_matta(x))
+        .map(|x| SetteEMezzo::get_card_sorting_rank(&x.rank))
+        .sum();
+    if hand.iter().any(is_matta) {
+        // La matta vale mezzo punto o da uno a sette, il meglio senza sballare
+        (1..=7)
+            .map(|x| x * 2)
+            .rev()
+            .chain(std::iter::once(1))
+            .map(|x| value + x)
+            .find(|x| *x <= SETTE_E_MEZZO)
+            .unwrap_or(value + 1)
+    } else {
+        value
+    }
+}
+
+/// Seven and a half made with the first two cards, which is paid double
+fn is_sette_e_mezzo_reale(hand: &[Card]) -> bool {
+    hand.len() == 2 && hand_value(hand) == SETTE_E_MEZZO
+}
+
+fn value_description(value: u8) -> fraction::GenericFraction<u8> {
+    fraction::GenericFraction::new(value, 2u8)
+}
+
+/// Sette e mezzo: every round a different player is the banker and the others bet against them.
+/// Players keep a balance of chips, which unlike the scores can go below zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetteEMezzo {
+    players: Vec<Player>,
+    /// The first card of each hand is covered
+    in_hand: Vec<Vec<Card>>,
+    deck: CardDeck,
+    /// Chips of each player
+    balances: Vec<i32>,
+    /// What each player bet in this round, if they're playing
+    bets: Vec<Option<i32>>,
+    banker: usize,
+    rounds_played: usize,
+    phase: Phase,
+    next_player: Option<usize>,
+    shuffler: utils::Shuffler,
+}
+
+impl SetteEMezzo {
+    /// The chips of each player
+    pub fn get_balances(&self) -> Vec<(Player, i32)> {
+        self.players
+            .iter()
+            .cloned()
+            .zip(self.balances.iter().cloned())
+            .collect()
+    }
+    /// Players other than the banker, in playing order
+    fn punters(&self) -> Vec<usize> {
+        (1..self.players.len())
+            .map(|x| (self.banker + x) % self.players.len())
+            .collect()
+    }
+    /// Shuffles a new deck and asks for the bets, returns who bets first if anybody still has chips
+    fn deal_round(&mut self) -> Option<usize> {
+        self.deck = self.shuffler.deck(CardDeckType::Briscola);
+        self.bets = vec![None; self.players.len()];
+        for hand in self.in_hand.iter_mut() {
+            hand.clear();
+        }
+        self.phase = Phase::Betting;
+        let first = self.punters().into_iter().find(|x| self.balances[*x] > 0);
+        self.next_player = first;
+        first
+    }
+    fn new_round(&mut self) -> Vec<GameStatus> {
+        match self.deal_round() {
+            Some(player) => vec![
+                GameStatus::NotifyRoom(text!("Il banco è {}", self.players[self.banker].name)),
+                self.bet_request(player),
+            ],
+            None => self.end_game(),
+        }
+    }
+    fn bet_request(&self, player: usize) -> GameStatus {
+        GameStatus::NotifyUser(
+            self.players[player].clone(),
+            text!(
+                "Hai {} gettoni, quanto vuoi puntare (al massimo {})?",
+                self.balances[player],
+                MAX_BET.min(self.balances[player])
+            ),
+        )
+    }
+    fn play_request(&self, player: usize) -> GameStatus {
+        GameStatus::NotifyUser(
+            self.players[player].clone(),
+            text!(
+                "Le tue carte: {} (valore {})\nScrivi «carta» per chiedere un'altra carta o «sto» per fermarti",
+                Text::join(&self.in_hand[player], ", "),
+                value_description(hand_value(&self.in_hand[player]))
+            ),
+        )
+    }
+    fn handle_bet(&mut self, player: usize, bet: i32) -> Vec<GameStatus> {
+        if bet < 1 || bet > MAX_BET.min(self.balances[player]) {
+            return vec![GameStatus::InvalidMove(GameError::InvalidBid)];
+        }
+        self.bets[player] = Some(bet);
+        let mut res = vec![GameStatus::NotifyRoom(text!(
+            "{} punta {} gettoni",
+            self.players[player].name,
+            bet
+        ))];
+        let next_player = self
+            .punters()
+            .into_iter()
+            .skip_while(|x| *x != player)
+            .skip(1)
+            .find(|x| self.balances[*x] > 0);
+        match next_player {
+            Some(x) => {
+                self.next_player = Some(x);
+                res.push(self.bet_request(x));
+            }
+            None => {
+                // Tutti hanno puntato, do la prima carta coperta
+                self.phase = Phase::Playing;
+                for player in 0..self.players.len() {
+                    if player == self.banker || self.bets[player].is_some() {
+                        match self.deck.pop() {
+                            Some(card) => self.in_hand[player].push(card),
+                            None => {
+                                res.append(&mut self.deck_over());
+                                return res;
+                            }
+                        }
+                    }
+                }
+                let first = self
+                    .punters()
+                    .into_iter()
+                    .find(|x| self.bets[*x].is_some())
+                    .unwrap();
+                self.next_player = Some(first);
+                res.push(GameStatus::InProgress(self.players[first].clone()));
+                res.push(self.play_request(first));
+            }
+        }
+        res
+    }
+    /// Gives a card to the player, returning whether they went over seven and a half,
+    /// or `None` if the deck is over
+    fn give_card(&mut self, player: usize) -> Option<(bool, Vec<GameStatus>)> {
+        let card = self.deck.pop()?;
+        let res = vec![GameStatus::NotifyRoom(text!(
+            "{} prende {}",
+            self.players[player].name,
+            card
+        ))];
+        self.in_hand[player].push(card);
+        Some((hand_value(&self.in_hand[player]) > SETTE_E_MEZZO, res))
+    }
+    /// Settles the round with the cards already dealt
+    fn deck_over(&mut self) -> Vec<GameStatus> {
+        let mut res = vec![GameStatus::NotifyRoom(Text::new(
+            "Il mazzo è finito, la mano si chiude con le carte già date",
+        ))];
+        res.append(&mut self.end_round());
+        res
+    }
+    fn handle_play(&mut self, player: usize, ask_card: bool) -> Vec<GameStatus> {
+        let mut res = vec![];
+        if ask_card {
+            let (busted, mut messages) = match self.give_card(player) {
+                Some(x) => x,
+                None => return self.deck_over(),
+            };
+            res.append(&mut messages);
+            if busted {
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} ha sballato!",
+                    self.players[player].name
+                )));
+                if player != self.banker {
+                    // Chi sballa paga subito il banco
+                    let bet = self.bets[player].take().unwrap();
+                    self.balances[player] -= bet;
+                    self.balances[self.banker] += bet;
+                }
+            } else if hand_value(&self.in_hand[player]) < SETTE_E_MEZZO {
+                res.push(self.play_request(player));
+                return res;
+            }
+        }
+        if player == self.banker {
+            res.append(&mut self.end_round());
+            return res;
+        }
+        let next_player = self
+            .punters()
+            .into_iter()
+            .skip_while(|x| *x != player)
+            .skip(1)
+            .find(|x| self.bets[*x].is_some());
+        match next_player {
+            Some(x) => {
+                self.next_player = Some(x);
+                res.push(GameStatus::InProgress(self.players[x].clone()));
+                res.push(self.play_request(x));
+            }
+            None if self.bets.iter().all(|x| x.is_none()) => {
+                // Hanno sballato tutti, il banco non deve giocare
+                res.append(&mut self.end_round());
+            }
+            None => {
+                self.next_player = Some(self.banker);
+                res.push(GameStatus::InProgress(self.players[self.banker].clone()));
+                res.push(self.play_request(self.banker));
+            }
+        }
+        res
+    }
+    /// Settles the bets and passes the bank to the next player
+    fn end_round(&mut self) -> Vec<GameStatus> {
+        let banker_value = hand_value(&self.in_hand[self.banker]);
+        let banker_busted = banker_value > SETTE_E_MEZZO;
+        let mut res = vec![];
+        for player in self.punters() {
+            if let Some(bet) = self.bets[player] {
+                let value = hand_value(&self.in_hand[player]);
+                let won = banker_busted || value > banker_value;
+                let amount = if won && is_sette_e_mezzo_reale(&self.in_hand[player]) {
+                    bet * 2
+                } else {
+                    bet
+                };
+                let amount = if won { amount } else { -amount };
+                self.balances[player] += amount;
+                self.balances[self.banker] -= amount;
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} ({}) {} {} gettoni",
+                    self.players[player].name,
+                    Text::join(&self.in_hand[player], ", "),
+                    if won {
+                        Text::new("vince")
+                    } else {
+                        Text::new("perde")
+                    },
+                    amount.abs()
+                )));
+            }
+        }
+        self.rounds_played += 1;
+        if self.rounds_played >= self.players.len() {
+            res.append(&mut self.end_game());
+        } else {
+            self.banker = (self.banker + 1) % self.players.len();
+            res.append(&mut self.new_round());
+        }
+        res
+    }
+    fn end_game(&mut self) -> Vec<GameStatus> {
+        self.phase = Phase::Ended;
+        self.next_player = None;
+        vec![
+            GameStatus::NotifyRoom(self.get_status()),
+            GameStatus::GameEnded,
+        ]
+    }
+}
+
+impl Game for SetteEMezzo {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        "Sette e mezzo"
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Briscola
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        2..6
+    }
+    fn handle_move(&mut self, by: &Player, _card: Card) -> Vec<GameStatus> {
+        vec![
+            GameStatus::InvalidMove(GameError::CardNotExpected),
+            GameStatus::NotifyUser(
+                by.clone(),
+                Text::new("Scrivi quanto vuoi puntare, «carta» o «sto»"),
+            ),
+        ]
+    }
+    /// Players never play a card, they ask for one or stand
+    fn legal_moves(&self, _player: &Player) -> Vec<Card> {
+        vec![]
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.next_player.is_some() || self.phase != Phase::Betting {
+            Err(GameError::AlreadyStarted)
+        } else if self.players.len() >= self.get_num_players().end as usize {
+            Err(GameError::GameFull)
+        } else {
+            self.players.push(player.clone());
+            self.in_hand.push(vec![]);
+            self.balances.push(STARTING_CHIPS);
+            Ok(GameStatus::WaitingForPlayers(
+                self.players.len() >= self.get_num_players().start as usize,
+                player,
+            ))
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.next_player.map(|x| self.players[x].clone())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.next_player.is_some() || self.phase != Phase::Betting {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        if self.players.len() < self.get_num_players().start as usize {
+            return GameStatus::InvalidMove(GameError::NotEnoughPlayers);
+        }
+        self.banker = 0;
+        // Il banco della prima mano si legge nello stato della partita
+        match self.deal_round() {
+            Some(player) => self.bet_request(player),
+            None => GameStatus::InvalidMove(GameError::NotEnoughPlayers),
+        }
+    }
+    /// The chips of each player, without the ones they owe
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.get_balances()
+            .into_iter()
+            .map(|x| {
+                (
+                    vec![x.0],
+                    fraction::GenericFraction::new(x.1.max(0).min(u8::MAX as i32) as u8, 1u8),
+                )
+            })
+            .collect()
+    }
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nGettoni:\n{}\nIl banco è: {}\nTocca a: {}\nCarte scoperte:\n{}",
+            self.get_name(),
+            self.get_balances()
+                .iter()
+                .map(|x| format!("{}: {}", x.0.name, x.1))
+                .join("\n"),
+            self.players
+                .get(self.banker)
+                .map(|x| x.name.clone())
+                .unwrap_or_else(|| "".to_owned()),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            Text::join(
+                self.players
+                    .iter()
+                    .zip(self.in_hand.iter())
+                    .filter(|x| x.1.len() > 1)
+                    .map(|x| text!("- {}: {}", x.0.name, Text::join(&x.1[1..], ", "))),
+                "\n"
+            )
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::default())
+    }
+    /// The first card of each hand is covered
+    fn view_for(&self, player: &Player) -> PlayerView {
+        let me = self.players.iter().position(|x| x == player);
+        PlayerView {
+            hand: me.map(|x| self.in_hand[x].clone()).unwrap_or_default(),
+            face_up: self
+                .players
+                .iter()
+                .zip(self.in_hand.iter())
+                .filter(|x| x.0 != player)
+                .map(|x| (x.0.clone(), x.1.iter().skip(1).cloned().collect()))
+                .collect(),
+            hand_sizes: view::hand_sizes(&self.players, &self.in_hand),
+            stock: self.deck.len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::SetteEMezzo(self.clone()).into()
+    }
+    /// Players bet by writing a number, then ask for a card with «carta» or stop with «sto»
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        let text = message.trim().to_lowercase();
+        if let Some(player) = self.next_player.filter(|x| self.players[*x] == from) {
+            match (&self.phase, text.as_str()) {
+                (Phase::Betting, _) if text.parse::<i32>().is_ok() => {
+                    return self.handle_bet(player, text.parse().unwrap())
+                }
+                (Phase::Playing, "carta") => return self.handle_play(player, true),
+                (Phase::Playing, "sto") => return self.handle_play(player, false),
+                _ => {}
+            }
+        }
+        vec![GameStatus::NotifyRoom(text!(
+            "{} ha detto: {}",
+            from.name,
+            message
+        ))]
+    }
+}
+
+impl CardValues for SetteEMezzo {
+    /// Figures are worth half a point
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        value_description(Self::get_card_sorting_rank(card))
+    }
+    /// The value of the card in half points
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        match card {
+            Rank::Jack | Rank::Queen | Rank::King => 1,
+            x => x.number() * 2,
+        }
+    }
+}
+
+impl Default for SetteEMezzo {
+    fn default() -> Self {
+        Self {
+            players: vec![],
+            in_hand: vec![],
+            deck: vec![],
+            balances: vec![],
+            bets: vec![],
+            banker: 0,
+            rounds_played: 0,
+            phase: Phase::Betting,
+            next_player: None,
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            hand_value(&[Card::new(Seven, Coppe), Card::new(Jack, Spade)]),
+            15
+        );
+        assert!(is_sette_e_mezzo_reale(&[
+            Card::new(Seven, Coppe),
+            Card::new(Jack, Spade)
+        ]));
+        assert_eq!(
+            hand_value(&[Card::new(Three, Coppe), Card::new(King, Denari)]),
+            14
+        );
+        assert_eq!(
+            hand_value(&[
+                Card::new(Three, Coppe),
+                Card::new(Jack, Spade),
+                Card::new(King, Denari)
+            ]),
+            15
+        );
+        assert_eq!(
+            hand_value(&[Card::new(Seven, Coppe), Card::new(King, Denari)]),
+            15
+        );
+        assert_eq!(
+            hand_value(&[
+                Card::new(Seven, Coppe),
+                Card::new(Queen, Spade),
+                Card::new(King, Denari)
+            ]),
+            16
+        );
+    }
+
+    #[test]
+    fn round() {
+        let mut game = SetteEMezzo::default();
+        testing::add_players(&mut game, 2);
+        let res = game.start();
+        let (banker, punter) = (game.players[0].clone(), game.players[1].clone());
+        assert!(matches!(res, GameStatus::NotifyUser(ref p, _) if p == &punter));
+        assert!(game.get_status().to_string().contains("Il banco è: 0"));
+        assert_eq!(game.get_next_player(), Some(punter.clone()));
+        game.handle_message("5".to_owned(), punter.clone());
+        assert_eq!(game.phase, Phase::Playing);
+        game.in_hand[1] = vec![Card::new(Six, Coppe)];
+        game.in_hand[0] = vec![Card::new(Five, Coppe)];
+        game.handle_message("sto".to_owned(), punter);
+        assert_eq!(game.get_next_player(), Some(banker.clone()));
+        game.handle_message("sto".to_owned(), banker);
+        assert_eq!(game.balances, vec![95, 105]);
+        // Ora il banco passa al secondo giocatore
+        assert_eq!(game.banker, 1);
+        assert_eq!(game.phase, Phase::Betting);
+    }
+
+    #[test]
+    fn empty_deck_ends_the_round() {
+        let mut game = SetteEMezzo::default();
+        testing::add_players(&mut game, 2);
+        game.start();
+        let punter = game.players[1].clone();
+        game.handle_message("5".to_owned(), punter.clone());
+        game.in_hand[1] = vec![Card::new(Two, Coppe)];
+        game.in_hand[0] = vec![Card::new(Five, Coppe)];
+        game.deck.clear();
+        game.handle_message("carta".to_owned(), punter);
+        assert_eq!(game.balances, vec![105, 95]);
+        assert_eq!(game.banker, 1);
+        // Finita la partita non si può ricominciare
+        game.handle_message("5".to_owned(), game.players[0].clone());
+        game.deck.clear();
+        game.handle_message("carta".to_owned(), game.players[0].clone());
+        assert_eq!(game.phase, Phase::Ended);
+        assert!(matches!(
+            game.start(),
+            GameStatus::InvalidMove(GameError::AlreadyStarted)
+        ));
+    }
+}
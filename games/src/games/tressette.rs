@@ -0,0 +1,381 @@
+use super::beccaccino::Beccaccino;
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::tricks::{Played, Teams, TrickRules, Tricks};
+use crate::utils;
+use crate::view::PlayerView;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Cards in hand for each player
+const HAND_SIZE: usize = 10;
+
+/// Finds the accusi in a hand: the napoletana (ace, two and three of the same suit)
+/// is worth three points, three aces, twos or threes are worth three points and four of them four points.
+fn accusi(hand: &[Card]) -> Vec<(Text, u8)> {
+    let mut res = vec![];
+    for suit in Suit::ITALIAN.iter() {
+        if [Rank::Ace, Rank::Two, Rank::Three]
+            .iter()
+            .all(|x| hand.contains(&Card::new(*x, *suit)))
+        {
+            res.push((text!("napoletana di {}", suit), 3));
+        }
+    }
+    for (rank, three, four) in [
+        (Rank::Ace, "tre assi", "quattro assi"),
+        (Rank::Two, "tre due", "quattro due"),
+        (Rank::Three, "tre tre", "quattro tre"),
+    ]
+    .iter()
+    {
+        match hand.iter().filter(|x| &x.rank == rank).count() {
+            4 => res.push((Text::new(four), 4)),
+            3 => res.push((Text::new(three), 3)),
+            _ => {}
+        }
+    }
+    res
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tressette {
+    /// In two or in two teams, without trumps. When playing in two the rest of the deck is the stock.
+    tricks: Tricks<Tressette>,
+    /// Points declared with accusi by each team
+    accusi: Vec<u8>,
+    /// Whether each player can still declare accusi, that is they haven't played a card yet
+    can_declare: Vec<bool>,
+    shuffler: utils::Shuffler,
+}
+
+/// Players must follow the suit led if they can, and there's no briscola
+impl TrickRules for Tressette {
+    const FOLLOW_SUIT: bool = true;
+}
+
+impl Game for Tressette {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        "Tressette"
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        CardDeckType::Briscola
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        2..4
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let played = match self.tricks.play(by, card) {
+            Err(GameError::MustFollowSuit) => {
+                return vec![
+                    GameStatus::InvalidMove(GameError::MustFollowSuit),
+                    GameStatus::WaitingForChoice(by.clone(), self.tricks.hand_of(by)),
+                ]
+            }
+            Err(e) => return vec![GameStatus::InvalidMove(e)],
+            Ok(x) => x,
+        };
+        let player = self.tricks.position(by).unwrap();
+        self.can_declare[player] = false;
+        let mut res = vec![GameStatus::CardPlayed(by.clone(), card)];
+        if let Played::Trick { winner, drawn } = played {
+            res.push(GameStatus::RoundWon(self.tricks.players()[winner].clone()));
+            // In due, dopo ogni presa si pesca a partire da chi l'ha vinta
+            for (player, card) in drawn {
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} pesca {}",
+                    self.tricks.players()[player].name,
+                    card
+                )));
+            }
+        }
+        let next_player = match self.get_next_player() {
+            Some(x) => x,
+            None => {
+                res.push(GameStatus::GameEnded);
+                return res;
+            }
+        };
+        res.push(GameStatus::WaitingForChoice(
+            next_player.clone(),
+            self.tricks.hand_of(&next_player),
+        ));
+        res.push(GameStatus::InProgress(next_player));
+        res
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        self.tricks.legal_moves(player)
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.tricks.is_dealt() {
+            Err(GameError::AlreadyStarted)
+        } else if self.tricks.players().len() >= 4 {
+            Err(GameError::GameFull)
+        } else {
+            self.tricks.add_player(player.clone());
+            self.can_declare.push(true);
+            let players = self.tricks.players().len();
+            Ok(GameStatus::WaitingForPlayers(
+                players == 2 || players == 4,
+                player,
+            ))
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.tricks
+            .next_player()
+            .map(|x| self.tricks.players()[x].clone())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.tricks.is_dealt() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        let players = self.tricks.players();
+        if players.len() != 2 && players.len() != 4 {
+            return GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(vec![2, 4]));
+        }
+        let player = players[0].clone();
+        self.tricks
+            .deal(self.shuffler.deck(CardDeckType::Briscola), HAND_SIZE);
+        GameStatus::WaitingForChoice(player, self.tricks.hands()[0].clone())
+    }
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.tricks
+            .teams()
+            .into_iter()
+            .zip(self.accusi.iter())
+            .enumerate()
+            .map(|x| {
+                let score = self
+                    .tricks
+                    .won_cards(x.0)
+                    .iter()
+                    .map(|y| Self::get_card_rank(&y.rank))
+                    .fold(utils::zero(), |acc, y| acc + y)
+                    + if self.tricks.last_trick() == Some(x.0) {
+                        utils::one()
+                    } else {
+                        utils::zero()
+                    }
+                    + fraction::GenericFraction::new(*(x.1).1, 1u8);
+                ((x.1).0, score)
+            })
+            .collect()
+    }
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nPunteggi:\n{}\nTocca a: {}\nCarte nel mazzo: {}\nCarte sul tavolo:\n{}",
+            self.get_name(),
+            Text::join(
+                self.get_scores().into_iter().map(|x| text!(
+                    "{}: {} punti",
+                    x.0.iter().map(|y| y.name.clone()).join(", "),
+                    x.1
+                )),
+                "\n"
+            ),
+            self.get_next_player()
+                .map(|x| x.name)
+                .unwrap_or_else(|| "".to_owned()),
+            self.tricks.stock().len(),
+            Text::join(
+                self.tricks
+                    .table()
+                    .iter()
+                    .map(|x| text!("- {} ({})", x.1, &x.0.name)),
+                "\n"
+            )
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.tricks.players().to_vec()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::default())
+    }
+    fn determinize(&self, player: &Player, seed: u64) -> Option<Box<dyn Game>> {
+        crate::ai::determinize_boxed(self, player, seed)
+    }
+    fn view_for(&self, player: &Player) -> PlayerView {
+        PlayerView {
+            hand: self.tricks.hand_of(player),
+            table: self.tricks.table_view(),
+            captured: self.tricks.captured(),
+            hand_sizes: self.tricks.hand_sizes(),
+            stock: self.tricks.stock().len(),
+            ..PlayerView::new(self, player)
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Tressette(self.clone()).into()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        match message
+            .to_lowercase()
+            .chars()
+            .filter(|x| x.is_alphabetic())
+            .collect::<String>()
+            .as_str()
+        {
+            "busso" => vec![GameStatus::NotifyRoom(text!(
+                "{} ha detto: Busso",
+                from.name
+            ))],
+            "striscio" => vec![GameStatus::NotifyRoom(text!(
+                "{} ha detto: Striscio",
+                from.name
+            ))],
+            "volo" => vec![GameStatus::NotifyRoom(text!(
+                "{} ha detto: Volo",
+                from.name
+            ))],
+            "accuso" => {
+                let player = match self.tricks.position(&from) {
+                    Some(x) if self.tricks.is_dealt() => x,
+                    _ => {
+                        return vec![GameStatus::NotifyUser(
+                            from,
+                            Text::new("La partita non è ancora iniziata"),
+                        )]
+                    }
+                };
+                if !self.can_declare[player] {
+                    return vec![GameStatus::NotifyUser(
+                        from,
+                        Text::new("Puoi accusare solo prima di giocare la tua prima carta"),
+                    )];
+                }
+                let found = accusi(&self.tricks.hands()[player]);
+                if found.is_empty() {
+                    return vec![GameStatus::NotifyUser(
+                        from,
+                        Text::new("Non hai niente da accusare"),
+                    )];
+                }
+                let points: u8 = found.iter().map(|x| x.1).sum();
+                let team = self.tricks.team_of(player);
+                self.accusi[team] += points;
+                self.can_declare[player] = false;
+                vec![GameStatus::NotifyRoom(text!(
+                    "{} accusa: {} ({} punti)",
+                    from.name,
+                    Text::join(found.iter().map(|x| &x.0), ", "),
+                    points
+                ))]
+            }
+            _ => vec![GameStatus::NotifyUser(
+                from,
+                Text::new("Puoi dire solo busso, striscio, volo o accuso."),
+            )],
+        }
+    }
+}
+
+impl CardValues for Tressette {
+    /// Aces are worth one point, twos, threes and figures a third
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8> {
+        Beccaccino::get_card_rank(card)
+    }
+    /// Threes are the highest cards, followed by twos and aces
+    fn get_card_sorting_rank(card: &Rank) -> u8 {
+        Beccaccino::get_card_sorting_rank(card)
+    }
+}
+
+impl Default for Tressette {
+    fn default() -> Self {
+        Self {
+            tricks: Tricks::new(Teams::Alternate),
+            accusi: vec![0, 0],
+            can_declare: vec![],
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+}
+
+impl crate::ai::HiddenCards for Tressette {
+    fn hidden_cards(&mut self, player: &Player) -> Vec<&mut [Card]> {
+        self.tricks.hidden_cards(player, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use Rank::*;
+    use Suit::*;
+
+    fn started_game(players: i64) -> Tressette {
+        let mut game = Tressette::default();
+        game.init();
+        testing::started(game, players)
+    }
+
+    #[test]
+    fn declare_accusi() {
+        let hand = vec![
+            Card::new(Ace, Coppe),
+            Card::new(Two, Coppe),
+            Card::new(Three, Coppe),
+            Card::new(Ace, Spade),
+            Card::new(Ace, Denari),
+            Card::new(Seven, Denari),
+        ];
+        assert_eq!(
+            accusi(&hand),
+            vec![
+                (text!("napoletana di {}", Coppe), 3),
+                (Text::new("tre assi"), 3)
+            ]
+        );
+        let mut game = started_game(4);
+        *game.tricks.hand_mut(1) = hand;
+        let player = game.get_players()[1].clone();
+        game.handle_message("Accuso!".to_owned(), player.clone());
+        assert_eq!(game.accusi, vec![0, 6]);
+        // Non si può accusare due volte
+        game.handle_message("accuso".to_owned(), player);
+        assert_eq!(game.accusi, vec![0, 6]);
+    }
+
+    #[test]
+    fn must_follow_suit() {
+        let mut game = started_game(4);
+        let players = game.get_players();
+        game.tricks.hand_mut(0)[0] = Card::new(Four, Coppe);
+        *game.tricks.hand_mut(1) = vec![Card::new(Five, Coppe), Card::new(Three, Spade)];
+        game.handle_move(&players[0], Card::new(Four, Coppe));
+        assert_eq!(game.legal_moves(&players[1]), vec![Card::new(Five, Coppe)]);
+        assert!(game.legal_moves(&players[2]).is_empty());
+        let res = game.handle_move(&players[1], Card::new(Three, Spade));
+        assert!(matches!(res[0], GameStatus::InvalidMove(_)));
+        game.handle_move(&players[1], Card::new(Five, Coppe));
+        assert_eq!(game.get_next_player(), Some(players[2].clone()));
+    }
+
+    #[test]
+    fn highest_card_of_the_first_suit_wins() {
+        let mut game = started_game(2);
+        let players = game.get_players();
+        game.tricks.hand_mut(0)[0] = Card::new(Four, Coppe);
+        *game.tricks.hand_mut(1) = vec![Card::new(Three, Spade)];
+        game.handle_move(&players[0], Card::new(Four, Coppe));
+        game.handle_move(&players[1], Card::new(Three, Spade));
+        assert_eq!(game.get_next_player(), Some(players[0].clone()));
+        assert_eq!(game.tricks.won_cards(0).len(), 2);
+        // Entrambi i giocatori hanno pescato
+        assert_eq!(game.tricks.hands()[0].len(), HAND_SIZE);
+        assert_eq!(game.tricks.hands()[1].len(), 1);
+        assert_eq!(game.tricks.stock().len(), 40 - 2 * HAND_SIZE - 2);
+    }
+}
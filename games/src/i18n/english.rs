@@ -0,0 +1,392 @@
+//! The English catalogue: Italian messages and their translation
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    // Carte e parole usate negli argomenti
+    ("Asso", "Ace"),
+    ("J", "J"),
+    ("Q", "Q"),
+    ("K", "K"),
+    ("{} di {}", "{} of {}"),
+    (" e ", " and "),
+    (" o in ", " or "),
+    ("due", "two"),
+    ("tre", "three"),
+    ("quattro", "four"),
+    ("cinque", "five"),
+    ("sì", "yes"),
+    ("no", "no"),
+    // Errori
+    ("La partita non è ancora iniziata", "The game hasn't started yet"),
+    ("La partita è già cominciata", "The game has already started"),
+    ("La partita è finita", "The game is over"),
+    ("La partita è al completo", "The game is full"),
+    ("Non ci sono abbastanza giocatori", "There aren't enough players"),
+    ("Si gioca in {}", "This game is played by {} players"),
+    ("Non è ancora il tuo turno!", "It's not your turn yet!"),
+    ("Non tocca a te scegliere!", "It's not up to you to choose!"),
+    ("Non hai questa carta", "You don't have this card"),
+    (
+        "Devi giocare una carta dello stesso seme della prima!",
+        "You must play a card of the same suit as the first one!",
+    ),
+    (
+        "Questa mossa non è prevista in questo gioco",
+        "This move isn't part of this game",
+    ),
+    ("Ora non devi giocare una carta", "You don't have to play a card now"),
+    ("Non hai indicato carte valide", "You didn't pick any valid card"),
+    ("Non puoi puntare questa cifra", "You can't bid this amount"),
+    (
+        "Non puoi chiamare una carta che hai in mano",
+        "You can't call a card in your own hand",
+    ),
+    ("Non puoi prendere questa carta", "You can't take this card"),
+    ("Questa combinazione non esiste", "This meld doesn't exist"),
+    (
+        "Le carte non formano una scala o un tris",
+        "The cards don't make a run or a set",
+    ),
+    (
+        "Per aprire servono almeno {} punti",
+        "You need at least {} points to open",
+    ),
+    ("Prima devi aprire", "You have to open first"),
+    (
+        "Devi aprire con la carta raccolta, oppure scartarla per rimetterla negli scarti e pescare dal mazzo",
+        "You have to open with the card you picked up, or discard it to put it back and draw from the stock",
+    ),
+    (
+        "Queste carte non si possono attaccare alla combinazione",
+        "These cards can't be added to the meld",
+    ),
+    ("Non ci sono carte da pescare", "There are no cards to draw"),
+    ("Non ci sono scarti da raccogliere", "There are no discards to pick up"),
+    ("Hai già pescato", "You have already drawn"),
+    ("Devi prima pescare una carta", "You have to draw a card first"),
+    ("Devi tenere una carta da scartare", "You must keep a card to discard"),
+    (
+        "Prima devi fare almeno un burraco",
+        "You need at least one burraco first",
+    ),
+    (
+        "Non puoi chiudere scartando una matta",
+        "You can't go out by discarding a wild card",
+    ),
+    (
+        "Questa variante delle regole non esiste",
+        "This variant of the rules doesn't exist",
+    ),
+    (
+        "Il salvataggio è della versione {} del formato, questa versione legge solo la {}",
+        "The save uses version {} of the format, this version only reads version {}",
+    ),
+    // Messaggi comuni ai giochi
+    ("{} ha detto: {}", "{} said: {}"),
+    ("{} prende {}", "{} takes {}"),
+    ("{}: {} punti", "{}: {} points"),
+    (
+        "Fine della mano {}\n{}\nTotale:\n{}",
+        "End of hand {}\n{}\nTotal:\n{}",
+    ),
+    ("{} ha vinto la partita!", "{} won the game!"),
+    (
+        "Partita di {}\nPunteggi della partita:\n{}\n{}",
+        "Game of {}\nGame scores:\n{}\n{}",
+    ),
+    ("{} ai {}", "{} to {}"),
+    ("{} al meglio delle {}", "{}, best of {}"),
+    ("{} con eliminazione a {} punti", "{}, out at {} points"),
+    (
+        "Partita di {}\nPunteggi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+        "Game of {}\nScores:\n{}\nNext: {}\nCards on the table:\n{}",
+    ),
+    // Briscola e Beccaccino
+    (
+        "Scegli quale sarà il seme di briscola",
+        "Choose the trump suit",
+    ),
+    (
+        "Partita di {}\nPunteggi:\n{}\nBriscola è: {}\nTocca a: {}\nCarte sul tavolo:\n{}",
+        "Game of {}\nScores:\n{}\nTrump is: {}\nNext: {}\nCards on the table:\n{}",
+    ),
+    ("non ancora scelta", "not chosen yet"),
+    ("{} ha detto: Busso", "{} said: Knock"),
+    ("{} ha detto: Striscio", "{} said: Slide"),
+    ("{} ha detto: Volo", "{} said: Fly"),
+    (
+        "Puoi dire solo busso, striscio o volo.",
+        "You can only say busso, striscio or volo.",
+    ),
+    ("{} ha la marafona!", "{} has the marafona!"),
+    (
+        "Tre punti a chi sceglie la briscola con asso, due e tre",
+        "Three points to who chooses the trump with its ace, two and three",
+    ),
+    ("Carte in mano in due", "Cards in hand when playing in two"),
+    ("Briscola scoperta", "Face-up Briscola"),
+    ("{}\nCarte in mano:\n{}", "{}\nCards in hand:\n{}"),
+    (
+        "Briscola scelta da chi ha il 4 di denara",
+        "Trump chosen by who has the 4 of coins",
+    ),
+    ("Terzi di punto", "Thirds of a point"),
+    ("contati esatti", "counted exactly"),
+    ("arrotondati per difetto", "rounded down"),
+    ("arrotondati per eccesso", "rounded up"),
+    // Briscola chiamata
+    (
+        "Tocca a te: scrivi quanti punti pensi di fare (da 61 a 120) o «passo»",
+        "Your turn: write how many points you think you'll make (61 to 120) or «passo»",
+    ),
+    ("{} offre {}", "{} bids {}"),
+    ("{} passa", "{} passes"),
+    (
+        "Hanno passato tutti, si ridistribuiscono le carte",
+        "Everybody passed, the cards are dealt again",
+    ),
+    ("{} ha vinto l'asta con {} punti", "{} won the auction with {} points"),
+    (
+        "Chiama una carta: chi ce l'ha sarà il tuo compagno e il suo seme sarà la briscola",
+        "Call a card: whoever has it will be your partner and its suit will be trump",
+    ),
+    ("{} chiama {}", "{} calls {}"),
+    ("{} hanno fatto {} punti su {}: {}", "{} made {} points out of {}: {}"),
+    ("hanno vinto!", "they won!"),
+    ("hanno perso!", "they lost!"),
+    (
+        "Durante l'asta devi scrivere la tua offerta o «passo»",
+        "During the auction you have to write your bid or «passo»",
+    ),
+    ("Asta in corso", "Auction in progress"),
+    (
+        "Asta in corso, offerta più alta: {} ({})",
+        "Auction in progress, highest bid: {} ({})",
+    ),
+    (
+        "{} deve fare {} punti\nCarta chiamata: {}",
+        "{} has to make {} points\nCalled card: {}",
+    ),
+    (
+        "Partita di {}\n{}\nPunteggi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+        "Game of {}\n{}\nScores:\n{}\nNext: {}\nCards on the table:\n{}",
+    ),
+    // Burraco e Scala 40
+    (
+        "Le carte sono finite, la mano si chiude senza vincitori",
+        "The cards have run out, the hand ends with no winner",
+    ),
+    ("La tua mano:\n{}", "Your hand:\n{}"),
+    (
+        "{}\nTocca a te! Scrivi «pesca» per pescare dal mazzo o «raccogli» per prendere tutti gli scarti",
+        "{}\nYour turn! Write «pesca» to draw from the stock or «raccogli» to pick up all the discards",
+    ),
+    (
+        "{}\nScrivi «cala 1 2 3 / 4 5 6» per calare, «attacca 1 4» per attaccare la quarta carta alla prima combinazione della tua squadra oppure scegli una carta da scartare.",
+        "{}\nWrite «cala 1 2 3 / 4 5 6» to meld, «attacca 1 4» to add the fourth card to the first meld of your team or choose a card to discard.",
+    ),
+    ("Scegli la carta da scartare:", "Choose the card to discard:"),
+    (
+        "{} raccoglie gli scarti ({} carte)",
+        "{} picks up the discards ({} cards)",
+    ),
+    ("{} prende il pozzetto al volo", "{} takes the pozzetto on the fly"),
+    ("{} ha chiuso!", "{} went out!"),
+    (
+        "{} rimette {} negli scarti e pesca dal mazzo",
+        "{} puts {} back on the discards and draws from the stock",
+    ),
+    (
+        "{} prende il jolly e mette al suo posto {}",
+        "{} takes the joker and puts {} in its place",
+    ),
+    (
+        "Il mazzo è finito, la mano si chiude senza bonus",
+        "The stock is over, the hand ends without bonus",
+    ),
+    ("{} prende il pozzetto", "{} takes the pozzetto"),
+    (
+        "Partita di {}\nTocca a: {}\nScarto: {} ({} carte)\nCarte nel mazzo: {}\n{}",
+        "Game of {}\nNext: {}\nDiscard: {} ({} cards)\nCards in the stock: {}\n{}",
+    ),
+    ("nessuno", "none"),
+    ("Combinazioni di {}{}:\n{}", "Melds of {}{}:\n{}"),
+    (" (pozzetto preso)", " (pozzetto taken)"),
+    (" (burraco pulito)", " (clean burraco)"),
+    (" (burraco sporco)", " (dirty burraco)"),
+    (
+        "{}\nTocca a te! Scrivi «pesca» per pescare dal mazzo o «raccogli» per prendere {}",
+        "{}\nYour turn! Write «pesca» to draw from the stock or «raccogli» to take {}",
+    ),
+    ("lo scarto", "the discard"),
+    (
+        "{}\nScrivi «cala 1 2 3 / 4 5 6» per calare, «attacca 1 4» per attaccare la quarta carta alla prima combinazione oppure scegli una carta da scartare.",
+        "{}\nWrite «cala 1 2 3 / 4 5 6» to meld, «attacca 1 4» to add the fourth card to the first meld or choose a card to discard.",
+    ),
+    (
+        "{} ha superato i {} punti ed è eliminato",
+        "{} went over {} points and is out",
+    ),
+    (
+        "Partita di {}\nPenalità:\n{}\nTocca a: {}\nScarto: {}\nCarte nel mazzo: {}\nCombinazioni sul tavolo:\n{}",
+        "Game of {}\nPenalties:\n{}\nNext: {}\nDiscard: {}\nCards in the stock: {}\nMelds on the table:\n{}",
+    ),
+    (" (eliminato)", " (out)"),
+    // Rubamazzo
+    (
+        "{} ruba il mazzo di {} ({} carte)",
+        "{} steals the pile of {} ({} cards)",
+    ),
+    (
+        "Partita di {}\nMazzi:\n{}\nTocca a: {}\nCarte sul tavolo:\n{}",
+        "Game of {}\nPiles:\n{}\nNext: {}\nCards on the table:\n{}",
+    ),
+    ("{}: {} carte{}", "{}: {} cards{}"),
+    (", in cima {}", ", {} on top"),
+    // Scopa e Scopone
+    ("{} ha fatto scopa!", "{} made a scopa!"),
+    (
+        "{} prende le carte rimaste sul tavolo",
+        "{} takes the cards left on the table",
+    ),
+    ("{}: {} scope {}", "{}: {} scope {}"),
+    ("carte", "cards"),
+    ("denari", "coins"),
+    ("settebello", "settebello"),
+    ("primiera", "primiera"),
+    (
+        "Scegli le altre carte da prendere:",
+        "Choose the other cards to take:",
+    ),
+    (
+        "Puoi fare più prese, scegli le carte da prendere:",
+        "You can take different cards, choose which ones:",
+    ),
+    ("{}: {} punti ({} scope)", "{}: {} points ({} scope)"),
+    // Sette e mezzo
+    ("Il banco è {}", "{} is the banker"),
+    (
+        "Hai {} gettoni, quanto vuoi puntare (al massimo {})?",
+        "You have {} chips, how much do you bet (at most {})?",
+    ),
+    (
+        "Le tue carte: {} (valore {})\nScrivi «carta» per chiedere un'altra carta o «sto» per fermarti",
+        "Your cards: {} (value {})\nWrite «carta» to ask for another card or «sto» to stand",
+    ),
+    ("{} punta {} gettoni", "{} bets {} chips"),
+    ("{} ha sballato!", "{} went bust!"),
+    (
+        "Il mazzo è finito, la mano si chiude con le carte già date",
+        "The deck is over, the round ends with the cards already dealt",
+    ),
+    ("{} ({}) {} {} gettoni", "{} ({}) {} {} chips"),
+    ("vince", "wins"),
+    ("perde", "loses"),
+    (
+        "Scrivi quanto vuoi puntare, «carta» o «sto»",
+        "Write how much you bet, «carta» or «sto»",
+    ),
+    (
+        "Partita di {}\nGettoni:\n{}\nIl banco è: {}\nTocca a: {}\nCarte scoperte:\n{}",
+        "Game of {}\nChips:\n{}\nThe banker is: {}\nNext: {}\nFace up cards:\n{}",
+    ),
+    // Tressette
+    ("napoletana di {}", "napoletana of {}"),
+    ("tre assi", "three aces"),
+    ("quattro assi", "four aces"),
+    ("tre due", "three twos"),
+    ("quattro due", "four twos"),
+    ("tre tre", "three threes"),
+    ("quattro tre", "four threes"),
+    ("{} pesca {}", "{} draws {}"),
+    (
+        "Partita di {}\nPunteggi:\n{}\nTocca a: {}\nCarte nel mazzo: {}\nCarte sul tavolo:\n{}",
+        "Game of {}\nScores:\n{}\nNext: {}\nCards in the stock: {}\nCards on the table:\n{}",
+    ),
+    (
+        "Puoi accusare solo prima di giocare la tua prima carta",
+        "You can only declare before playing your first card",
+    ),
+    ("Non hai niente da accusare", "You have nothing to declare"),
+    ("{} accusa: {} ({} punti)", "{} declares: {} ({} points)"),
+    (
+        "Puoi dire solo busso, striscio, volo o accuso.",
+        "You can only say busso, striscio, volo or accuso.",
+    ),
+    // Bot
+    ("{} ha cambiato le regole: {}", "{} changed the rules: {}"),
+    ("Fine della mano {}", "End of hand {}"),
+    (
+        "Solo chi ha creato la partita può cambiare le regole",
+        "Only who created the game can change the rules",
+    ),
+    (" o ", " or "),
+    ("Ciao! A che gioco vuoi giocare?", "Hi! Which game do you want to play?"),
+    ("{} ({} giocatori)", "{} ({} players)"),
+    ("Provo ad aggiungerti alla partita...", "Trying to add you to the game..."),
+    (
+        "Non puoi unirti a più partite contemporaneamente",
+        "You can't join more than one game at a time",
+    ),
+    ("Gioco non trovato!", "Game not found!"),
+    (
+        "Per invitare altre persone condividi questo link: https://t.me/{}?start={}",
+        "To invite other people share this link: https://t.me/{}?start={}",
+    ),
+    (
+        "Il bot è stato riavviato, puoi continuare la tua partita",
+        "The bot has been restarted, you can go on with your game",
+    ),
+    ("Lingua impostata: {}", "Language set: {}"),
+    ("Scrivi /lingua seguito da {}", "Write /lingua followed by {}"),
+    (
+        "Questa istanza è in esecuzione sulla versione {}",
+        "This instance is running on version {}",
+    ),
+    (
+        "Scrivi /verifica seguito dal seed, dall'impronta del mazzo e dai mescolamenti della partita",
+        "Write /verifica followed by the seed, the fingerprint of the deck and the shuffles of the game",
+    ),
+    (
+        "I mescolamenti della partita non sono validi",
+        "The shuffles of the game aren't valid",
+    ),
+    (
+        "✅ Il seed corrisponde all'impronta. Ecco i mazzi della partita, nell'ordine in cui sono stati mescolati:",
+        "✅ The seed matches the fingerprint. Here are the decks of the game, in the order they were shuffled:",
+    ),
+    ("Mazzo {} della mano {}:\n{}", "Deck {} of hand {}:\n{}"),
+    ("Mazzo {}:\n{}", "Deck {}:\n{}"),
+    (
+        "❌ Il seed non corrisponde all'impronta del mazzo",
+        "❌ The seed doesn't match the fingerprint of the deck",
+    ),
+    (
+        "Impronta del mazzo: {}\nA fine partita verrà rivelato il seed con cui è stato mescolato, per verificare che non sia truccato",
+        "Fingerprint of the deck: {}\nThe seed it was shuffled with will be revealed at the end of the game, to check that it wasn't rigged",
+    ),
+    (
+        "I mazzi sono stati mescolati con il seed {}, puoi verificarli con:\n/verifica {} {} {}",
+        "The decks were shuffled with the seed {}, you can check them with:\n/verifica {} {} {}",
+    ),
+    (
+        "Questo gioco sarà terminato per inattività a breve!",
+        "This game will soon be ended for inactivity!",
+    ),
+    ("Avvia partita", "Start the game"),
+    ("La partita è finita!", "The game is over!"),
+    ("{} ha vinto questo round", "{} won this round"),
+    ("Tocca a {}", "It's {}'s turn"),
+    ("{} si è unito alla partita", "{} joined the game"),
+    ("Scegli una carta:", "Choose a card:"),
+    ("Questa mossa non è valida! {}", "This move isn't valid! {}"),
+    ("{} ha giocato {}", "{} played {}"),
+    ("{} gioca in coppia con {}", "{} plays with {}"),
+    ("{} ha scelto la briscola: {}", "{} chose the trump: {}"),
+    ("Aggiungi un giocatore del computer ({})", "Add a computer player ({})"),
+    ("facile", "easy"),
+    ("medio", "medium"),
+    ("difficile", "hard"),
+    (
+        "Non puoi aggiungere giocatori del computer a questa partita",
+        "You can't add computer players to this game",
+    ),
+];
@@ -0,0 +1,306 @@
+//! Translations of the messages shown to the players.
+//! Messages are written in Italian and kept as [`Text`], together with their arguments,
+//! so that every player can read them in their own [`Language`].
+//! The Italian template is the key used to look up the translation in the catalogue
+//! of each language: a message without a translation is shown in Italian.
+use crate::primitives::*;
+use serde::{Deserialize, Serialize};
+
+mod english;
+
+/// The languages the messages can be shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    Italian,
+    English,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Italian, Language::English];
+    /// The language for an IETF tag, like the `language_code` of Telegram users.
+    /// Italian speakers and users who don't tell get Italian, everybody else English.
+    pub fn from_code(code: Option<&str>) -> Self {
+        match code.map(|x| x.to_lowercase()) {
+            Some(x) if !x.starts_with("it") => Language::English,
+            _ => Language::Italian,
+        }
+    }
+    /// The two letters code of the language
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Italian => "it",
+            Language::English => "en",
+        }
+    }
+    /// The name of the language, in the language itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Italian => "Italiano",
+            Language::English => "English",
+        }
+    }
+}
+
+/// The translation of an Italian message, or the message itself if there's none
+pub fn translate(message: &str, language: Language) -> &str {
+    let catalogue = match language {
+        Language::Italian => return message,
+        Language::English => english::MESSAGES,
+    };
+    catalogue
+        .iter()
+        .find(|x| x.0 == message)
+        .map(|x| x.1)
+        .unwrap_or(message)
+}
+
+/// The messages written with `text!` or `Text::new` in the Rust source code that have no
+/// English translation, so that the crates using this one can check their own messages.
+/// The tests, after the first `#[cfg(test)]`, can use messages without translation.
+pub fn untranslated(source: &str) -> Vec<String> {
+    let source = source.split("#[cfg(test)]").next().unwrap();
+    let mut res = vec![];
+    for start in source
+        .match_indices("text!(")
+        .chain(source.match_indices("Text::new("))
+    {
+        let rest = source[start.0 + start.1.len()..].trim_start();
+        if !rest.starts_with('"') {
+            continue;
+        }
+        let mut message = String::new();
+        let mut chars = rest[1..].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => message.push('\n'),
+                    // Stringa spezzata su più righe
+                    Some('\n') => {
+                        chars = chars.as_str().trim_start().chars();
+                    }
+                    Some(x) => message.push(x),
+                    None => {}
+                },
+                x => message.push(x),
+            }
+        }
+        // Segnaposto e punteggiatura non vanno tradotti
+        if message.chars().any(char::is_alphabetic)
+            && !english::MESSAGES.iter().any(|x| x.0 == message)
+        {
+            res.push(message);
+        }
+    }
+    res
+}
+
+/// A message with placeholders (`{}`) filled in by its arguments when it's translated
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    template: &'static str,
+    args: Vec<Arg>,
+}
+
+/// What fills a placeholder of a [`Text`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    /// Shown as it is, like the name of a player or a number
+    Plain(String),
+    Card(Card),
+    Text(Text),
+    /// Several arguments with a separator between them, which is translated too
+    List(Vec<Arg>, &'static str),
+}
+
+impl Text {
+    pub fn new(template: &'static str) -> Self {
+        Self {
+            template,
+            args: vec![],
+        }
+    }
+    /// Adds the argument for the next placeholder
+    pub fn arg(mut self, arg: impl ToArg) -> Self {
+        self.args.push(arg.to_arg());
+        self
+    }
+    /// The parts one after the other, with `separator` between them
+    pub fn join<T: ToArg>(parts: impl IntoIterator<Item = T>, separator: &'static str) -> Self {
+        Self::new("{}").arg(Arg::List(
+            parts.into_iter().map(|x| x.to_arg()).collect(),
+            separator,
+        ))
+    }
+    pub fn translate(&self, language: Language) -> String {
+        let mut pieces = translate(self.template, language).split("{}");
+        let mut args = self.args.iter();
+        let mut res = pieces.next().unwrap_or("").to_owned();
+        for piece in pieces {
+            if let Some(arg) = args.next() {
+                res.push_str(&arg.translate(language));
+            }
+            res.push_str(piece);
+        }
+        res
+    }
+}
+
+/// The Italian message
+impl std::fmt::Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.translate(Language::Italian))
+    }
+}
+
+impl Arg {
+    pub fn translate(&self, language: Language) -> String {
+        match self {
+            Arg::Plain(x) => x.clone(),
+            Arg::Card(x) => crate::utils::get_card_name(x, language),
+            Arg::Text(x) => x.translate(language),
+            Arg::List(x, separator) => x
+                .iter()
+                .map(|y| y.translate(language))
+                .collect::<Vec<_>>()
+                .join(translate(separator, language)),
+        }
+    }
+}
+
+/// Values that can fill a placeholder of a [`Text`]
+pub trait ToArg {
+    fn to_arg(&self) -> Arg;
+}
+
+impl<T: ToArg + ?Sized> ToArg for &T {
+    fn to_arg(&self) -> Arg {
+        (*self).to_arg()
+    }
+}
+
+impl ToArg for Arg {
+    fn to_arg(&self) -> Arg {
+        self.clone()
+    }
+}
+
+impl ToArg for Text {
+    fn to_arg(&self) -> Arg {
+        Arg::Text(self.clone())
+    }
+}
+
+impl ToArg for Card {
+    fn to_arg(&self) -> Arg {
+        Arg::Card(*self)
+    }
+}
+
+impl ToArg for Suit {
+    fn to_arg(&self) -> Arg {
+        Arg::Plain(String::from(self))
+    }
+}
+
+impl ToArg for str {
+    fn to_arg(&self) -> Arg {
+        Arg::Plain(self.to_owned())
+    }
+}
+
+macro_rules! plain_arg {
+    ($($t:ty),*) => {
+        $(impl ToArg for $t {
+            fn to_arg(&self) -> Arg {
+                Arg::Plain(self.to_string())
+            }
+        })*
+    };
+}
+
+plain_arg!(
+    String,
+    u8,
+    u16,
+    u32,
+    usize,
+    i32,
+    i64,
+    u64,
+    fraction::GenericFraction<u8>
+);
+
+/// Builds a [`Text`] like `format!` builds a string:
+/// `text!("{} ha giocato {}", player.name, card)`
+#[macro_export]
+macro_rules! text {
+    ($template:expr $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::Text::new($template)$(.arg(&$arg))*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_translated_too() {
+        let text = crate::text!("{} prende {}", "Anna", Card::new(Rank::Ace, Suit::Coppe));
+        assert_eq!(text.to_string(), "Anna prende Asso di 🏆");
+        assert_eq!(text.translate(Language::English), "Anna takes Ace of 🏆");
+        let list = Text::join(vec!["Anna", "Bruno"], " e ");
+        assert_eq!(list.translate(Language::English), "Anna and Bruno");
+        // I messaggi senza traduzione restano in italiano
+        assert_eq!(
+            Text::new("Messaggio nuovo").translate(Language::English),
+            "Messaggio nuovo"
+        );
+        assert_eq!(Language::from_code(Some("en-GB")), Language::English);
+        assert_eq!(Language::from_code(Some("it")), Language::Italian);
+        assert_eq!(Language::from_code(None), Language::Italian);
+    }
+
+    #[test]
+    fn translations_keep_the_placeholders() {
+        for (italian, english) in english::MESSAGES {
+            assert_eq!(
+                italian.matches("{}").count(),
+                english.matches("{}").count(),
+                "{}",
+                italian
+            );
+        }
+    }
+
+    /// Every message written with `text!` or `Text::new` in the crate has an English translation
+    #[test]
+    fn every_message_is_translated() {
+        fn check(dir: &std::path::Path) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    // Il catalogo contiene i messaggi stessi
+                    if !path.ends_with("i18n") {
+                        check(&path);
+                    }
+                } else if path.extension() == Some("rs".as_ref()) {
+                    let source = std::fs::read_to_string(&path).unwrap();
+                    assert_eq!(
+                        untranslated(&source),
+                        Vec::<String>::new(),
+                        "Mancano traduzioni in {}",
+                        path.display()
+                    );
+                }
+            }
+        }
+        check(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"));
+        // Un messaggio nuovo non sfugge al controllo
+        assert_eq!(
+            untranslated("Text::new(\"Messaggio\\n nuovo\")"),
+            vec!["Messaggio\n nuovo"]
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Plays every game at random until it ends, checking after every move what must always hold:
+//! - the cards are neither lost nor duplicated, and they're the ones of the deck
+//! - only the player whose turn it is can move, and wrong moves don't change the game
+//! - the scores at the end sum up to what the deck is worth, in the games where it's fixed
+//! - nothing panics
+//!
+//! New games only need to be added to [`all_games`].
+use crate::games::*;
+use crate::multi_hand::{Match, Target};
+use crate::primitives::*;
+use crate::snapshot::GameState;
+use crate::testing;
+use crate::utils::{self, Shuffler};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Random games that are still going after this many moves are broken
+const MAX_MOVES: usize = 5000;
+/// How many moves are played of the games that random players can't end
+const MOVES_WITHOUT_END: usize = 200;
+
+/// Every game of the crate, as they're offered by the bot
+pub(crate) fn all_games() -> Vec<Box<dyn Game>> {
+    vec![
+        Box::new(beccaccino::Beccaccino::default()),
+        // Le varianti non cambiano il totale dei punti
+        Box::new(beccaccino::Beccaccino::with_rules(beccaccino::Rules {
+            four_of_denari: false,
+            thirds: beccaccino::Thirds::Exact,
+            marafona: false,
+        })),
+        Box::new(briscola::Briscola::default()),
+        Box::new(briscola::Briscola::with_rules(briscola::Rules {
+            hand_size_in_two: 5,
+            face_up: true,
+        })),
+        Box::new(briscola_chiamata::BriscolaChiamata::default()),
+        Box::new(Match::new(
+            Box::new(burraco::Burraco::default()),
+            Target::Points(2005),
+        )),
+        Box::new(marafone::new()),
+        Box::new(rubamazzo::Rubamazzo::default()),
+        Box::new(Match::new(
+            Box::new(scala40::Scala40::default()),
+            Target::Elimination(101),
+        )),
+        Box::new(scopa::Scopa::default()),
+        Box::new(scopone::new(false, 11)),
+        Box::new(sette_e_mezzo::SetteEMezzo::default()),
+        Box::new(tressette::Tressette::default()),
+        Box::new(Match::new(
+            Box::new(briscola::Briscola::default()),
+            Target::BestOf(3),
+        )),
+    ]
+}
+
+/// A move of the random players
+#[derive(Clone, Debug)]
+enum Move {
+    Action(Action),
+    Message(&'static str),
+}
+
+/// What the harness has to know about a game, besides the [`Game`] trait
+struct Rules {
+    /// Moves that aren't a single card, tried when the player has no legal card to play
+    other_moves: Vec<Move>,
+    /// Whether random players are bound to end the game.
+    /// The others are only played for [`MOVES_WITHOUT_END`] moves.
+    ends: bool,
+    /// How many decks the game is played with
+    decks: usize,
+    /// How many cards of the decks may be left out
+    left_out: usize,
+    /// Fields of the state that name a card without holding it
+    references: &'static [&'static str],
+    /// The sum of the scores at the end, for the games where it's always the same
+    total: Option<Points>,
+}
+
+/// Scores summed up without overflowing
+type Points = fraction::GenericFraction<u32>;
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            other_moves: vec![],
+            ends: true,
+            decks: 1,
+            left_out: 0,
+            references: &[],
+            total: None,
+        }
+    }
+}
+
+fn rules_of(state: &GameState) -> Rules {
+    let points = |x: u32, y: u32| Some(Points::new(x, y));
+    let rummy = Rules {
+        other_moves: vec![Move::Action(Action::Draw), Move::Action(Action::PickUp)],
+        // I giocatori a caso non chiudono mai
+        ends: false,
+        decks: 2,
+        ..Rules::default()
+    };
+    match state {
+        // Dieci punti e due terzi nelle carte, più uno per l'ultima mano
+        GameState::Beccaccino(_) => Rules {
+            total: points(35, 3),
+            ..Rules::default()
+        },
+        // In tre si toglie un due
+        GameState::Briscola(_) => Rules {
+            left_out: 1,
+            total: points(120, 1),
+            ..Rules::default()
+        },
+        GameState::BriscolaChiamata(_) => Rules {
+            other_moves: vec![
+                Move::Message("passo"),
+                Move::Message("61"),
+                Move::Message("80"),
+                Move::Message("100"),
+            ],
+            references: &["called_card"],
+            total: points(120, 1),
+            ..Rules::default()
+        },
+        GameState::Burraco(_) => rummy,
+        // Lo scarto raccolto per aprire è già nella mano
+        GameState::Scala40(_) => Rules {
+            references: &["picked_up"],
+            ..rummy
+        },
+        // Le prese possibili sono carte del tavolo
+        GameState::Scopa(_) => Rules {
+            references: &["options", "chosen"],
+            ..Rules::default()
+        },
+        GameState::SetteEMezzo(_) => Rules {
+            other_moves: vec![
+                Move::Message("1"),
+                Move::Message("5"),
+                Move::Message("carta"),
+                Move::Message("sto"),
+            ],
+            ..Rules::default()
+        },
+        // Come nel Beccaccino, dato che i giocatori a caso non dichiarano accusi
+        GameState::Tressette(_) => Rules {
+            total: points(35, 3),
+            ..Rules::default()
+        },
+        // Le mani si giocano come la partita singola, ma i punteggi sono le mani vinte
+        GameState::Match(x) => Rules {
+            total: None,
+            ..rules_of(x.template())
+        },
+        _ => Rules::default(),
+    }
+}
+
+/// The cards in the full state of the game
+fn cards_of(game: &dyn Game, rules: &Rules) -> Vec<Card> {
+    fn collect(value: &Value, references: &[&str], cards: &mut Vec<Card>) {
+        match value {
+            Value::Object(x)
+                if x.len() == 2 && x.contains_key("rank") && x.contains_key("suit") =>
+            {
+                cards.push(serde_json::from_value(value.clone()).unwrap())
+            }
+            Value::Object(x) => x
+                .iter()
+                .filter(|y| !references.contains(&y.0.as_str()))
+                .for_each(|y| collect(y.1, references, cards)),
+            Value::Array(x) => x.iter().for_each(|y| collect(y, references, cards)),
+            _ => {}
+        }
+    }
+    let mut cards = vec![];
+    let state = serde_json::to_value(&game.snapshot().state).unwrap();
+    collect(&state, rules.references, &mut cards);
+    cards.sort();
+    cards
+}
+
+fn state_of(game: &dyn Game) -> String {
+    serde_json::to_string(&game.snapshot()).unwrap()
+}
+
+fn count(cards: &[Card]) -> HashMap<Card, usize> {
+    let mut res = HashMap::new();
+    for card in cards {
+        *res.entry(*card).or_insert(0) += 1;
+    }
+    res
+}
+
+/// Checks that the cards in the game are the ones of its decks, apart from the ones left out,
+/// returning how many they are
+fn check_cards(game: &dyn Game, rules: &Rules, context: &str) -> usize {
+    let cards = cards_of(game, rules);
+    let deck = count(&utils::new_deck(game.get_card_set()));
+    for (card, n) in count(&cards) {
+        assert!(
+            n <= deck.get(&card).copied().unwrap_or(0) * rules.decks,
+            "{}: {:?} is there {} times",
+            context,
+            card,
+            n
+        );
+    }
+    let full = deck.values().sum::<usize>() * rules.decks;
+    assert!(
+        cards.len() + rules.left_out >= full && cards.len() <= full,
+        "{}: {} cards instead of {}",
+        context,
+        cards.len(),
+        full
+    );
+    cards.len()
+}
+
+/// Checks that whoever isn't the next player can't move, and that wrong moves don't change the game
+fn check_turns(game: &mut dyn Game, state: &str, players: &[Player], next: &Player, context: &str) {
+    let legal = game.legal_moves(next);
+    for player in players.iter().filter(|x| *x != next) {
+        assert!(
+            game.legal_moves(player).is_empty(),
+            "{}: {} can move out of turn",
+            context,
+            player.name
+        );
+        if let Some(card) = game.view_for(player).hand.first() {
+            let res = game.handle_move(player, *card);
+            assert!(
+                matches!(res.first(), Some(GameStatus::InvalidMove(_))),
+                "{}: {} played out of turn",
+                context,
+                player.name
+            );
+        }
+    }
+    let deck = utils::new_deck(game.get_card_set());
+    if let Some(card) = deck.iter().find(|x| !legal.contains(x)) {
+        let res = game.handle_move(next, *card);
+        assert!(
+            matches!(res.first(), Some(GameStatus::InvalidMove(_))),
+            "{}: {} played {:?}, which isn't a legal move",
+            context,
+            next.name,
+            card
+        );
+    }
+    assert_eq!(
+        state_of(game),
+        state,
+        "{}: a wrong move changed the game",
+        context
+    );
+}
+
+/// Makes a random move for the next player, returning what the game answered
+fn random_move(
+    game: &mut dyn Game,
+    state: &str,
+    next: &Player,
+    rules: &Rules,
+    rng: &mut StdRng,
+    context: &str,
+) -> Vec<GameStatus> {
+    if let Some(card) = game.legal_moves(next).choose(rng) {
+        let res = game.handle_move(next, *card);
+        assert!(
+            !matches!(res.first(), Some(GameStatus::InvalidMove(_))),
+            "{}: the legal move {:?} was refused",
+            context,
+            card
+        );
+        return res;
+    }
+    let mut moves = rules.other_moves.clone();
+    moves.shuffle(rng);
+    for x in moves {
+        let res = match x {
+            Move::Action(action) => game.handle_action(next, action),
+            Move::Message(message) => game.handle_message(message.to_owned(), next.clone()),
+        };
+        if !matches!(res.first(), Some(GameStatus::InvalidMove(_))) && state_of(game) != state {
+            return res;
+        }
+    }
+    panic!("{}: {} can't make any move", context, next.name);
+}
+
+/// Plays a whole game at random, checking the invariants after every move
+fn play(mut game: Box<dyn Game>, players: usize, seed: u64) {
+    let rules = rules_of(&game.snapshot().state);
+    let name = format!(
+        "{} with {} players, seed {}",
+        game.get_name(),
+        players,
+        seed
+    );
+    let mut rng = StdRng::seed_from_u64(seed);
+    game.set_shuffler(Shuffler::new(seed));
+    game.init();
+    let players = testing::add_players(game.as_mut(), players as i64);
+    // Some games can't be played by every number of players in their range
+    if let GameStatus::InvalidMove(GameError::WrongNumberOfPlayers(_)) = game.start() {
+        return;
+    }
+    let cards = check_cards(game.as_ref(), &rules, &name);
+    let mut ended = false;
+    let moves = if rules.ends {
+        MAX_MOVES
+    } else {
+        MOVES_WITHOUT_END
+    };
+    for i in 0..moves {
+        let context = format!("{}, move {}", name, i);
+        let next = game
+            .get_next_player()
+            .unwrap_or_else(|| panic!("{}: nobody can move", context));
+        let state = state_of(game.as_ref());
+        check_turns(game.as_mut(), &state, &players, &next, &context);
+        let res = random_move(game.as_mut(), &state, &next, &rules, &mut rng, &context);
+        // Le carte lasciate fuori possono cambiare a ogni mano, ma non quante sono
+        assert_eq!(
+            check_cards(game.as_ref(), &rules, &context),
+            cards,
+            "{}: the number of cards changed",
+            context
+        );
+        if res.iter().any(|x| matches!(x, GameStatus::GameEnded)) {
+            ended = true;
+            break;
+        }
+    }
+    assert!(ended || !rules.ends, "{}: the game didn't end", name);
+    if ended {
+        assert!(
+            players.iter().all(|x| game.legal_moves(x).is_empty()),
+            "{}: somebody can move after the end",
+            name
+        );
+        let total: Points = game
+            .get_scores()
+            .into_iter()
+            .map(|x| match (x.1.numer(), x.1.denom()) {
+                (Some(n), Some(d)) => Points::new(*n as u32, *d as u32),
+                _ => Points::from(0),
+            })
+            .sum();
+        if let Some(x) = rules.total {
+            assert_eq!(total, x, "{}: the scores don't add up", name);
+        }
+    }
+}
+
+#[test]
+fn random_games_keep_the_invariants() {
+    for game in all_games() {
+        let players = game.get_num_players();
+        for n in players.start..=players.end {
+            for seed in 0..3 {
+                play(game.get_new_instance(), n as usize, seed);
+            }
+        }
+    }
+}
@@ -6,18 +6,27 @@
 //! - Create a new file under `games/src/games` named after the game
 //! - Create a public `struct` representing your game and implementing the `Default` and the `Game` traits
 //!     * Most of the methods are documented, but the main one is `handle_move` which updates the game status according to the card recieved
+//!     * Trick-taking games can keep their hands, tricks and stock in a [`tricks::Tricks`], so that only the scoring is left to write
+//!     * Rummy games can keep their hands, stock and discard pile in a [`rummy::Rummy`], so that only the melds are left to write
+//!     * Rules that change from group to group go in a `Rules` struct of the game, whose options are listed by `variants` and chosen with `set_variant`
 //! - Export your game in `games/src/games/mod.rs`
+//! - Add it to the games in `games/src/invariants.rs`, which plays it at random checking that it keeps the rules
 //! - Implement some tests
 //! - Open a pull request on the main repo
 
+pub mod ai;
+pub mod fairness;
 pub mod games;
+pub mod i18n;
+#[cfg(test)]
+mod invariants;
+pub mod log;
+pub mod multi_hand;
 pub mod primitives;
-pub mod utils;
-
+pub mod rummy;
+pub mod snapshot;
 #[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+mod testing;
+pub mod tricks;
+pub mod utils;
+pub mod view;
@@ -0,0 +1,376 @@
+//! An append-only log of what happens during a game.
+//! The log starts from a snapshot of the game and records what the players did
+//! together with its consequences, so that the game can be replayed step by step
+//! and two replays can be compared.
+//! Replays are identical as long as the game shuffles with its [`Shuffler`](crate::utils::Shuffler),
+//! which is part of the initial state.
+use crate::primitives::*;
+use crate::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+
+/// Something that happened in a game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// A player asked to join the game
+    PlayerJoined(Player),
+    /// The game was started and the cards were dealt
+    Dealt,
+    /// A player played a card
+    CardPlayed(Player, Card),
+    /// A player made a move that isn't playing a single card
+    ActionTaken(Player, Action),
+    /// A player wrote a message, which some games read as a command
+    MessageSent(Player, String),
+    /// The option of a variant of the rules was chosen before the start
+    VariantChosen(String, String),
+    /// The last input wasn't accepted by the game
+    Rejected(GameError),
+    /// The player has chosen the briscola, or called a card of this suit
+    BriscolaChosen(Player, Suit),
+    /// The player took the cards on the table
+    TrickWon(Player),
+    /// The first player has found out who's playing with them
+    PartnerRevealed(Player, Player),
+    /// The game (or the hand, for games made of single hands) ended with these scores
+    HandEnded(Vec<(Vec<Player>, String)>),
+    /// A hand of a match ended: its number and the points of each team in it
+    HandScored(usize, Vec<(Vec<Player>, i32)>),
+}
+
+impl GameEvent {
+    /// Whether the event was caused by a player, and has to be repeated to replay the game
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            GameEvent::PlayerJoined(_)
+                | GameEvent::Dealt
+                | GameEvent::CardPlayed(_, _)
+                | GameEvent::ActionTaken(_, _)
+                | GameEvent::MessageSent(_, _)
+                | GameEvent::VariantChosen(_, _)
+        )
+    }
+}
+
+/// The state of a game before anything happened, and the events that followed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameLog {
+    pub initial: Snapshot,
+    pub events: Vec<GameEvent>,
+}
+
+impl GameLog {
+    /// Restores the initial state, ready to repeat the inputs one at a time
+    pub fn replay(&self) -> Result<Replay, GameError> {
+        let mut recorder = Recorder::new(self.initial.clone().restore()?);
+        recorder.log.initial = self.initial.clone();
+        Ok(Replay {
+            recorder,
+            inputs: self
+                .events
+                .iter()
+                .filter(|x| x.is_input())
+                .cloned()
+                .collect(),
+            position: 0,
+        })
+    }
+    /// Replays the whole game, returning the log of the replay
+    pub fn replay_all(&self) -> Result<GameLog, GameError> {
+        let mut replay = self.replay()?;
+        while replay.step().is_some() {}
+        Ok(replay.recorder.log)
+    }
+    /// The position of the first event that differs between the two logs
+    pub fn first_difference(&self, other: &GameLog) -> Option<usize> {
+        let differs = self
+            .events
+            .iter()
+            .zip(other.events.iter())
+            .position(|(a, b)| a != b);
+        match differs {
+            Some(x) => Some(x),
+            None if self.events.len() != other.events.len() => {
+                Some(self.events.len().min(other.events.len()))
+            }
+            None => None,
+        }
+    }
+}
+
+/// A game being rebuilt from a [`GameLog`]
+pub struct Replay {
+    recorder: Recorder,
+    inputs: Vec<GameEvent>,
+    position: usize,
+}
+
+impl Replay {
+    /// Repeats the next input, returning the events it caused (the input included)
+    pub fn step(&mut self) -> Option<&[GameEvent]> {
+        let input = self.inputs.get(self.position)?.clone();
+        self.position += 1;
+        let start = self.recorder.log.events.len();
+        let game = &mut self.recorder;
+        match input {
+            GameEvent::PlayerJoined(p) => {
+                let _ = game.add_player(p);
+            }
+            GameEvent::Dealt => {
+                game.start();
+            }
+            GameEvent::CardPlayed(p, c) => {
+                game.handle_move(&p, c);
+            }
+            GameEvent::ActionTaken(p, a) => {
+                game.handle_action(&p, a);
+            }
+            GameEvent::MessageSent(p, m) => {
+                game.handle_message(m, p);
+            }
+            GameEvent::VariantChosen(v, o) => {
+                let _ = game.set_variant(&v, &o);
+            }
+            _ => unreachable!("Only inputs are replayed"),
+        }
+        Some(&self.recorder.log.events[start..])
+    }
+    /// The game as it is after the inputs replayed so far
+    pub fn game(&self) -> &dyn Game {
+        self.recorder.game.as_ref()
+    }
+    /// The events produced by the replay so far
+    pub fn get_log(&self) -> &GameLog {
+        &self.recorder.log
+    }
+}
+
+/// Wraps a game and writes what happens in it in a [`GameLog`]
+pub struct Recorder {
+    game: Box<dyn Game>,
+    log: GameLog,
+}
+
+impl Recorder {
+    /// Starts logging from the current state of the game
+    pub fn new(game: Box<dyn Game>) -> Self {
+        let log = GameLog {
+            initial: game.snapshot(),
+            events: vec![],
+        };
+        Self { game, log }
+    }
+    pub fn get_log(&self) -> &GameLog {
+        &self.log
+    }
+    /// Appends the input and the events in the statuses returned by the game
+    fn record(&mut self, input: GameEvent, statuses: &[GameStatus]) {
+        self.log.events.push(input);
+        for status in statuses {
+            let event = match status {
+                GameStatus::InvalidMove(x) => GameEvent::Rejected(x.clone()),
+                GameStatus::RoundWon(p) => GameEvent::TrickWon(p.clone()),
+                GameStatus::PartnerRevealed(p, partner) => {
+                    GameEvent::PartnerRevealed(p.clone(), partner.clone())
+                }
+                GameStatus::BriscolaChosen(p, suit) => GameEvent::BriscolaChosen(p.clone(), *suit),
+                GameStatus::HandScored(number, points) => {
+                    GameEvent::HandScored(*number, points.clone())
+                }
+                GameStatus::GameEnded => GameEvent::HandEnded(
+                    self.game
+                        .get_scores()
+                        .into_iter()
+                        .map(|x| (x.0, x.1.to_string()))
+                        .collect(),
+                ),
+                _ => continue,
+            };
+            self.log.events.push(event);
+        }
+    }
+}
+
+impl Game for Recorder {
+    fn init(&mut self) {
+        self.game.init();
+        if self.log.events.is_empty() {
+            self.log.initial = self.game.snapshot();
+        }
+    }
+    fn set_shuffler(&mut self, shuffler: crate::utils::Shuffler) {
+        self.game.set_shuffler(shuffler);
+    }
+    fn get_shuffler(&self) -> &crate::utils::Shuffler {
+        self.game.get_shuffler()
+    }
+    fn get_name(&self) -> &str {
+        self.game.get_name()
+    }
+    fn get_title(&self) -> crate::i18n::Text {
+        self.game.get_title()
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        self.game.get_card_set()
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        self.game.get_num_players()
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        let res = self.game.handle_move(by, card);
+        self.record(GameEvent::CardPlayed(by.clone(), card), &res);
+        res
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        self.game.legal_moves(player)
+    }
+    fn handle_action(&mut self, by: &Player, action: Action) -> Vec<GameStatus> {
+        let res = self.game.handle_action(by, action.clone());
+        self.record(GameEvent::ActionTaken(by.clone(), action), &res);
+        res
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        self.log
+            .events
+            .push(GameEvent::PlayerJoined(player.clone()));
+        match self.game.add_player(player) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                self.log.events.push(GameEvent::Rejected(e.clone()));
+                Err(e)
+            }
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        self.game.get_next_player()
+    }
+    fn start(&mut self) -> GameStatus {
+        let res = self.game.start();
+        self.record(GameEvent::Dealt, std::slice::from_ref(&res));
+        res
+    }
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.game.get_scores()
+    }
+    fn get_points(&self) -> Vec<(Vec<Player>, i32)> {
+        self.game.get_points()
+    }
+    fn get_status(&self) -> crate::i18n::Text {
+        self.game.get_status()
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.game.get_players()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::new(self.game.get_new_instance()))
+    }
+    fn determinize(&self, player: &Player, seed: u64) -> Option<Box<dyn Game>> {
+        self.game.determinize(player, seed)
+    }
+    fn view_for(&self, player: &Player) -> crate::view::PlayerView {
+        self.game.view_for(player)
+    }
+    /// The snapshot of the recorded game: the log isn't part of it
+    fn snapshot(&self) -> Snapshot {
+        self.game.snapshot()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        let res = self.game.handle_message(message.clone(), from.clone());
+        self.record(GameEvent::MessageSent(from, message), &res);
+        res
+    }
+    fn variants(&self) -> Vec<Variant> {
+        self.game.variants()
+    }
+    fn set_variant(&mut self, variant: &str, option: &str) -> Result<(), GameError> {
+        self.log.events.push(GameEvent::VariantChosen(
+            variant.to_owned(),
+            option.to_owned(),
+        ));
+        let res = self.game.set_variant(variant, option);
+        if let Err(e) = &res {
+            self.log.events.push(GameEvent::Rejected(e.clone()));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::briscola::Briscola;
+    use crate::games::scopa::Scopa;
+    use crate::games::tressette::Tressette;
+    use crate::multi_hand::{Match, Target};
+    use crate::testing;
+
+    /// Plays the first legal card until the game ends
+    fn play(game: &mut dyn Game, players: i64) {
+        game.init();
+        testing::add_players(game, players);
+        game.start();
+        while let Some(player) = game.get_next_player() {
+            let card = match game.legal_moves(&player).first() {
+                Some(x) => *x,
+                None => return,
+            };
+            game.handle_move(&player, card);
+        }
+    }
+
+    #[test]
+    fn replay_gives_the_same_events() {
+        let mut game = Recorder::new(Box::new(Briscola::default()));
+        play(&mut game, 2);
+        let log = game.get_log();
+        assert!(log
+            .events
+            .iter()
+            .any(|x| matches!(x, GameEvent::TrickWon(_))));
+        assert!(matches!(log.events.last(), Some(GameEvent::HandEnded(_))));
+        let json = serde_json::to_string(log).unwrap();
+        let log: GameLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(log.first_difference(&log.replay_all().unwrap()), None);
+    }
+
+    #[test]
+    fn replay_step_by_step() {
+        let mut game = Recorder::new(Box::new(Tressette::default()));
+        play(&mut game, 4);
+        let log = game.get_log().clone();
+        let mut replay = log.replay().unwrap();
+        let mut steps = 0;
+        while let Some(events) = replay.step() {
+            assert!(events[0].is_input());
+            steps += 1;
+        }
+        assert_eq!(steps, log.events.iter().filter(|x| x.is_input()).count());
+        assert_eq!(replay.game().get_scores(), game.get_scores());
+        // Un log modificato non corrisponde più alla partita giocata
+        let mut tampered = log.clone();
+        tampered.events.pop();
+        assert_eq!(log.first_difference(&tampered), Some(tampered.events.len()));
+    }
+
+    #[test]
+    fn hands_are_dealt_again_in_the_same_way() {
+        let mut game = Recorder::new(Box::new(Match::new(
+            Box::new(Scopa::default()),
+            Target::Points(11),
+        )));
+        play(&mut game, 2);
+        let log = game.get_log();
+        // Ogni mano finita è registrata con i suoi punti
+        let hands: Vec<usize> = log
+            .events
+            .iter()
+            .filter_map(|x| match x {
+                GameEvent::HandScored(number, _) => Some(*number),
+                _ => None,
+            })
+            .collect();
+        assert!(!hands.is_empty());
+        assert_eq!(hands, (1..=hands.len()).collect::<Vec<_>>());
+        assert_eq!(log.first_difference(&log.replay_all().unwrap()), None);
+    }
+}
@@ -0,0 +1,544 @@
+//! Matches made of several hands of a game, played until a team reaches a target.
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::snapshot::{GameState, Snapshot};
+use crate::text;
+use crate::utils;
+use crate::view::PlayerView;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// When a match ends
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    /// A team reaches these points, summed over the hands
+    Points(u16),
+    /// A team wins the majority of this many hands
+    BestOf(u8),
+    /// Players reaching these penalty points are out, the last one left wins
+    Elimination(u16),
+}
+
+/// Plays hands of a game one after the other, rotating the dealer,
+/// until the [`Target`] is reached.
+/// Any game that ends with `GameStatus::GameEnded` after a single hand can be used,
+/// the points of each hand are given by [`Game::get_points`].
+pub struct Match {
+    /// The game the hands are created from
+    template: Box<dyn Game>,
+    target: Target,
+    players: Vec<Player>,
+    /// The hand being played
+    hand: Option<Box<dyn Game>>,
+    /// Points of each team in every hand played
+    hand_scores: Vec<Vec<(Vec<Player>, i32)>>,
+    ended: bool,
+    /// Gives the decks of the hands
+    shuffler: utils::Shuffler,
+}
+
+/// The state of a [`Match`], with the state of the games it contains
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchState {
+    template: Box<GameState>,
+    target: Target,
+    players: Vec<Player>,
+    hand: Option<Box<GameState>>,
+    hand_scores: Vec<Vec<(Vec<Player>, i32)>>,
+    ended: bool,
+    shuffler: utils::Shuffler,
+}
+
+impl MatchState {
+    /// The state of the game the hands are created from
+    pub(crate) fn template(&self) -> &GameState {
+        &self.template
+    }
+}
+
+impl From<MatchState> for Match {
+    fn from(state: MatchState) -> Self {
+        Self {
+            players: state.players,
+            hand: state.hand.map(|x| x.into_game()),
+            hand_scores: state.hand_scores,
+            ended: state.ended,
+            shuffler: state.shuffler,
+            ..Self::new(state.template.into_game(), state.target)
+        }
+    }
+}
+
+impl Match {
+    pub fn new(template: Box<dyn Game>, target: Target) -> Self {
+        Self {
+            template,
+            target,
+            players: vec![],
+            hand: None,
+            hand_scores: vec![],
+            ended: false,
+            shuffler: utils::Shuffler::default(),
+        }
+    }
+    /// The points of each team in every hand played so far
+    pub fn get_hand_scores(&self) -> &[Vec<(Vec<Player>, i32)>] {
+        &self.hand_scores
+    }
+    /// The points of each team summed over the hands, or the hands they won for [`Target::BestOf`]
+    pub fn get_totals(&self) -> Vec<(Vec<Player>, i32)> {
+        let mut totals: Vec<(Vec<Player>, i32)> = vec![];
+        for hand in &self.hand_scores {
+            let winner = hand_winner(hand);
+            for (team, points) in hand {
+                let points = match self.target {
+                    Target::BestOf(_) => (winner == Some(team)) as i32,
+                    _ => *points,
+                };
+                match totals.iter_mut().find(|x| same_team(&x.0, team)) {
+                    Some(x) => x.1 += points,
+                    None => totals.push((team.clone(), points)),
+                }
+            }
+        }
+        totals
+    }
+    /// The penalty points a team is out of the match at, for [`Target::Elimination`]
+    fn limit(&self) -> Option<i32> {
+        match self.target {
+            Target::Elimination(x) => Some(x as i32),
+            _ => None,
+        }
+    }
+    /// The players who haven't been eliminated
+    fn active_players(&self) -> Vec<Player> {
+        let totals = self.get_totals();
+        self.players
+            .iter()
+            .filter(|x| {
+                !totals
+                    .iter()
+                    .any(|y| y.0.contains(x) && self.limit().is_some_and(|z| y.1 >= z))
+            })
+            .cloned()
+            .collect()
+    }
+    /// The team that won the match, if it's over
+    fn winner(&self) -> Option<Vec<Player>> {
+        let totals = self.get_totals();
+        let needed = match self.target {
+            Target::Points(x) => x as i32,
+            Target::BestOf(x) => x as i32 / 2 + 1,
+            // Vince l'ultimo rimasto, che è quello con meno penalità
+            Target::Elimination(_) => {
+                return totals
+                    .into_iter()
+                    .min_by_key(|x| x.1)
+                    .map(|x| x.0)
+                    .filter(|_| self.active_players().len() <= 1);
+            }
+        };
+        let max = totals.iter().map(|x| x.1).max()?;
+        match totals
+            .iter()
+            .filter(|x| x.1 == max)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [x] if max >= needed => Some(x.0.clone()),
+            _ => None,
+        }
+    }
+    /// Starts a new hand with the players still in the match,
+    /// the first player moves to the right at every hand
+    fn new_hand(&mut self) -> GameStatus {
+        let mut hand = self.template.get_new_instance();
+        hand.set_shuffler(self.shuffler.split());
+        hand.init();
+        let players = self.active_players();
+        for i in 0..players.len() {
+            let player = players[(i + self.hand_scores.len()) % players.len()].clone();
+            hand.add_player(player).unwrap();
+        }
+        let status = hand.start();
+        self.hand = Some(hand);
+        status
+    }
+    /// Records the points of the hand, then deals again or ends the match
+    fn end_hand(&mut self) -> Vec<GameStatus> {
+        let active = self.active_players();
+        let hand = self.hand.as_ref().unwrap();
+        self.shuffler.join(hand.get_shuffler());
+        self.hand_scores.push(hand.get_points());
+        let mut res = vec![
+            GameStatus::HandScored(
+                self.hand_scores.len(),
+                self.hand_scores.last().unwrap().clone(),
+            ),
+            GameStatus::NotifyRoom(text!(
+                "Fine della mano {}\n{}\nTotale:\n{}",
+                self.hand_scores.len(),
+                scores_description(self.hand_scores.last().unwrap(), None),
+                scores_description(&self.get_totals(), self.limit())
+            )),
+        ];
+        if let Some(limit) = self.limit() {
+            let still_active = self.active_players();
+            for player in active.iter().filter(|x| !still_active.contains(x)) {
+                res.push(GameStatus::NotifyRoom(text!(
+                    "{} ha superato i {} punti ed è eliminato",
+                    player.name,
+                    limit - 1
+                )));
+            }
+        }
+        if let Some(winner) = self.winner() {
+            self.ended = true;
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} ha vinto la partita!",
+                Text::join(winner.iter().map(|x| &x.name), " e ")
+            )));
+            res.push(GameStatus::GameEnded);
+        } else {
+            let status = self.new_hand();
+            res.push(status);
+            if let Some(player) = self.get_next_player() {
+                res.push(GameStatus::InProgress(player));
+            }
+        }
+        res
+    }
+}
+
+fn same_team(a: &[Player], b: &[Player]) -> bool {
+    a.len() == b.len() && a.iter().all(|x| b.contains(x))
+}
+
+/// The team with the most points in a hand, if there's no tie
+fn hand_winner(hand: &[(Vec<Player>, i32)]) -> Option<&Vec<Player>> {
+    let max = hand.iter().map(|x| x.1).max()?;
+    match hand
+        .iter()
+        .filter(|x| x.1 == max)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [x] => Some(&x.0),
+        _ => None,
+    }
+}
+
+/// The points of each team, marking who reached the `limit` and is out
+fn scores_description(scores: &[(Vec<Player>, i32)], limit: Option<i32>) -> Text {
+    Text::join(
+        scores.iter().map(|x| {
+            text!(
+                "{}: {}{}",
+                x.0.iter().map(|y| y.name.clone()).join(", "),
+                x.1,
+                if limit.is_some_and(|y| x.1 >= y) {
+                    Text::new(" (eliminato)")
+                } else {
+                    Text::new("")
+                }
+            )
+        }),
+        "\n",
+    )
+}
+
+impl Game for Match {
+    fn init(&mut self) {}
+    fn set_shuffler(&mut self, shuffler: utils::Shuffler) {
+        self.shuffler = shuffler;
+    }
+    fn get_shuffler(&self) -> &utils::Shuffler {
+        &self.shuffler
+    }
+    fn get_name(&self) -> &str {
+        self.template.get_name()
+    }
+    fn get_title(&self) -> Text {
+        match self.target {
+            Target::Points(x) => text!("{} ai {}", self.get_name(), x),
+            Target::BestOf(x) => text!("{} al meglio delle {}", self.get_name(), x),
+            Target::Elimination(x) => text!("{} con eliminazione a {} punti", self.get_name(), x),
+        }
+    }
+    fn get_card_set(&self) -> CardDeckType {
+        self.template.get_card_set()
+    }
+    fn get_num_players(&self) -> std::ops::Range<u8> {
+        self.template.get_num_players()
+    }
+    fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus> {
+        self.handle_action(by, Action::PlayCard(card))
+    }
+    fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match self.hand.as_ref() {
+            Some(x) if !self.ended => x.legal_moves(player),
+            _ => vec![],
+        }
+    }
+    fn handle_action(&mut self, by: &Player, action: Action) -> Vec<GameStatus> {
+        let hand = match self.hand.as_mut() {
+            Some(x) if !self.ended => x,
+            _ => return vec![GameStatus::InvalidMove(GameError::GameNotStarted)],
+        };
+        let mut res = hand.handle_action(by, action);
+        if let Some(i) = res.iter().position(|x| matches!(x, GameStatus::GameEnded)) {
+            res.remove(i);
+            res.append(&mut self.end_hand());
+        }
+        res
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError> {
+        if self.hand.is_some() {
+            Err(GameError::AlreadyStarted)
+        } else if self.players.len() >= self.get_num_players().end as usize {
+            Err(GameError::GameFull)
+        } else {
+            self.players.push(player.clone());
+            Ok(GameStatus::WaitingForPlayers(
+                self.players.len() >= self.get_num_players().start as usize,
+                player,
+            ))
+        }
+    }
+    fn get_next_player(&self) -> Option<Player> {
+        if self.ended {
+            return None;
+        }
+        self.hand.as_ref().and_then(|x| x.get_next_player())
+    }
+    fn start(&mut self) -> GameStatus {
+        if self.hand.is_some() {
+            return GameStatus::InvalidMove(GameError::AlreadyStarted);
+        }
+        let status = self.new_hand();
+        if matches!(status, GameStatus::InvalidMove(_)) {
+            self.hand = None;
+        }
+        status
+    }
+    /// The totals of the teams, capped to what fits in the fraction.
+    /// Use [`Game::get_points`] for the actual points.
+    fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)> {
+        self.get_totals()
+            .into_iter()
+            .map(|x| {
+                (
+                    x.0,
+                    fraction::GenericFraction::new(x.1.clamp(0, u8::MAX as i32) as u8, 1u8),
+                )
+            })
+            .collect()
+    }
+    fn get_points(&self) -> Vec<(Vec<Player>, i32)> {
+        self.get_totals()
+    }
+    fn get_status(&self) -> Text {
+        text!(
+            "Partita di {}\nPunteggi della partita:\n{}\n{}",
+            self.get_title(),
+            scores_description(&self.get_totals(), self.limit()),
+            self.hand
+                .as_ref()
+                .map(|x| x.get_status())
+                .unwrap_or_else(|| Text::new(""))
+        )
+    }
+    fn get_players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+    fn get_new_instance(&self) -> Box<dyn Game> {
+        Box::new(Self::new(self.template.get_new_instance(), self.target))
+    }
+    /// The variants of the game the hands are created from
+    fn variants(&self) -> Vec<Variant> {
+        self.template.variants()
+    }
+    /// The rules can't change once the first hand has been dealt
+    fn set_variant(&mut self, variant: &str, option: &str) -> Result<(), GameError> {
+        if self.hand.is_some() {
+            Err(GameError::AlreadyStarted)
+        } else {
+            self.template.set_variant(variant, option)
+        }
+    }
+    /// The match with the hand being played determinized and the next hands shuffled from `seed`.
+    /// Before the first hand it's only possible if the hands can be determinized.
+    fn determinize(&self, player: &Player, seed: u64) -> Option<Box<dyn Game>> {
+        let hand = match self.hand.as_ref() {
+            Some(x) => Some(x.determinize(player, seed)?),
+            None => {
+                self.template.determinize(player, seed)?;
+                None
+            }
+        };
+        Some(Box::new(Self {
+            template: self.template.snapshot().state.into_game(),
+            target: self.target,
+            players: self.players.clone(),
+            hand,
+            hand_scores: self.hand_scores.clone(),
+            ended: self.ended,
+            shuffler: utils::Shuffler::new(seed),
+        }))
+    }
+    /// The view of the hand being played, with the scores of the whole match
+    fn view_for(&self, player: &Player) -> PlayerView {
+        let view = PlayerView::new(self, player);
+        match self.hand.as_ref() {
+            Some(hand) if !self.ended => PlayerView {
+                game: view.game,
+                scores: view.scores,
+                ..hand.view_for(player)
+            },
+            _ => view,
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        GameState::Match(MatchState {
+            template: Box::new(self.template.snapshot().state),
+            target: self.target,
+            players: self.players.clone(),
+            hand: self.hand.as_ref().map(|x| Box::new(x.snapshot().state)),
+            hand_scores: self.hand_scores.clone(),
+            ended: self.ended,
+            shuffler: self.shuffler.clone(),
+        })
+        .into()
+    }
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus> {
+        let hand = match self.hand.as_mut() {
+            Some(x) if !self.ended => x,
+            _ => {
+                return vec![GameStatus::NotifyRoom(text!(
+                    "{} ha detto: {}",
+                    from.name,
+                    message
+                ))]
+            }
+        };
+        let mut res = hand.handle_message(message, from);
+        if let Some(i) = res.iter().position(|x| matches!(x, GameStatus::GameEnded)) {
+            res.remove(i);
+            res.append(&mut self.end_hand());
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::briscola::Briscola;
+    use crate::games::scala40::Scala40;
+    use crate::games::scopa::Scopa;
+    use crate::games::{marafone, scopone};
+    use crate::testing;
+
+    fn new_match(template: Box<dyn Game>, target: Target, players: i64) -> Match {
+        let mut game = Match::new(template, target);
+        testing::add_players(&mut game, players);
+        game
+    }
+
+    /// The last choice the game asked for
+    fn choice(status: &[GameStatus]) -> Option<(Player, Vec<Card>)> {
+        status.iter().rev().find_map(|x| match x {
+            GameStatus::WaitingForChoice(p, c) => Some((p.clone(), c.clone())),
+            GameStatus::WaitingForChoiceCustomMessage(p, c, _) => Some((p.clone(), c.clone())),
+            _ => None,
+        })
+    }
+
+    /// Plays the first legal card until `hands` hands have been played or the match ends,
+    /// returns the next choice the game asks for
+    fn play(game: &mut Match, hands: usize) -> Option<(Player, Vec<Card>)> {
+        let mut next = choice(&[game.start()]);
+        while let Some((player, cards)) = next {
+            let status = cards
+                .iter()
+                .map(|x| game.handle_move(&player, *x))
+                .find(|x| !matches!(x[0], GameStatus::InvalidMove(_)))
+                .unwrap();
+            next = choice(&status);
+            if game.ended || game.hand_scores.len() >= hands {
+                break;
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn best_of_three() {
+        let mut game = new_match(Box::new(Briscola::default()), Target::BestOf(3), 2);
+        assert_eq!(game.get_name(), "Briscola");
+        assert_eq!(game.get_title().to_string(), "Briscola al meglio delle 3");
+        play(&mut game, usize::MAX);
+        assert!(game.get_hand_scores().len() >= 2);
+        assert!(game.get_totals().iter().any(|x| x.1 == 2));
+        assert_eq!(game.get_next_player(), None);
+    }
+
+    #[test]
+    fn points_are_summed() {
+        let mut game = new_match(Box::new(Scopa::default()), Target::Points(11), 2);
+        play(&mut game, usize::MAX);
+        let totals = game.get_totals();
+        for (team, total) in &totals {
+            let sum: i32 = game
+                .get_hand_scores()
+                .iter()
+                .flatten()
+                .filter(|x| same_team(&x.0, team))
+                .map(|x| x.1)
+                .sum();
+            assert_eq!(*total, sum);
+        }
+        assert!(totals.iter().any(|x| x.1 >= 11));
+    }
+
+    #[test]
+    fn the_first_player_rotates() {
+        let mut game = marafone::new();
+        testing::add_players(&mut game, 4);
+        // Nella seconda mano la briscola la sceglie il secondo giocatore
+        let (chooser, cards) = play(&mut game, 1).unwrap();
+        assert_eq!(chooser, game.get_players()[1]);
+        assert_eq!(cards.len(), 10);
+    }
+
+    #[test]
+    fn scopone() {
+        let mut game = scopone::new(false, 11);
+        testing::add_players(&mut game, 4);
+        play(&mut game, usize::MAX);
+        assert!(game.get_totals().iter().any(|x| x.1 >= 11));
+        assert_eq!(game.get_totals().len(), 2);
+    }
+
+    #[test]
+    fn players_are_eliminated() {
+        let mut game = new_match(Box::new(Scala40::default()), Target::Elimination(101), 3);
+        game.start();
+        let players = game.get_players();
+        let hand = |points: &[i32]| {
+            players
+                .iter()
+                .zip(points.iter())
+                .map(|x| (vec![x.0.clone()], *x.1))
+                .collect()
+        };
+        game.hand_scores.push(hand(&[0, 100, 120]));
+        assert_eq!(game.active_players(), players[..2].to_vec());
+        assert_eq!(game.winner(), None);
+        // Chi è eliminato non gioca più
+        game.new_hand();
+        assert_eq!(game.hand.as_ref().unwrap().get_players().len(), 2);
+        game.hand_scores.push(hand(&[10, 1]));
+        assert_eq!(game.winner(), Some(vec![players[0].clone()]));
+    }
+}
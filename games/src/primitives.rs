@@ -1,30 +1,109 @@
+use crate::i18n::Text;
+use crate::text;
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, PartialEq};
 use std::hash::Hash;
 
-pub type Card = (CardType, CardSuit);
+/// A playing card. Jokers have their own rank and suit, see [`Card::JOKER`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
 
+impl Card {
+    pub const JOKER: Card = Card {
+        rank: Rank::Joker,
+        suit: Suit::Joker,
+    };
+    pub const fn new(rank: Rank, suit: Suit) -> Self {
+        Self { rank, suit }
+    }
+    pub fn is_joker(&self) -> bool {
+        self.rank == Rank::Joker
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum CardDeckType {
+    /// Forty cards with Italian suits, from ace to seven plus fante, cavallo and re
     Briscola,
+    /// Fifty-two cards with French suits and two jokers
     Poker,
 }
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub enum CardType {
-    Numeric(u8),
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Rank {
+    Ace,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    /// Fante in the Italian deck
+    Jack,
+    /// Cavallo in the Italian deck
+    Queen,
+    /// Re in the Italian deck
     King,
-    Queen, // Cavallo in Briscola
-    Jack,  // Fante in Briscola
-    Jolly, // Poker
+    Joker,
 }
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub enum CardSuit {
+
+impl Rank {
+    /// Ranks in order, from ace to king
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+    ];
+    /// The number of the rank: one for aces, eleven to thirteen for figures and zero for jokers
+    pub fn number(&self) -> u8 {
+        match self {
+            Rank::Joker => 0,
+            _ => *self as u8 + 1,
+        }
+    }
+    /// The rank with the given number, from one for aces to thirteen for kings
+    pub fn from_number(number: u8) -> Option<Rank> {
+        Rank::ALL.get((number as usize).checked_sub(1)?).copied()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Suit {
+    // Semi italiani
     Spade,
     Coppe,
     Denari,
     Bastoni,
+    // Semi francesi
+    Hearts,
+    Diamonds,
+    Clubs,
+    Spades,
+    Joker,
+}
+
+impl Suit {
+    pub const ITALIAN: [Suit; 4] = [Suit::Spade, Suit::Coppe, Suit::Denari, Suit::Bastoni];
+    pub const FRENCH: [Suit; 4] = [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades];
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
 pub struct Player {
     pub id: i64,
     pub name: String,
@@ -37,52 +116,296 @@ pub enum GameStatus {
     InProgress(Player),
     WaitingForPlayers(bool, Player),
     WaitingForChoice(Player, Vec<Card>),
-    InvalidMove(&'static str),
-    WaitingForChoiceCustomMessage(Player, Vec<Card>, &'static str),
-    NotifyUser(Player, String),
-    NotifyRoom(String),
+    InvalidMove(GameError),
+    WaitingForChoiceCustomMessage(Player, Vec<Card>, Text),
+    NotifyUser(Player, Text),
+    NotifyRoom(Text),
     CardPlayed(Player, Card),
+    /// The first player has found out who's playing with them
+    PartnerRevealed(Player, Player),
+    /// The player has chosen the briscola, or called a card of this suit
+    BriscolaChosen(Player, Suit),
+    /// The player has changed the rules before the start, the text says how
+    RulesChanged(Player, Text),
+    /// A hand of a match has ended: its number, counting from one, and the points of each team in it
+    HandScored(usize, Vec<(Vec<Player>, i32)>),
+}
+
+/// Why a game didn't accept a player or a move
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameError {
+    GameNotStarted,
+    AlreadyStarted,
+    GameEnded,
+    GameFull,
+    NotEnoughPlayers,
+    /// The game can only be played by this many players
+    WrongNumberOfPlayers(Vec<u8>),
+    NotYourTurn,
+    /// It's somebody else's turn to choose the briscola or call a card
+    NotYourChoice,
+    CardNotInHand,
+    MustFollowSuit,
+    /// The game doesn't have moves of this kind
+    UnsupportedAction,
+    /// The game expects a message, not a card
+    CardNotExpected,
+    NoCardsSelected,
+    InvalidBid,
+    /// The called card is in the hand of the caller
+    CannotCallOwnCard,
+    /// There's no combination of cards on the table that can be taken with this card
+    CannotCapture,
+    NoSuchMeld,
+    InvalidMeld,
+    /// Melds have to be worth at least these points to open
+    NotEnoughPointsToOpen(u16),
+    NotOpenedYet,
+    /// Who picks up the discard before opening has to open with it, or give it back
+    MustOpenWithPickedUp,
+    CannotAttach,
+    EmptyStock,
+    NoDiscards,
+    AlreadyDrawn,
+    MustDrawFirst,
+    MustKeepADiscard,
+    /// Closing, or staying with a single card, needs a burraco
+    BurracoNeeded,
+    CannotDiscardWildcard,
+    /// The game has no such variant of the rules, or no such option for it
+    NoSuchVariant,
+    /// The snapshot was taken with this version of the format, which can't be restored
+    IncompatibleSnapshot(u32),
+}
+
+impl GameError {
+    /// What went wrong, to be shown to the player
+    pub fn text(&self) -> Text {
+        match self {
+            GameError::GameNotStarted => Text::new("La partita non è ancora iniziata"),
+            GameError::AlreadyStarted => Text::new("La partita è già cominciata"),
+            GameError::GameEnded => Text::new("La partita è finita"),
+            GameError::GameFull => Text::new("La partita è al completo"),
+            GameError::NotEnoughPlayers => Text::new("Non ci sono abbastanza giocatori"),
+            GameError::WrongNumberOfPlayers(x) => text!(
+                "Si gioca in {}",
+                Text::join(
+                    x.iter().map(|n| match n {
+                        2 => Text::new("due"),
+                        3 => Text::new("tre"),
+                        4 => Text::new("quattro"),
+                        5 => Text::new("cinque"),
+                        n => text!("{}", *n),
+                    }),
+                    " o in "
+                )
+            ),
+            GameError::NotYourTurn => Text::new("Non è ancora il tuo turno!"),
+            GameError::NotYourChoice => Text::new("Non tocca a te scegliere!"),
+            GameError::CardNotInHand => Text::new("Non hai questa carta"),
+            GameError::MustFollowSuit => {
+                Text::new("Devi giocare una carta dello stesso seme della prima!")
+            }
+            GameError::UnsupportedAction => {
+                Text::new("Questa mossa non è prevista in questo gioco")
+            }
+            GameError::CardNotExpected => Text::new("Ora non devi giocare una carta"),
+            GameError::NoCardsSelected => Text::new("Non hai indicato carte valide"),
+            GameError::InvalidBid => Text::new("Non puoi puntare questa cifra"),
+            GameError::CannotCallOwnCard => {
+                Text::new("Non puoi chiamare una carta che hai in mano")
+            }
+            GameError::CannotCapture => Text::new("Non puoi prendere questa carta"),
+            GameError::NoSuchMeld => Text::new("Questa combinazione non esiste"),
+            GameError::InvalidMeld => Text::new("Le carte non formano una scala o un tris"),
+            GameError::NotEnoughPointsToOpen(x) => text!("Per aprire servono almeno {} punti", *x),
+            GameError::NotOpenedYet => Text::new("Prima devi aprire"),
+            GameError::MustOpenWithPickedUp => Text::new(
+                "Devi aprire con la carta raccolta, oppure scartarla per rimetterla negli scarti e pescare dal mazzo",
+            ),
+            GameError::CannotAttach => {
+                Text::new("Queste carte non si possono attaccare alla combinazione")
+            }
+            GameError::EmptyStock => Text::new("Non ci sono carte da pescare"),
+            GameError::NoDiscards => Text::new("Non ci sono scarti da raccogliere"),
+            GameError::AlreadyDrawn => Text::new("Hai già pescato"),
+            GameError::MustDrawFirst => Text::new("Devi prima pescare una carta"),
+            GameError::MustKeepADiscard => Text::new("Devi tenere una carta da scartare"),
+            GameError::BurracoNeeded => Text::new("Prima devi fare almeno un burraco"),
+            GameError::CannotDiscardWildcard => Text::new("Non puoi chiudere scartando una matta"),
+            GameError::NoSuchVariant => Text::new("Questa variante delle regole non esiste"),
+            GameError::IncompatibleSnapshot(x) => text!(
+                "Il salvataggio è della versione {} del formato, questa versione legge solo la {}",
+                *x,
+                crate::snapshot::SNAPSHOT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text())
+    }
 }
 
+impl std::error::Error for GameError {}
+
 pub type CardDeck = Vec<Card>;
 
+/// A move that can involve more than one card or no card at all
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Action {
+    /// Play or discard a single card, like `Game::handle_move`
+    PlayCard(Card),
+    /// Draw a card from the stock
+    Draw,
+    /// Pick up the discard pile
+    PickUp,
+    /// Put one or more new melds on the table
+    Meld(Vec<Vec<Card>>),
+    /// Add cards to the meld in the given (zero-based) position
+    Attach(usize, Vec<Card>),
+}
+
+/// A part of the rules that changes from group to group, as it's shown to the players.
+/// Games keep their rules in a struct of their own, variants are how they're chosen from outside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    /// Identifies the variant in [`Game::set_variant`]
+    pub code: &'static str,
+    pub name: Text,
+    /// The codes of the options, with their names
+    pub options: Vec<(&'static str, Text)>,
+    /// The code of the option in use
+    pub chosen: &'static str,
+}
+
+impl Variant {
+    /// A variant that is either played or not, with the options `si` and `no`
+    pub fn switch(code: &'static str, name: Text, on: bool) -> Self {
+        Self {
+            code,
+            name,
+            options: vec![("no", Text::new("no")), ("si", Text::new("sì"))],
+            chosen: if on { "si" } else { "no" },
+        }
+    }
+    /// Whether the option of a [`switch`](Self::switch) turns it on, `None` if it isn't an option
+    pub fn is_on(option: &str) -> Option<bool> {
+        match option {
+            "si" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    }
+    /// The name of the option in use
+    pub fn chosen_name(&self) -> Text {
+        self.options
+            .iter()
+            .find(|x| x.0 == self.chosen)
+            .map(|x| x.1.clone())
+            .unwrap_or_else(|| Text::new(""))
+    }
+    /// The option after the one in use, going back to the first after the last one
+    pub fn next_option(&self) -> &'static str {
+        let chosen = self.options.iter().position(|x| x.0 == self.chosen);
+        let next = chosen.map(|x| (x + 1) % self.options.len()).unwrap_or(0);
+        self.options[next].0
+    }
+}
+
 pub trait Game: Send {
     /// Reinitialise the game (i.e. prepare the deck and so on) after a default instance has been cloned
     fn init(&mut self);
+    /// Sets where the decks of the game come from, it has to be called before `init`.
+    /// Games that aren't given a shuffler use one with a random seed.
+    fn set_shuffler(&mut self, shuffler: crate::utils::Shuffler);
+    /// Where the decks of the game come from
+    fn get_shuffler(&self) -> &crate::utils::Shuffler;
     /// Get the game's name
     fn get_name(&self) -> &str;
+    /// The name shown to the players, with how long the game lasts when it's more than a hand
+    fn get_title(&self) -> Text {
+        text!("{}", self.get_name())
+    }
     /// Which set does the game use? Briscola or Poker?
     fn get_card_set(&self) -> CardDeckType;
     /// Get the range in which the game can be played
     fn get_num_players(&self) -> std::ops::Range<u8>;
     /// The implementor of the game logic
     fn handle_move(&mut self, by: &Player, card: Card) -> Vec<GameStatus>;
-    /// The points associated to each card
-    fn get_card_rank(card: &CardType) -> fraction::GenericFraction<u8>
-    where
-        Self: Sized;
-    fn get_card_sorting_rank(card: &CardType) -> u8
-    where
-        Self: Sized;
-    fn add_player(&mut self, player: Player) -> Result<GameStatus, &str>;
+    /// The cards `player` can play right now.
+    /// It's empty when it isn't their turn or they can't move by playing a card.
+    fn legal_moves(&self, player: &Player) -> Vec<Card>;
+    /// Handles moves that can't be expressed by playing a single card.
+    /// Games that only need cards to be played can rely on the default implementation.
+    fn handle_action(&mut self, by: &Player, action: Action) -> Vec<GameStatus> {
+        match action {
+            Action::PlayCard(card) => self.handle_move(by, card),
+            _ => vec![GameStatus::InvalidMove(GameError::UnsupportedAction)],
+        }
+    }
+    fn add_player(&mut self, player: Player) -> Result<GameStatus, GameError>;
     fn get_next_player(&self) -> Option<Player>;
     fn start(&mut self) -> GameStatus;
     fn get_scores(&self) -> Vec<(Vec<Player>, fraction::GenericFraction<u8>)>;
-    fn get_status(&self) -> String;
+    /// The points of each team as whole numbers, for games whose points can be negative
+    /// or too many for [`get_scores`](Self::get_scores). By default the scores without their fractions.
+    fn get_points(&self) -> Vec<(Vec<Player>, i32)> {
+        self.get_scores()
+            .into_iter()
+            .map(|x| (x.0, x.1.floor().numer().map(|y| *y as i32).unwrap_or(0)))
+            .collect()
+    }
+    fn get_status(&self) -> Text;
     fn get_players(&self) -> Vec<Player>;
     fn get_new_instance(&self) -> Box<dyn Game>;
+    /// What `player` can see of the game, without the information hidden to them.
+    /// Anybody who isn't playing gets the view of a spectator.
+    fn view_for(&self, player: &Player) -> crate::view::PlayerView;
+    /// A copy of the game where the cards `player` can't see have been dealt again at random,
+    /// to imagine what the others might have. Games that can't do it return `None`.
+    fn determinize(&self, _player: &Player, _seed: u64) -> Option<Box<dyn Game>> {
+        None
+    }
+    /// The variants of the rules the game can be played with, each with the option in use.
+    /// Games played in a single way have none.
+    fn variants(&self) -> Vec<Variant> {
+        vec![]
+    }
+    /// Chooses the option of a variant of the rules, which can only be done before the game starts
+    fn set_variant(&mut self, _variant: &str, _option: &str) -> Result<(), GameError> {
+        Err(GameError::NoSuchVariant)
+    }
+    /// The full state of the game, which can be saved and restored later
+    fn snapshot(&self) -> crate::snapshot::Snapshot;
     /// This function gets called when a user sends a text message to the bot.
     /// It should handle the message and pass it to the right users.
-    fn handle_message(&self, message: String, from: Player) -> Vec<GameStatus>;
+    /// Games can also use it to receive commands that can't be expressed by playing a card.
+    fn handle_message(&mut self, message: String, from: Player) -> Vec<GameStatus>;
+}
+
+/// The value of the cards in a game, known without an instance of it.
+/// Games that wrap another one, like a [`Match`](crate::multi_hand::Match), don't know it.
+pub trait CardValues {
+    /// The points associated to each card
+    fn get_card_rank(card: &Rank) -> fraction::GenericFraction<u8>;
+    fn get_card_sorting_rank(card: &Rank) -> u8;
 }
 
-impl From<&CardSuit> for String {
-    fn from(s: &CardSuit) -> Self {
+impl From<&Suit> for String {
+    fn from(s: &Suit) -> Self {
         match s {
-            CardSuit::Bastoni => "🥢",
-            CardSuit::Spade => "🗡 ",
-            CardSuit::Coppe => "🏆",
-            CardSuit::Denari => "💰",
+            Suit::Bastoni => "🥢",
+            Suit::Spade => "🗡 ",
+            Suit::Coppe => "🏆",
+            Suit::Denari => "💰",
+            Suit::Hearts => "♥️",
+            Suit::Diamonds => "♦️",
+            Suit::Clubs => "♣️",
+            Suit::Spades => "♠️",
+            Suit::Joker => "",
         }
         .to_owned()
     }
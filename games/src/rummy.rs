@@ -0,0 +1,367 @@
+//! The core shared by the rummy games: the hands, the stock, the discard pile
+//! and the turn made of drawing, melding and discarding.
+//! A game only says how it's played through [`RummyRules`], checks and keeps its melds,
+//! and decides what happens when a player runs out of cards.
+use crate::i18n::Text;
+use crate::primitives::*;
+use crate::text;
+use crate::utils;
+use crate::view;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// How the turns of a rummy game are played
+pub trait RummyRules: Game + Sized {
+    /// Whether picking up takes the whole discard pile, rather than the card on top
+    const PICK_UP_ALL: bool;
+    /// Whether each team melds on its own, rather than everybody on the same table
+    const TEAM_MELDS: bool;
+    /// Puts the cards of a hand in the order they're shown to the player
+    fn sort_hand(hand: &mut [Card]);
+}
+
+/// Position of the suit when sorting a hand
+pub(crate) fn suit_index(suit: &Suit) -> u8 {
+    match suit {
+        Suit::Clubs => 0,
+        Suit::Hearts => 1,
+        Suit::Diamonds => 2,
+        Suit::Spades => 3,
+        _ => 4,
+    }
+}
+
+/// Position of the card in a sequence, with the ace counted as one
+pub(crate) fn card_number(card: &Rank) -> Option<u8> {
+    match card {
+        Rank::Joker => None,
+        x => Some(x.number()),
+    }
+}
+
+/// The hands of a rummy game `G`, played by players known by their position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rummy<G> {
+    players: Vec<Player>,
+    /// Cards in hand for each player, kept sorted
+    in_hand: Vec<Vec<Card>>,
+    /// The cards still to be drawn, from the last one
+    stock: CardDeck,
+    /// The discard pile, the last card is the one on top
+    discarded: Vec<Card>,
+    next_player: Option<usize>,
+    /// Whether the player whose turn it is has already drawn
+    has_drawn: bool,
+    #[serde(skip)]
+    game: PhantomData<fn() -> G>,
+}
+
+impl<G: RummyRules> Default for Rummy<G> {
+    fn default() -> Self {
+        Self {
+            players: vec![],
+            in_hand: vec![],
+            stock: vec![],
+            discarded: vec![],
+            next_player: None,
+            has_drawn: false,
+            game: PhantomData,
+        }
+    }
+}
+
+impl<G: RummyRules> Rummy<G> {
+    pub fn add_player(&mut self, player: Player) {
+        self.players.push(player);
+        self.in_hand.push(vec![]);
+    }
+    /// Gives `hand_size` cards to each player, taking them from the end of the deck,
+    /// and turns up the first discard. The rest is the stock, the first player starts.
+    pub fn deal(&mut self, mut deck: CardDeck, hand_size: usize) {
+        for hand in self.in_hand.iter_mut() {
+            *hand = deck.split_off(deck.len() - hand_size);
+            G::sort_hand(hand);
+        }
+        self.discarded = deck.pop().into_iter().collect();
+        self.stock = deck;
+        self.next_player = Some(0);
+        self.has_drawn = false;
+    }
+    /// Takes `count` cards from the stock, to set them aside
+    pub fn take_from_stock(&mut self, count: usize) -> Vec<Card> {
+        self.stock.split_off(self.stock.len().saturating_sub(count))
+    }
+    /// When the stock is over, the discards but the one on top are shuffled into a new stock
+    pub fn reshuffle_discards(&mut self, shuffler: &mut utils::Shuffler) {
+        if self.stock.is_empty() && self.discarded.len() > 1 {
+            let top = self.discarded.pop().unwrap();
+            self.stock.append(&mut self.discarded);
+            shuffler.shuffle(&mut self.stock);
+            self.discarded.push(top);
+        }
+    }
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+    pub fn position(&self, player: &Player) -> Option<usize> {
+        self.players.iter().position(|x| x == player)
+    }
+    pub fn hands(&self) -> &[Vec<Card>] {
+        &self.in_hand
+    }
+    /// The hand of `player`, empty for who isn't playing
+    pub fn hand_of(&self, player: &Player) -> Vec<Card> {
+        self.position(player)
+            .map(|x| self.in_hand[x].clone())
+            .unwrap_or_default()
+    }
+    /// Gives the player a new hand, like what's left after melding or a pozzetto
+    pub fn set_hand(&mut self, player: usize, mut hand: Vec<Card>) {
+        G::sort_hand(&mut hand);
+        self.in_hand[player] = hand;
+    }
+    /// The hand of the player without the given cards, or `CardNotInHand` if they don't have them
+    pub fn hand_without(&self, player: usize, cards: &[Card]) -> Result<Vec<Card>, GameError> {
+        let mut hand = self.in_hand[player].clone();
+        if utils::remove_cards(&mut hand, cards) {
+            Ok(hand)
+        } else {
+            Err(GameError::CardNotInHand)
+        }
+    }
+    pub fn stock(&self) -> &[Card] {
+        &self.stock
+    }
+    pub fn discarded(&self) -> &[Card] {
+        &self.discarded
+    }
+    pub fn next_player(&self) -> Option<usize> {
+        self.next_player
+    }
+    pub fn set_next_player(&mut self, player: Option<usize>) {
+        self.next_player = player;
+    }
+    /// Checks that it's the turn of `by` and that they have drawn (or not) already
+    pub fn check_turn(&self, by: &Player, must_have_drawn: bool) -> Result<usize, GameError> {
+        let player = match self.next_player {
+            None => return Err(GameError::GameNotStarted),
+            Some(x) => x,
+        };
+        if &self.players[player] != by {
+            Err(GameError::NotYourTurn)
+        } else if must_have_drawn && !self.has_drawn {
+            Err(GameError::MustDrawFirst)
+        } else if !must_have_drawn && self.has_drawn {
+            Err(GameError::AlreadyDrawn)
+        } else {
+            Ok(player)
+        }
+    }
+    /// After drawing, any card in hand can be discarded
+    pub fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match self.check_turn(player, true) {
+            Ok(x) => self.in_hand[x].clone(),
+            Err(_) => vec![],
+        }
+    }
+    pub fn hand_description(&self, player: usize) -> Text {
+        text!(
+            "La tua mano:\n{}",
+            Text::join(
+                self.in_hand[player]
+                    .iter()
+                    .enumerate()
+                    .map(|x| text!("{}. {}", x.0 + 1, x.1)),
+                "\n"
+            )
+        )
+    }
+    /// Tells the player it's their turn to draw
+    pub fn turn_message(&self, player: usize) -> GameStatus {
+        let message = if G::PICK_UP_ALL {
+            text!(
+                "{}\nTocca a te! Scrivi «pesca» per pescare dal mazzo o «raccogli» per prendere tutti gli scarti",
+                self.hand_description(player)
+            )
+        } else {
+            text!(
+                "{}\nTocca a te! Scrivi «pesca» per pescare dal mazzo o «raccogli» per prendere {}",
+                self.hand_description(player),
+                self.discarded
+                    .last()
+                    .map(|x| text!("{}", x))
+                    .unwrap_or_else(|| Text::new("lo scarto"))
+            )
+        };
+        GameStatus::NotifyUser(self.players[player].clone(), message)
+    }
+    /// Asks the player, who has drawn, to meld or to discard
+    pub fn play_message(&self, player: usize) -> Vec<GameStatus> {
+        let message = if G::TEAM_MELDS {
+            text!(
+                "{}\nScrivi «cala 1 2 3 / 4 5 6» per calare, «attacca 1 4» per attaccare la quarta carta alla prima combinazione della tua squadra oppure scegli una carta da scartare.",
+                self.hand_description(player)
+            )
+        } else {
+            text!(
+                "{}\nScrivi «cala 1 2 3 / 4 5 6» per calare, «attacca 1 4» per attaccare la quarta carta alla prima combinazione oppure scegli una carta da scartare.",
+                self.hand_description(player)
+            )
+        };
+        vec![
+            GameStatus::NotifyUser(self.players[player].clone(), message),
+            GameStatus::WaitingForChoiceCustomMessage(
+                self.players[player].clone(),
+                self.in_hand[player].clone(),
+                Text::new("Scegli la carta da scartare:"),
+            ),
+        ]
+    }
+    /// Draws the card on top of the stock or picks up from the discard pile,
+    /// then asks the player to meld or to discard
+    pub fn draw(&mut self, by: &Player, pick_up: bool) -> Result<Vec<GameStatus>, GameError> {
+        let player = self.check_turn(by, false)?;
+        let mut res = vec![];
+        let mut cards = if !pick_up {
+            vec![self.stock.pop().ok_or(GameError::EmptyStock)?]
+        } else if self.discarded.is_empty() {
+            return Err(GameError::NoDiscards);
+        } else if G::PICK_UP_ALL {
+            res.push(GameStatus::NotifyRoom(text!(
+                "{} raccoglie gli scarti ({} carte)",
+                by.name,
+                self.discarded.len()
+            )));
+            std::mem::take(&mut self.discarded)
+        } else {
+            self.discarded.pop().into_iter().collect()
+        };
+        self.in_hand[player].append(&mut cards);
+        G::sort_hand(&mut self.in_hand[player]);
+        self.has_drawn = true;
+        res.append(&mut self.play_message(player));
+        Ok(res)
+    }
+    /// Puts the card on the discard pile, the player may have no cards left
+    pub fn discard(&mut self, by: &Player, card: Card) -> Result<usize, GameError> {
+        let player = self.check_turn(by, true)?;
+        let index = self.in_hand[player]
+            .iter()
+            .position(|x| x == &card)
+            .ok_or(GameError::CardNotInHand)?;
+        self.in_hand[player].remove(index);
+        self.discarded.push(card);
+        Ok(player)
+    }
+    /// Passes the turn to the player after `player`, who has to draw
+    pub fn next_turn(&mut self, player: usize) -> Vec<GameStatus> {
+        let next_player = (player + 1) % self.players.len();
+        self.next_player = Some(next_player);
+        self.has_drawn = false;
+        vec![
+            GameStatus::InProgress(self.players[next_player].clone()),
+            self.turn_message(next_player),
+        ]
+    }
+    /// The action written by the player, with the cards given by their position in hand.
+    /// `None` if the message isn't a command.
+    pub fn parse_message(&self, message: &str, from: &Player) -> Option<Result<Action, GameError>> {
+        utils::parse_action(message, &self.hand_of(from))
+    }
+    /// Only the card on top of the discard pile, for [`PlayerView`](crate::view::PlayerView)
+    pub fn table_view(&self) -> Vec<(Option<Player>, Card)> {
+        self.discarded
+            .last()
+            .map(|x| (None, *x))
+            .into_iter()
+            .collect()
+    }
+    /// How many cards each player has, for [`PlayerView`](crate::view::PlayerView)
+    pub fn hand_sizes(&self) -> Vec<(Player, usize)> {
+        view::hand_sizes(&self.players, &self.in_hand)
+    }
+    #[cfg(test)]
+    pub(crate) fn hand_mut(&mut self, player: usize) -> &mut Vec<Card> {
+        &mut self.in_hand[player]
+    }
+    #[cfg(test)]
+    pub(crate) fn stock_mut(&mut self) -> &mut CardDeck {
+        &mut self.stock
+    }
+    #[cfg(test)]
+    pub(crate) fn discarded_mut(&mut self) -> &mut Vec<Card> {
+        &mut self.discarded
+    }
+    #[cfg(test)]
+    pub(crate) fn set_turn(&mut self, player: usize, has_drawn: bool) {
+        self.next_player = Some(player);
+        self.has_drawn = has_drawn;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{burraco::Burraco, scala40::Scala40};
+    use crate::testing::player;
+    use Rank::*;
+    use Suit::*;
+
+    /// Two players with three cards each, the Queen of Spades turned up
+    /// and two cards under it, the Four of Clubs on top of the stock
+    fn dealt<G: RummyRules>() -> Rummy<G> {
+        let mut rummy = Rummy::default();
+        rummy.add_player(player(0));
+        rummy.add_player(player(1));
+        let mut deck = vec![
+            Card::new(Three, Clubs),
+            Card::new(Four, Clubs),
+            Card::new(Queen, Spades),
+        ];
+        deck.extend((5..8).map(|_| Card::new(King, Hearts)));
+        deck.extend((5..8).map(|_| Card::new(Ace, Hearts)));
+        rummy.deal(deck, 3);
+        rummy.discarded.insert(0, Card::new(Two, Diamonds));
+        rummy
+    }
+
+    #[test]
+    fn picking_up_takes_the_top_discard() {
+        let mut rummy = dealt::<Scala40>();
+        assert_eq!(rummy.hands()[0], vec![Card::new(Ace, Hearts); 3]);
+        assert!(rummy.discard(&player(0), Card::new(Ace, Hearts)).is_err());
+        rummy.draw(&player(0), true).unwrap();
+        assert!(rummy.hands()[0].contains(&Card::new(Queen, Spades)));
+        assert_eq!(rummy.discarded(), [Card::new(Two, Diamonds)]);
+        assert!(matches!(
+            rummy.draw(&player(0), false),
+            Err(GameError::AlreadyDrawn)
+        ));
+        assert_eq!(rummy.discard(&player(0), Card::new(Ace, Hearts)), Ok(0));
+        rummy.next_turn(0);
+        assert!(matches!(
+            rummy.check_turn(&player(0), false),
+            Err(GameError::NotYourTurn)
+        ));
+        rummy.draw(&player(1), false).unwrap();
+        assert!(rummy.hands()[1].contains(&Card::new(Four, Clubs)));
+    }
+
+    #[test]
+    fn picking_up_takes_the_whole_pile() {
+        let mut rummy = dealt::<Burraco>();
+        rummy.draw(&player(0), true).unwrap();
+        assert_eq!(rummy.hands()[0].len(), 5);
+        assert!(rummy.discarded().is_empty());
+        assert_eq!(rummy.legal_moves(&player(0)).len(), 5);
+    }
+
+    #[test]
+    fn discards_make_a_new_stock() {
+        let mut rummy = dealt::<Scala40>();
+        rummy.stock.clear();
+        rummy.reshuffle_discards(&mut utils::Shuffler::new(0));
+        assert_eq!(rummy.stock(), [Card::new(Two, Diamonds)]);
+        assert_eq!(rummy.discarded(), [Card::new(Queen, Spades)]);
+    }
+}
@@ -0,0 +1,111 @@
+//! Snapshots of the full state of a game, so that it can be saved and restored later.
+use crate::games::*;
+use crate::multi_hand::{Match, MatchState};
+use crate::primitives::{Game, GameError};
+use serde::{Deserialize, Serialize};
+
+/// The version of the snapshot format, to be increased when the state of a game changes.
+/// Snapshots of other versions aren't migrated but refused by [`Snapshot::restore`],
+/// so increasing it drops every game saved before the update.
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+/// The state of one of the games of this crate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GameState {
+    Beccaccino(beccaccino::Beccaccino),
+    Briscola(briscola::Briscola),
+    BriscolaChiamata(briscola_chiamata::BriscolaChiamata),
+    Burraco(burraco::Burraco),
+    Rubamazzo(rubamazzo::Rubamazzo),
+    Scala40(scala40::Scala40),
+    Scopa(scopa::Scopa),
+    SetteEMezzo(sette_e_mezzo::SetteEMezzo),
+    Tressette(tressette::Tressette),
+    Match(MatchState),
+}
+
+impl GameState {
+    /// Turns the state back into a game that can be played
+    pub fn into_game(self) -> Box<dyn Game> {
+        match self {
+            GameState::Beccaccino(x) => Box::new(x),
+            GameState::Briscola(x) => Box::new(x),
+            GameState::BriscolaChiamata(x) => Box::new(x),
+            GameState::Burraco(x) => Box::new(x),
+            GameState::Rubamazzo(x) => Box::new(x),
+            GameState::Scala40(x) => Box::new(x),
+            GameState::Scopa(x) => Box::new(x),
+            GameState::SetteEMezzo(x) => Box::new(x),
+            GameState::Tressette(x) => Box::new(x),
+            GameState::Match(x) => Box::new(Match::from(x)),
+        }
+    }
+}
+
+/// A game state tagged with the version of the format it was saved with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub state: GameState,
+}
+
+impl Snapshot {
+    /// Restores the game, if the snapshot was taken with the current format.
+    /// Any other version is a [`GameError::IncompatibleSnapshot`], even an older one:
+    /// the state of the games may have changed in ways that can't be read back.
+    pub fn restore(self) -> Result<Box<dyn Game>, GameError> {
+        if self.version == SNAPSHOT_VERSION {
+            Ok(self.state.into_game())
+        } else {
+            Err(GameError::IncompatibleSnapshot(self.version))
+        }
+    }
+}
+
+impl From<GameState> for Snapshot {
+    fn from(state: GameState) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::all_games;
+    use crate::primitives::*;
+    use crate::testing;
+
+    #[test]
+    fn started_games_survive_a_round_trip() {
+        for mut game in all_games() {
+            game.init();
+            let count = game.get_num_players().end as i64;
+            testing::add_players(game.as_mut(), count);
+            game.start();
+            let json = serde_json::to_string(&game.snapshot()).unwrap();
+            let snapshot: Snapshot = serde_json::from_str(&json).unwrap();
+            let restored = snapshot.restore().unwrap();
+            assert_eq!(restored.get_status(), game.get_status());
+            assert_eq!(restored.get_next_player(), game.get_next_player());
+            assert_eq!(
+                serde_json::to_string(&restored.snapshot()).unwrap(),
+                json,
+                "{}",
+                game.get_name()
+            );
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut snapshot = briscola::Briscola::default().snapshot();
+        snapshot.version -= 1;
+        assert_eq!(
+            snapshot.restore().err(),
+            Some(GameError::IncompatibleSnapshot(SNAPSHOT_VERSION - 1))
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Fixtures shared by the tests of the crate
+use crate::primitives::*;
+
+/// The player with the given id, named after it
+pub(crate) fn player(id: i64) -> Player {
+    Player {
+        id,
+        name: format!("{}", id),
+    }
+}
+
+/// The players with the ids from 0 to `count - 1`
+pub(crate) fn players(count: i64) -> Vec<Player> {
+    (0..count).map(player).collect()
+}
+
+/// Adds the players with the ids from 0 to `count - 1` to the game and returns them
+pub(crate) fn add_players<G: Game + ?Sized>(game: &mut G, count: i64) -> Vec<Player> {
+    let players = players(count);
+    for player in &players {
+        game.add_player(player.clone()).unwrap();
+    }
+    players
+}
+
+/// The game with `count` players added, started
+pub(crate) fn started<G: Game>(mut game: G, count: i64) -> G {
+    add_players(&mut game, count);
+    game.start();
+    game
+}
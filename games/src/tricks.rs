@@ -0,0 +1,397 @@
+//! The core shared by the trick-taking games: the hands, the trick on the table,
+//! the cards won by each team and the stock the players draw from.
+//! A game only says how it's played through [`TrickRules`] and the [`Teams`] it's played in,
+//! chooses the trump, deals, and counts the points of the cards won.
+use crate::primitives::*;
+use crate::utils;
+use crate::view;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// How the tricks of a game are played.
+/// The strength of the cards in their suit is the one given by [`CardValues::get_card_sorting_rank`].
+pub trait TrickRules: Game + CardValues + Sized {
+    /// Whether players have to answer with the suit led, as long as they have it
+    const FOLLOW_SUIT: bool;
+}
+
+/// How the players are split in teams
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Teams {
+    /// Everybody plays for themselves
+    Single,
+    /// Players sitting in front of each other play together: the first with the third,
+    /// the second with the fourth. In two, everybody plays for themselves.
+    Alternate,
+}
+
+impl Teams {
+    fn count(&self, players: usize) -> usize {
+        match self {
+            Teams::Single => players,
+            Teams::Alternate => players.min(2),
+        }
+    }
+    fn team_of(&self, player: usize) -> usize {
+        match self {
+            Teams::Single => player,
+            Teams::Alternate => player % 2,
+        }
+    }
+}
+
+/// What happened after a card was played
+#[derive(Clone, Debug, PartialEq)]
+pub enum Played {
+    /// The trick goes on, and it's the turn of this player
+    Next(usize),
+    /// The trick was taken by `winner`, who leads the next one unless the hand is over.
+    /// After it, every player drew a card from the stock, starting from the winner.
+    Trick {
+        winner: usize,
+        drawn: Vec<(usize, Card)>,
+    },
+}
+
+/// The position in the trick of the card that takes it: the highest trump or,
+/// if no trump was played, the highest card of the suit led
+pub fn trick_winner<G: CardValues>(trick: &[Card], trump: Option<Suit>) -> usize {
+    let winning_suit = match trump {
+        Some(x) if trick.iter().any(|y| y.suit == x) => x,
+        _ => trick[0].suit,
+    };
+    trick
+        .iter()
+        .enumerate()
+        .filter(|x| x.1.suit == winning_suit)
+        .max_by_key(|x| G::get_card_sorting_rank(&x.1.rank))
+        .unwrap()
+        .0
+}
+
+/// The hands of a trick-taking game `G`, played by players known by their position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tricks<G> {
+    players: Vec<Player>,
+    in_hand: Vec<Vec<Card>>,
+    table: Vec<(Player, Card)>,
+    /// Cards won by each team, empty until the cards are dealt
+    won_cards: Vec<Vec<Card>>,
+    /// The team that took the last trick, once every card has been played
+    last_trick: Option<usize>,
+    /// The cards still to be drawn, from the last one
+    stock: CardDeck,
+    trump: Option<Suit>,
+    teams: Teams,
+    next_player: Option<usize>,
+    #[serde(skip)]
+    game: PhantomData<fn() -> G>,
+}
+
+impl<G: TrickRules> Tricks<G> {
+    pub fn new(teams: Teams) -> Self {
+        Self {
+            players: vec![],
+            in_hand: vec![],
+            table: vec![],
+            won_cards: vec![],
+            last_trick: None,
+            stock: vec![],
+            trump: None,
+            teams,
+            next_player: None,
+            game: PhantomData,
+        }
+    }
+    pub fn add_player(&mut self, player: Player) {
+        self.players.push(player);
+        self.in_hand.push(vec![]);
+    }
+    pub fn set_teams(&mut self, teams: Teams) {
+        self.teams = teams;
+    }
+    /// Gives `hand_size` cards to each player, taking them from the end of the deck in turn,
+    /// and leaves the rest as the stock. The first player leads.
+    pub fn deal(&mut self, mut deck: CardDeck, hand_size: usize) {
+        for hand in self.in_hand.iter_mut() {
+            *hand = (0..hand_size).filter_map(|_| deck.pop()).collect();
+        }
+        self.stock = deck;
+        self.table.clear();
+        self.won_cards = vec![vec![]; self.teams.count(self.players.len())];
+        self.last_trick = None;
+        self.next_player = Some(0);
+    }
+    /// Whether the cards have been dealt
+    pub fn is_dealt(&self) -> bool {
+        !self.won_cards.is_empty()
+    }
+    /// Whether every card has been played
+    pub fn is_over(&self) -> bool {
+        self.last_trick.is_some()
+    }
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+    pub fn position(&self, player: &Player) -> Option<usize> {
+        self.players.iter().position(|x| x == player)
+    }
+    pub fn hands(&self) -> &[Vec<Card>] {
+        &self.in_hand
+    }
+    /// The hand of `player`, empty for who isn't playing
+    pub fn hand_of(&self, player: &Player) -> Vec<Card> {
+        self.position(player)
+            .map(|x| self.in_hand[x].clone())
+            .unwrap_or_default()
+    }
+    #[cfg(test)]
+    pub(crate) fn hand_mut(&mut self, player: usize) -> &mut Vec<Card> {
+        &mut self.in_hand[player]
+    }
+    #[cfg(test)]
+    pub(crate) fn won_cards_mut(&mut self, team: usize) -> &mut Vec<Card> {
+        &mut self.won_cards[team]
+    }
+    pub fn table(&self) -> &[(Player, Card)] {
+        &self.table
+    }
+    pub fn stock(&self) -> &[Card] {
+        &self.stock
+    }
+    pub fn trump(&self) -> Option<Suit> {
+        self.trump
+    }
+    pub fn set_trump(&mut self, trump: Option<Suit>) {
+        self.trump = trump;
+    }
+    pub fn next_player(&self) -> Option<usize> {
+        self.next_player
+    }
+    pub fn set_next_player(&mut self, player: Option<usize>) {
+        self.next_player = player;
+    }
+    pub fn team_of(&self, player: usize) -> usize {
+        self.teams.team_of(player)
+    }
+    /// The players of each team
+    pub fn teams(&self) -> Vec<Vec<Player>> {
+        (0..self.teams.count(self.players.len()))
+            .map(|x| {
+                self.players
+                    .iter()
+                    .enumerate()
+                    .filter(|y| self.teams.team_of(y.0) == x)
+                    .map(|y| y.1.clone())
+                    .collect()
+            })
+            .collect()
+    }
+    /// The cards won by the team
+    pub fn won_cards(&self, team: usize) -> &[Card] {
+        self.won_cards
+            .get(team)
+            .map(|x| x.as_slice())
+            .unwrap_or(&[])
+    }
+    /// The team that took the last trick, once every card has been played
+    pub fn last_trick(&self) -> Option<usize> {
+        self.last_trick
+    }
+    /// The cards the player can play now
+    pub fn legal_moves(&self, player: &Player) -> Vec<Card> {
+        match self.next_player {
+            Some(x) if &self.players[x] == player && G::FOLLOW_SUIT => {
+                utils::follow_suit(&self.in_hand[x], self.table.first().map(|y| &y.1.suit))
+            }
+            Some(x) if &self.players[x] == player => self.in_hand[x].clone(),
+            _ => vec![],
+        }
+    }
+    /// Plays the card on the table, and when everybody has played gives the trick to its winner
+    pub fn play(&mut self, by: &Player, card: Card) -> Result<Played, GameError> {
+        let player = match self.next_player {
+            None => return Err(GameError::GameNotStarted),
+            Some(x) if &self.players[x] != by => return Err(GameError::NotYourTurn),
+            Some(x) => x,
+        };
+        let index = self.in_hand[player]
+            .iter()
+            .position(|x| x == &card)
+            .ok_or(GameError::CardNotInHand)?;
+        if !self.legal_moves(by).contains(&card) {
+            return Err(GameError::MustFollowSuit);
+        }
+        self.in_hand[player].remove(index);
+        self.table.push((by.clone(), card));
+        if self.table.len() < self.players.len() {
+            let next = (player + 1) % self.players.len();
+            self.next_player = Some(next);
+            return Ok(Played::Next(next));
+        }
+        let trick: Vec<Card> = self.table.iter().map(|x| x.1).collect();
+        let winner = self
+            .position(&self.table[trick_winner::<G>(&trick, self.trump)].0)
+            .unwrap();
+        let team = self.teams.team_of(winner);
+        self.table.clear();
+        self.won_cards[team].extend(trick);
+        let drawn = self.draw(winner);
+        if self.in_hand.iter().all(|x| x.is_empty()) {
+            self.last_trick = Some(team);
+            self.next_player = None;
+        } else {
+            self.next_player = Some(winner);
+        }
+        Ok(Played::Trick { winner, drawn })
+    }
+    /// Everybody draws a card starting from the winner of the trick, if there are enough for all
+    fn draw(&mut self, winner: usize) -> Vec<(usize, Card)> {
+        if self.stock.len() < self.players.len() {
+            return vec![];
+        }
+        (0..self.players.len())
+            .map(|x| {
+                let player = (winner + x) % self.players.len();
+                let card = self.stock.pop().unwrap();
+                self.in_hand[player].push(card);
+                (player, card)
+            })
+            .collect()
+    }
+    /// The cards on the table, for [`PlayerView`](crate::view::PlayerView)
+    pub fn table_view(&self) -> Vec<(Option<Player>, Card)> {
+        view::trick(&self.table)
+    }
+    /// How many cards each player has, for [`PlayerView`](crate::view::PlayerView)
+    pub fn hand_sizes(&self) -> Vec<(Player, usize)> {
+        view::hand_sizes(&self.players, &self.in_hand)
+    }
+    /// How many cards each team has won, for [`PlayerView`](crate::view::PlayerView)
+    pub fn captured(&self) -> Vec<(Vec<Player>, usize)> {
+        self.teams()
+            .into_iter()
+            .zip(self.won_cards.iter().map(|x| x.len()))
+            .collect()
+    }
+    /// The hands of the others and the stock, apart from the `shown` cards at its bottom
+    pub(crate) fn hidden_cards(&mut self, player: &Player, shown: usize) -> Vec<&mut [Card]> {
+        let mut res: Vec<&mut [Card]> = self
+            .in_hand
+            .iter_mut()
+            .zip(self.players.iter())
+            .filter(|x| x.1 != player)
+            .map(|x| x.0.as_mut_slice())
+            .collect();
+        if self.stock.len() > shown {
+            res.push(&mut self.stock[shown..]);
+        }
+        res
+    }
+    /// Only the stock apart from the `shown` cards at its bottom, for games played with the hands face up
+    pub(crate) fn hidden_stock(&mut self, shown: usize) -> Vec<&mut [Card]> {
+        if self.stock.len() > shown {
+            vec![&mut self.stock[shown..]]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::{beccaccino::Beccaccino, briscola::Briscola, tressette::Tressette};
+    use crate::testing::players;
+    use Rank::*;
+    use Suit::*;
+
+    /// Deals exactly the given hands, leaving `stock` to be drawn from
+    fn dealt<G: TrickRules>(teams: Teams, hands: Vec<Vec<Card>>, stock: CardDeck) -> Tricks<G> {
+        let mut tricks = Tricks::new(teams);
+        for player in players(hands.len() as i64) {
+            tricks.add_player(player);
+        }
+        // Le carte si danno dalla fine del mazzo
+        let mut deck = stock;
+        for hand in hands.iter().rev() {
+            deck.extend(hand.iter().rev());
+        }
+        tricks.deal(deck, hands[0].len());
+        tricks
+    }
+
+    #[test]
+    fn trump_beats_the_suit_led() {
+        let trick = [
+            Card::new(Three, Coppe),
+            Card::new(Two, Spade),
+            Card::new(Ace, Coppe),
+        ];
+        assert_eq!(trick_winner::<Briscola>(&trick, None), 2);
+        assert_eq!(trick_winner::<Briscola>(&trick, Some(Spade)), 1);
+        // Nel tressette il tre è la carta più alta
+        assert_eq!(trick_winner::<Tressette>(&trick, Some(Denari)), 0);
+    }
+
+    #[test]
+    fn players_must_follow_suit_when_required() {
+        let players = players(2);
+        let hands = vec![
+            vec![Card::new(Four, Coppe), Card::new(Seven, Bastoni)],
+            vec![Card::new(Five, Coppe), Card::new(Three, Spade)],
+        ];
+        let mut free: Tricks<Briscola> = dealt(Teams::Single, hands.clone(), vec![]);
+        let mut bound: Tricks<Beccaccino> = dealt(Teams::Single, hands, vec![]);
+        assert_eq!(
+            free.play(&players[0], Card::new(Four, Coppe)),
+            Ok(Played::Next(1))
+        );
+        assert_eq!(
+            bound.play(&players[0], Card::new(Four, Coppe)),
+            Ok(Played::Next(1))
+        );
+        assert_eq!(free.legal_moves(&players[1]).len(), 2);
+        assert_eq!(bound.legal_moves(&players[1]), vec![Card::new(Five, Coppe)]);
+        assert_eq!(
+            bound.play(&players[1], Card::new(Three, Spade)),
+            Err(GameError::MustFollowSuit)
+        );
+        assert_eq!(
+            free.play(&players[0], Card::new(Three, Spade)),
+            Err(GameError::NotYourTurn)
+        );
+        assert_eq!(
+            free.play(&players[1], Card::new(Three, Spade)),
+            Ok(Played::Trick {
+                winner: 0,
+                drawn: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn winner_draws_first_and_takes_the_last_trick() {
+        let players = players(2);
+        let hands = vec![vec![Card::new(Four, Coppe)], vec![Card::new(Five, Coppe)]];
+        let stock = vec![Card::new(Ace, Spade), Card::new(Two, Spade)];
+        let mut tricks: Tricks<Tressette> = dealt(Teams::Alternate, hands, stock);
+        tricks.play(&players[0], Card::new(Four, Coppe)).unwrap();
+        assert_eq!(
+            tricks.play(&players[1], Card::new(Five, Coppe)),
+            Ok(Played::Trick {
+                winner: 1,
+                drawn: vec![(1, Card::new(Two, Spade)), (0, Card::new(Ace, Spade))]
+            })
+        );
+        assert_eq!(tricks.next_player(), Some(1));
+        assert!(!tricks.is_over());
+        tricks.play(&players[1], Card::new(Two, Spade)).unwrap();
+        tricks.play(&players[0], Card::new(Ace, Spade)).unwrap();
+        assert!(tricks.is_over());
+        assert_eq!(tricks.next_player(), None);
+        assert_eq!(tricks.won_cards(1).len(), 4);
+        assert_eq!(tricks.last_trick(), Some(1));
+        assert!(tricks.legal_moves(&players[0]).is_empty());
+    }
+}
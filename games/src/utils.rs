@@ -1,62 +1,146 @@
+use super::i18n::{Language, Text};
 use super::primitives::*;
+use crate::text;
+use itertools::Itertools;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 pub fn random_deck(of_type: CardDeckType) -> Vec<Card> {
-    let mut rng = rand::thread_rng();
+    shuffled_deck(of_type, &mut rand::thread_rng())
+}
+
+/// A deck shuffled with the given random number generator
+pub fn shuffled_deck<R: Rng + ?Sized>(of_type: CardDeckType, rng: &mut R) -> Vec<Card> {
+    let mut deck = new_deck(of_type);
+    deck.shuffle(rng);
+    deck
+}
+
+/// All the cards of a deck, sorted by suit and rank
+pub fn new_deck(of_type: CardDeckType) -> Vec<Card> {
     match of_type {
-        CardDeckType::Briscola => {
-            let mut deck_raw: Vec<u8> = (0..40).collect();
-            deck_raw.shuffle(&mut rng);
-            let deck: Vec<Card> = deck_raw
-                .iter()
-                .map(|x| {
-                    let value: u8 = x % 10;
-                    // Ricordarsi di aggiungere 1
-                    let suit = match x - value {
-                        0 => CardSuit::Bastoni,
-                        10 => CardSuit::Coppe,
-                        20 => CardSuit::Denari,
-                        _ => CardSuit::Spade,
-                    };
-                    let c_type = match value {
-                        9 => CardType::King,
-                        8 => CardType::Queen,
-                        7 => CardType::Jack,
-                        _ => CardType::Numeric(value + 1),
-                    };
-                    (c_type, suit)
-                })
-                .collect();
-            deck
+        CardDeckType::Briscola => Suit::ITALIAN
+            .iter()
+            .cartesian_product(Rank::ALL.iter())
+            // Le carte italiane non hanno 8, 9 e 10
+            .filter(|x| !matches!(x.1, Rank::Eight | Rank::Nine | Rank::Ten))
+            .map(|x| Card::new(*x.1, *x.0))
+            .collect(),
+        CardDeckType::Poker => Suit::FRENCH
+            .iter()
+            .cartesian_product(Rank::ALL.iter())
+            .map(|x| Card::new(*x.1, *x.0))
+            .chain(vec![Card::JOKER; 2])
+            .collect(),
+    }
+}
+
+/// A deck that is always shuffled the same way for the same seed
+pub fn seeded_deck(of_type: CardDeckType, seed: u64) -> Vec<Card> {
+    shuffled_deck(of_type, &mut rand::rngs::StdRng::seed_from_u64(seed))
+}
+
+/// What a [`Shuffler`] has been used for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Shuffle {
+    /// A new deck of this type
+    Deck(CardDeckType),
+    /// Cards already in the game, which can't be shuffled again without them
+    Cards,
+    /// A game played inside this one, with what its shuffler has been used for
+    Split(Vec<Shuffle>),
+}
+
+/// Where the decks of a game come from.
+/// Every shuffle depends only on the seed and on how many shuffles came before it,
+/// so a game can be dealt again in the same way from its seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shuffler {
+    seed: u64,
+    shuffles: u64,
+    /// Decks to be used, in this order, before shuffling new ones
+    stacked: Vec<Vec<Card>>,
+    /// The shuffles made so far, to shuffle the same decks again from the seed
+    history: Vec<Shuffle>,
+}
+
+impl Shuffler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            shuffles: 0,
+            stacked: vec![],
+            history: vec![],
+        }
+    }
+    /// A shuffler with a seed chosen by the given random number generator
+    pub fn from_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::new(rng.gen())
+    }
+    /// A shuffler that gives these decks first, in the same order
+    /// [`random_deck`] would return them: most games deal from the end of the deck
+    pub fn stacked(decks: Vec<Vec<Card>>) -> Self {
+        Self {
+            stacked: decks,
+            ..Self::default()
         }
-        CardDeckType::Poker => {
-            let mut deck_raw: Vec<u8> = (0..54).collect();
-            deck_raw.shuffle(&mut rng);
-            deck_raw
-                .iter()
-                .map(|x| {
-                    if *x > 51 {
-                        (CardType::Jolly, CardSuit::Bastoni)
-                    } else {
-                        let value = *x as u8 % 13;
-                        let c_value = match value {
-                            12 => CardType::King,
-                            11 => CardType::Queen,
-                            10 => CardType::Jack,
-                            _ => CardType::Numeric(value),
-                        };
-                        let c_type = match (x - value) / 13 {
-                            0 => CardSuit::Bastoni,
-                            1 => CardSuit::Coppe,
-                            2 => CardSuit::Denari,
-                            _ => CardSuit::Spade,
-                        };
-                        (c_value, c_type)
-                    }
-                })
-                .collect::<Vec<Card>>()
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+    /// The shuffles made so far, in order
+    pub fn get_history(&self) -> &[Shuffle] {
+        &self.history
+    }
+    fn next_rng(&mut self) -> rand::rngs::StdRng {
+        self.shuffles += 1;
+        rand::rngs::StdRng::seed_from_u64(self.seed.wrapping_add(self.shuffles))
+    }
+    /// The next stacked deck, or a new shuffled one
+    pub fn deck(&mut self, of_type: CardDeckType) -> Vec<Card> {
+        self.history.push(Shuffle::Deck(of_type));
+        if self.stacked.is_empty() {
+            shuffled_deck(of_type, &mut self.next_rng())
+        } else {
+            self.stacked.remove(0)
+        }
+    }
+    /// Shuffles cards that are already in the game, like a discard pile turned into the stock
+    pub fn shuffle(&mut self, cards: &mut [Card]) {
+        self.history.push(Shuffle::Cards);
+        cards.shuffle(&mut self.next_rng());
+    }
+    /// A shuffler for a game played inside this one, like a hand of a match.
+    /// It gets the decks still stacked.
+    pub fn split(&mut self) -> Shuffler {
+        self.history.push(Shuffle::Split(vec![]));
+        Shuffler {
+            seed: self.next_rng().gen(),
+            shuffles: 0,
+            stacked: std::mem::take(&mut self.stacked),
+            history: vec![],
         }
     }
+    /// Records the shuffles made by the last shuffler [`split`](Self::split) from this one,
+    /// once the game it was given to is over
+    pub fn join(&mut self, inner: &Shuffler) {
+        if let Some(Shuffle::Split(history)) = self
+            .history
+            .iter_mut()
+            .rev()
+            .find(|x| matches!(x, Shuffle::Split(_)))
+        {
+            *history = inner.history.clone();
+        }
+    }
+}
+
+/// A shuffler with a random seed
+impl Default for Shuffler {
+    fn default() -> Self {
+        Self::from_rng(&mut rand::thread_rng())
+    }
 }
 
 pub fn zero() -> fraction::GenericFraction<u8> {
@@ -66,16 +150,105 @@ pub fn one() -> fraction::GenericFraction<u8> {
     fraction::GenericFraction::new(1u8, 1u8)
 }
 
-pub fn get_card_name(card: &Card) -> String {
-    let c_type = match card.0 {
-        CardType::Jack => "🚶‍♂️".to_owned(),
-        CardType::Queen => "🐴".to_owned(),
-        CardType::King => "🤴".to_owned(),
-        CardType::Jolly => "🃏".to_owned(),
-        CardType::Numeric(x) => match x {
-            1 => "Asso".to_owned(),
-            _ => format!("{}", x),
-        },
+/// The name of the card in the given language
+pub fn get_card_name(card: &Card, language: Language) -> String {
+    let french = Suit::FRENCH.contains(&card.suit);
+    let c_type = match card.rank {
+        Rank::Joker => return "🃏".to_owned(),
+        Rank::Ace => Text::new("Asso"),
+        Rank::Jack if french => Text::new("J"),
+        Rank::Queen if french => Text::new("Q"),
+        Rank::King if french => Text::new("K"),
+        Rank::Jack => Text::new("🚶‍♂️"),
+        Rank::Queen => Text::new("🐴"),
+        Rank::King => Text::new("🤴"),
+        x => text!("{}", x.number()),
     };
-    format!("{} di {}", c_type, String::from(&card.1))
+    text!("{} di {}", c_type, card.suit).translate(language)
+}
+
+/// The cards in `hand` that can be played in a trick opened with a card of suit `lead`:
+/// players must follow suit when they can
+pub fn follow_suit(hand: &[Card], lead: Option<&Suit>) -> Vec<Card> {
+    match lead {
+        Some(suit) if hand.iter().any(|x| &x.suit == suit) => {
+            hand.iter().filter(|x| &x.suit == suit).cloned().collect()
+        }
+        _ => hand.to_vec(),
+    }
+}
+
+/// Removes one copy of each of the cards from the hand.
+/// If any of them is missing the hand is left untouched and `false` is returned.
+pub fn remove_cards(hand: &mut Vec<Card>, cards: &[Card]) -> bool {
+    let mut remaining = hand.clone();
+    for card in cards {
+        match remaining.iter().position(|x| x == card) {
+            Some(i) => {
+                remaining.remove(i);
+            }
+            None => return false,
+        }
+    }
+    *hand = remaining;
+    true
+}
+
+/// Parses one-based card positions, making sure they're distinct and less than `hand_size`
+fn parse_indices(text: &str, hand_size: usize) -> Option<Vec<usize>> {
+    let indices = text
+        .split_whitespace()
+        .map(|x| x.parse::<usize>().ok().filter(|y| *y >= 1).map(|y| y - 1))
+        .collect::<Option<Vec<usize>>>()?;
+    if !indices.is_empty() && indices.iter().all(|x| *x < hand_size) && indices.iter().all_unique()
+    {
+        Some(indices)
+    } else {
+        None
+    }
+}
+
+/// Parses the text commands of games with multi-card moves.
+/// Cards are given as their one-based position in `hand`:
+/// «pesca», «raccogli», «cala 1 2 3 / 4 5 6», «attacca 1 4 5» (to the first meld) and «scarta 3».
+/// Returns `None` if the message isn't a command.
+pub fn parse_action(message: &str, hand: &[Card]) -> Option<Result<Action, GameError>> {
+    let lowercase = message.to_lowercase();
+    let mut words = lowercase.trim().splitn(2, ' ');
+    let command = words.next().unwrap_or("");
+    let arguments = words.next().unwrap_or("");
+    let cards = |indices: Vec<usize>| indices.iter().map(|x| hand[*x]).collect();
+    Some(match command {
+        "pesca" => Ok(Action::Draw),
+        "raccogli" => Ok(Action::PickUp),
+        "cala" => match arguments
+            .split(['/', ','])
+            .map(|x| parse_indices(x, hand.len()))
+            .collect::<Option<Vec<Vec<usize>>>>()
+        {
+            Some(x) if x.iter().flatten().all_unique() => {
+                Ok(Action::Meld(x.into_iter().map(cards).collect()))
+            }
+            _ => Err(GameError::NoCardsSelected),
+        },
+        "attacca" => {
+            let mut words = arguments.split_whitespace();
+            match words
+                .next()
+                .and_then(|x| x.parse::<usize>().ok())
+                .filter(|x| *x >= 1)
+            {
+                Some(meld) => match parse_indices(&words.join(" "), hand.len()) {
+                    Some(x) => Ok(Action::Attach(meld - 1, cards(x))),
+                    None => Err(GameError::NoCardsSelected),
+                },
+                None => Err(GameError::NoSuchMeld),
+            }
+        }
+        "scarta" => match parse_indices(arguments, hand.len()) {
+            Some(x) if x.len() == 1 => Ok(Action::PlayCard(hand[x[0]])),
+            _ => Err(GameError::NoCardsSelected),
+        },
+        _ => return None,
+    })
 }
@@ -0,0 +1,109 @@
+//! What a single player can see of a game, with the information hidden to them removed.
+//! Views can be used by computer players, spectators and frontends other than the bot.
+use crate::primitives::*;
+use serde::{Deserialize, Serialize};
+
+/// The state of a game as seen by one player
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerView {
+    /// The name of the game
+    pub game: String,
+    /// Who's looking at the game: if they're not playing, they only see what's public
+    pub player: Option<Player>,
+    /// The cards in the hand of the player
+    pub hand: Vec<Card>,
+    /// The cards on the table, with who played them when it matters
+    pub table: Vec<(Option<Player>, Card)>,
+    /// Melds on the table in rummy games, grouped by the team that owns them
+    pub melds: Vec<(Vec<Player>, Vec<Vec<Card>>)>,
+    /// The suit of briscola, once everybody knows it
+    pub briscola: Option<Suit>,
+    /// The card turned face up to show the briscola, while it's still there
+    pub trump_card: Option<Card>,
+    /// Cards of other players that everybody can see
+    pub face_up: Vec<(Player, Vec<Card>)>,
+    /// How many cards each team (or player) has taken
+    pub captured: Vec<(Vec<Player>, usize)>,
+    /// How many cards each player has in hand
+    pub hand_sizes: Vec<(Player, usize)>,
+    /// Cards left in the stock
+    pub stock: usize,
+    /// The scores as far as everybody knows them
+    pub scores: Vec<(Vec<Player>, String)>,
+    pub next_player: Option<Player>,
+    /// The cards the player can play right now
+    pub legal_moves: Vec<Card>,
+}
+
+impl PlayerView {
+    /// The view with the information every game gives through [`Game`],
+    /// games fill in the rest
+    pub fn new(game: &dyn Game, player: &Player) -> Self {
+        let playing = game.get_players().contains(player);
+        Self {
+            game: game.get_name().to_owned(),
+            player: Some(player.clone()).filter(|_| playing),
+            scores: game
+                .get_scores()
+                .into_iter()
+                .map(|x| (x.0, x.1.to_string()))
+                .collect(),
+            next_player: game.get_next_player(),
+            legal_moves: game.legal_moves(player),
+            ..Default::default()
+        }
+    }
+}
+
+/// How many cards each player has, given the hands in the same order as the players
+pub(crate) fn hand_sizes(players: &[Player], hands: &[Vec<Card>]) -> Vec<(Player, usize)> {
+    players
+        .iter()
+        .cloned()
+        .zip(hands.iter().map(|x| x.len()))
+        .collect()
+}
+
+/// The cards on the table of trick-taking games, with who played them
+pub(crate) fn trick(table: &[(Player, Card)]) -> Vec<(Option<Player>, Card)> {
+    table.iter().map(|x| (Some(x.0.clone()), x.1)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::all_games;
+    use crate::testing;
+
+    #[test]
+    fn players_only_see_their_own_hand() {
+        for mut game in all_games() {
+            game.init();
+            let count = game.get_num_players().end as i64;
+            let players = testing::add_players(game.as_mut(), count);
+            game.start();
+            let views: Vec<PlayerView> = players.iter().map(|x| game.view_for(x)).collect();
+            for (player, view) in players.iter().zip(views.iter()) {
+                let name = game.get_name();
+                assert_eq!(view.player.as_ref(), Some(player), "{}", name);
+                let size = view.hand_sizes.iter().find(|x| &x.0 == player).unwrap().1;
+                assert_eq!(view.hand.len(), size, "{}", name);
+                assert!(
+                    view.legal_moves.iter().all(|x| view.hand.contains(x)),
+                    "{}",
+                    name
+                );
+                let json = serde_json::to_string(view).unwrap();
+                assert_eq!(&serde_json::from_str::<PlayerView>(&json).unwrap(), view);
+            }
+            // Chi guarda senza giocare non vede nessuna mano
+            let spectator = game.view_for(&Player {
+                id: -1,
+                name: "spettatore".to_owned(),
+            });
+            assert_eq!(spectator.player, None);
+            assert!(spectator.hand.is_empty());
+            assert_eq!(spectator.hand_sizes, views[0].hand_sizes);
+        }
+    }
+}
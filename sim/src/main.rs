@@ -0,0 +1,505 @@
+//! Plays the computer players against each other over many seeded games,
+//! to see whether a change to a strategy makes it stronger and to check that
+//! the rules always hand out all the points of the deck.
+//!
+//! The seats are given to the strategies in turn, and they rotate at every game,
+//! so that everybody plays from every seat. In four players play in pairs:
+//! there has to be a strategy for each team, used by both partners.
+use cardgames::ai::{Budget, Heuristic, Ismcts, Level, Strategy};
+use cardgames::games::{beccaccino::Beccaccino, briscola::Briscola};
+use cardgames::i18n::Language;
+use cardgames::primitives::*;
+use cardgames::utils::Shuffler;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::cell::RefCell;
+
+const USAGE: &str = "Usage: cardgames-sim [options] STRATEGY STRATEGY...
+
+Options:
+    --game NAME      briscola or beccaccino (default briscola)
+    --players N      how many players sit at the table (default 2 for Briscola, 4 for Beccaccino),
+                     in four they play in pairs and there is a strategy for each pair
+    --games N        how many games are played (default 1000)
+    --seed N         the seed of the first game, the next ones use the following seeds (default 0)
+    --threads N      how many games are played at the same time (default all the cores)
+
+Strategies:
+    random           plays a random legal card
+    heuristic        the rule-based player
+    easy, medium, hard
+                     the ISMCTS player at the levels offered by the bot
+    ismcts:N         the ISMCTS player with N iterations per move";
+
+/// Games that are cut short after this many moves are reported as broken
+const MAX_MOVES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GameKind {
+    Briscola,
+    Beccaccino,
+}
+
+impl GameKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "briscola" => Some(GameKind::Briscola),
+            "beccaccino" => Some(GameKind::Beccaccino),
+            _ => None,
+        }
+    }
+    fn new_game(&self) -> Box<dyn Game> {
+        match self {
+            GameKind::Briscola => Box::new(Briscola::default()),
+            GameKind::Beccaccino => Box::new(Beccaccino::default()),
+        }
+    }
+    fn default_players(&self) -> usize {
+        match self {
+            GameKind::Briscola => 2,
+            GameKind::Beccaccino => 4,
+        }
+    }
+    /// How many teams the players form: in four they play in pairs
+    fn teams(&self, players: usize) -> usize {
+        if players == 4 {
+            2
+        } else {
+            players
+        }
+    }
+    /// The whole points handed out in every game
+    fn total_points(&self) -> u32 {
+        match self {
+            GameKind::Briscola => 120,
+            // 10 points and two thirds in the cards, which are lost, and one for the last trick
+            GameKind::Beccaccino => 11,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StrategyKind {
+    Random,
+    Heuristic,
+    Ismcts(Budget),
+}
+
+impl StrategyKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "random" => Some(StrategyKind::Random),
+            "heuristic" => Some(StrategyKind::Heuristic),
+            _ => match name.strip_prefix("ismcts:") {
+                Some(x) => x
+                    .parse()
+                    .ok()
+                    .map(|x| StrategyKind::Ismcts(Budget::Iterations(x))),
+                None => Level::from_code(name).map(|x| StrategyKind::Ismcts(x.budget())),
+            },
+        }
+    }
+    fn build(&self, game: GameKind, seed: u64) -> Box<dyn Strategy> {
+        match (self, game) {
+            (StrategyKind::Random, _) => {
+                Box::new(RandomPlayer(RefCell::new(StdRng::seed_from_u64(seed))))
+            }
+            (StrategyKind::Heuristic, GameKind::Briscola) => {
+                Box::new(Heuristic::<Briscola>::default())
+            }
+            (StrategyKind::Heuristic, GameKind::Beccaccino) => {
+                Box::new(Heuristic::<Beccaccino>::default())
+            }
+            (StrategyKind::Ismcts(budget), _) => Box::new(Ismcts::new(*budget).with_seed(seed)),
+        }
+    }
+}
+
+/// Plays a random legal card, the baseline every strategy should beat
+struct RandomPlayer(RefCell<StdRng>);
+
+impl Strategy for RandomPlayer {
+    fn choose_card(&self, game: &dyn Game, player: &Player) -> Option<Card> {
+        game.legal_moves(player)
+            .choose(&mut *self.0.borrow_mut())
+            .copied()
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    game: GameKind,
+    players: usize,
+    games: u64,
+    seed: u64,
+    threads: usize,
+    strategies: Vec<(String, StrategyKind)>,
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    value
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", option))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut game = GameKind::Briscola;
+    let mut players = None;
+    let mut games = 1000;
+    let mut seed = 0;
+    let mut threads = std::thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1);
+    let mut strategies = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--game" => {
+                let name = args.next().unwrap_or_default();
+                game = GameKind::from_name(&name)
+                    .ok_or_else(|| format!("Unknown game \"{}\"", name))?;
+            }
+            "--players" => players = Some(parse_number(&arg, args.next())?),
+            "--games" => games = parse_number(&arg, args.next())?,
+            "--seed" => seed = parse_number(&arg, args.next())?,
+            "--threads" => threads = parse_number::<usize>(&arg, args.next())?.max(1),
+            _ => match StrategyKind::from_name(&arg) {
+                Some(x) => strategies.push((arg, x)),
+                None => return Err(format!("Unknown strategy \"{}\"", arg)),
+            },
+        }
+    }
+    let players = players.unwrap_or_else(|| game.default_players());
+    let allowed = game.new_game().get_num_players();
+    // The games take the range as inclusive
+    if players < allowed.start as usize || players > allowed.end as usize {
+        return Err(format!("{:?} can't be played by {} players", game, players));
+    }
+    let teams = game.teams(players);
+    if teams < players && strategies.len() != teams {
+        return Err(format!(
+            "{} strategies are needed, one for each team",
+            teams
+        ));
+    }
+    if strategies.len() < 2 || strategies.len() > players {
+        return Err(format!("Between 2 and {} strategies are needed", players));
+    }
+    Ok(Options {
+        game,
+        players,
+        games,
+        seed,
+        threads,
+        strategies,
+    })
+}
+
+/// How a strategy did in one game: the whole points of its team and whether it won (1), drew (0.5) or lost (0)
+type Outcome = (usize, u32, f64);
+
+fn whole_points(score: fraction::GenericFraction<u8>) -> u32 {
+    match (score.numer(), score.denom()) {
+        (Some(n), Some(d)) => (*n / *d) as u32,
+        _ => 0,
+    }
+}
+
+/// Plays the game with the given number, returning what every strategy got
+fn play_game(options: &Options, number: u64) -> Result<Vec<Outcome>, String> {
+    let seed = options.seed.wrapping_add(number);
+    let mut game = options.game.new_game();
+    game.set_shuffler(Shuffler::new(seed));
+    game.init();
+    for id in 0..options.players {
+        game.add_player(Player {
+            id: id as i64,
+            name: format!("{}", id),
+        })
+        .map_err(|x| format!("seed {}: {}", seed, x))?;
+    }
+    if let GameStatus::InvalidMove(x) = game.start() {
+        return Err(format!("seed {}: {}", seed, x));
+    }
+    let seats: Vec<usize> = (0..options.players)
+        .map(|x| (x + number as usize) % options.strategies.len())
+        .collect();
+    let strategies: Vec<Box<dyn Strategy>> = seats
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            options.strategies[*x]
+                .1
+                .build(options.game, seed ^ (i as u64) << 32)
+        })
+        .collect();
+    let mut ended = false;
+    for _ in 0..MAX_MOVES {
+        let player = match game.get_next_player() {
+            Some(x) => x,
+            None => break,
+        };
+        let card = strategies[player.id as usize]
+            .choose_card(game.as_ref(), &player)
+            .ok_or_else(|| format!("seed {}: {} can't play any card", seed, player.name))?;
+        let status = game.handle_move(&player, card);
+        if let Some(GameStatus::InvalidMove(x)) = status.first() {
+            return Err(format!(
+                "seed {}: {} can't play {}: {}",
+                seed,
+                player.name,
+                cardgames::utils::get_card_name(&card, Language::English),
+                x
+            ));
+        }
+        if status.iter().any(|x| matches!(x, GameStatus::GameEnded)) {
+            ended = true;
+            break;
+        }
+    }
+    if !ended {
+        return Err(format!("seed {}: the game didn't end", seed));
+    }
+    let scores: Vec<(Vec<usize>, u32)> = game
+        .get_scores()
+        .into_iter()
+        .map(|x| {
+            let mut team: Vec<usize> = x.0.iter().map(|y| seats[y.id as usize]).collect();
+            team.sort_unstable();
+            team.dedup();
+            (team, whole_points(x.1))
+        })
+        .collect();
+    let total: u32 = scores.iter().map(|x| x.1).sum();
+    if total != options.game.total_points() {
+        return Err(format!(
+            "seed {}: {} points were handed out instead of {}",
+            seed,
+            total,
+            options.game.total_points()
+        ));
+    }
+    let best = scores.iter().map(|x| x.1).max().unwrap_or(0);
+    let winners = scores.iter().filter(|x| x.1 == best).count();
+    Ok(scores
+        .iter()
+        .flat_map(|(team, points)| {
+            let result = if *points < best {
+                0.0
+            } else if winners > 1 {
+                0.5
+            } else {
+                1.0
+            };
+            team.iter().map(move |x| (*x, *points, result))
+        })
+        .collect())
+}
+
+#[derive(Default)]
+struct Stats {
+    games: u64,
+    wins: u64,
+    draws: u64,
+    points: f64,
+    squared_points: f64,
+}
+
+impl Stats {
+    fn add(&mut self, points: u32, result: f64) {
+        self.games += 1;
+        if result == 1.0 {
+            self.wins += 1;
+        } else if result == 0.5 {
+            self.draws += 1;
+        }
+        self.points += points as f64;
+        self.squared_points += (points as f64).powi(2);
+    }
+    /// The share of games won, with the draws counted as half a win, and its 95% Wilson score interval
+    fn win_rate(&self) -> (f64, f64, f64) {
+        let n = self.games as f64;
+        if n == 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let p = (self.wins as f64 + self.draws as f64 / 2.0) / n;
+        let z: f64 = 1.96;
+        let denominator = 1.0 + z * z / n;
+        let centre = (p + z * z / (2.0 * n)) / denominator;
+        let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+        (p, centre - margin, centre + margin)
+    }
+    /// The average points with the margin of their 95% confidence interval
+    fn average_points(&self) -> (f64, f64) {
+        let n = self.games as f64;
+        if n == 0.0 {
+            return (0.0, 0.0);
+        }
+        let mean = self.points / n;
+        let variance = if n > 1.0 {
+            (self.squared_points - n * mean * mean).max(0.0) / (n - 1.0)
+        } else {
+            0.0
+        };
+        (mean, 1.96 * (variance / n).sqrt())
+    }
+}
+
+/// Plays all the games, returning the stats of every strategy and what went wrong
+fn simulate(options: &Options) -> (Vec<Stats>, Vec<String>) {
+    let results: Vec<Result<Vec<Outcome>, String>> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..options.threads as u64)
+            .map(|thread| {
+                scope.spawn(move || {
+                    (thread..options.games)
+                        .step_by(options.threads)
+                        .map(|x| play_game(options, x))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .flat_map(|x| x.join().unwrap())
+            .collect()
+    });
+    let mut stats: Vec<Stats> = options
+        .strategies
+        .iter()
+        .map(|_| Stats::default())
+        .collect();
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(x) => {
+                for (strategy, points, result) in x {
+                    stats[strategy].add(points, result);
+                }
+            }
+            Err(x) => errors.push(x),
+        }
+    }
+    (stats, errors)
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("{}\n\n{}", x, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let start = std::time::Instant::now();
+    let (stats, errors) = simulate(&options);
+    println!(
+        "{} games of {:?} with {} players, seeds from {}, in {:.1}s",
+        options.games,
+        options.game,
+        options.players,
+        options.seed,
+        start.elapsed().as_secs_f64()
+    );
+    println!(
+        "{:<16} {:>7} {:>7} {:>7} {:>26} {:>22}",
+        "strategy", "games", "wins", "draws", "win rate (95% CI)", "avg points (95% CI)"
+    );
+    for ((name, _), stats) in options.strategies.iter().zip(stats.iter()) {
+        let (rate, low, high) = stats.win_rate();
+        let (points, margin) = stats.average_points();
+        println!(
+            "{:<16} {:>7} {:>7} {:>7} {:>26} {:>22}",
+            name,
+            stats.games,
+            stats.wins,
+            stats.draws,
+            format!(
+                "{:.1}% ({:.1}%-{:.1}%)",
+                rate * 100.0,
+                low * 100.0,
+                high * 100.0
+            ),
+            format!("{:.2} ± {:.2}", points, margin)
+        );
+    }
+    if !errors.is_empty() {
+        eprintln!("{} games broke the rules:", errors.len());
+        for error in &errors {
+            eprintln!("{}", error);
+        }
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &str) -> Options {
+        parse_args(args.split_whitespace().map(|x| x.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn parses_the_options() {
+        let options = options("--game beccaccino --games 10 --seed 5 hard ismcts:20");
+        assert_eq!(options.game, GameKind::Beccaccino);
+        assert_eq!(options.players, 4);
+        assert_eq!(options.games, 10);
+        assert_eq!(options.seed, 5);
+        assert_eq!(
+            options.strategies.iter().map(|x| x.1).collect::<Vec<_>>(),
+            vec![
+                StrategyKind::Ismcts(Level::Hard.budget()),
+                StrategyKind::Ismcts(Budget::Iterations(20))
+            ]
+        );
+        assert!(parse_args(vec!["heuristic".to_owned()].into_iter()).is_err());
+        // In four the players are in pairs, with a strategy for each pair
+        assert!(parse_args(
+            "--game beccaccino heuristic hard random"
+                .split_whitespace()
+                .map(|x| x.to_owned())
+        )
+        .is_err());
+        assert!(parse_args(
+            "--players 5 random random"
+                .split_whitespace()
+                .map(|x| x.to_owned())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn hands_out_all_the_points() {
+        for args in [
+            "--games 20 --threads 2 random heuristic",
+            "--games 20 --threads 2 --players 3 heuristic random random",
+            "--games 20 --threads 2 --players 4 heuristic random",
+            "--games 20 --threads 2 --game beccaccino random heuristic",
+        ] {
+            let options = options(args);
+            let (stats, errors) = simulate(&options);
+            assert_eq!(errors, Vec::<String>::new());
+            for x in stats {
+                assert_eq!(x.games, 20);
+            }
+        }
+    }
+
+    #[test]
+    fn draws_count_half() {
+        let mut stats = Stats::default();
+        stats.add(60, 0.5);
+        stats.add(70, 1.0);
+        stats.add(50, 0.0);
+        stats.add(60, 0.5);
+        assert_eq!(stats.win_rate().0, 0.5);
+    }
+
+    #[test]
+    fn same_seeds_same_results() {
+        let options = options("--games 10 --threads 3 --game beccaccino random ismcts:5");
+        let points = |x: Vec<Stats>| x.iter().map(|y| y.points).collect::<Vec<_>>();
+        assert_eq!(points(simulate(&options).0), points(simulate(&options).0));
+    }
+}